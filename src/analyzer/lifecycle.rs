@@ -1,33 +1,38 @@
-use crate::model::Offer;
 use crate::model::OfferLifecycle;
+use crate::model::PriceObservation;
 use std::collections::HashMap;
 
-/// Builds lifecycle data from a list of offers.
-/// Groups offers by their id and tracks price changes along with the earliest and latest timestamps.
-pub async fn build_lifecycle_data(offers: &[Offer]) -> Vec<OfferLifecycle> {
+/// Builds lifecycle data from recorded price observations.
+/// Groups observations by offer id and tracks price changes along with the earliest and latest timestamps,
+/// so the metrics span every scrape cycle the offer was seen in.
+pub async fn build_lifecycle_data(history: &[PriceObservation]) -> Vec<OfferLifecycle> {
+    // Price changes are only meaningful in chronological order.
+    let mut observations: Vec<&PriceObservation> = history.iter().collect();
+    observations.sort_by_key(|o| o.observed_at);
+
     let mut grouped: HashMap<String, OfferLifecycle> = HashMap::new();
 
-    for offer in offers {
+    for observation in observations {
         // If an offer with the same id hasn't been seen yet, create a new OfferLifecycle.
-        let entry = grouped.entry(offer.id.clone()).or_insert_with(|| OfferLifecycle {
-            price: offer.price,
-            first_seen: offer.fetched_at,
-            last_seen: offer.fetched_at,
+        let entry = grouped.entry(observation.offer_id.clone()).or_insert_with(|| OfferLifecycle {
+            price: observation.price,
+            first_seen: observation.observed_at,
+            last_seen: observation.observed_at,
             price_changes: 0,
         });
 
         // If the price has changed (accounting for floating point precision), record the change.
-        if (offer.price - entry.price).abs() > f64::EPSILON {
+        if (observation.price - entry.price).abs() > f64::EPSILON {
             entry.price_changes += 1; 
-            entry.price = offer.price;
+            entry.price = observation.price;
         }
 
         // Update the first seen and last seen timestamps.
-        if offer.fetched_at < entry.first_seen {
-            entry.first_seen = offer.fetched_at;
+        if observation.observed_at < entry.first_seen {
+            entry.first_seen = observation.observed_at;
         }
-        if offer.fetched_at > entry.last_seen {
-            entry.last_seen = offer.fetched_at;
+        if observation.observed_at > entry.last_seen {
+            entry.last_seen = observation.observed_at;
         }
     }
 
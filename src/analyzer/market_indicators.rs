@@ -11,15 +11,29 @@ pub struct PriceRange(pub u32, pub u32);
 pub struct MarketAnalyzer;
 
 impl MarketAnalyzer {
-    /// Default step size for the price range (50 units).
+    /// Step size used when no average price is known (50 units).
     const DEFAULT_STEP: u32 = 50;
+    /// Number of price ranges that fit below a model's average price.
+    const RANGES_PER_AVERAGE: f64 = 20.0;
 
-    /// Calculates the average lifespan (disappearance speed) of offers for each price range.
-    pub fn disappearance_speed(offers: &[OfferLifecycle]) -> HashMap<PriceRange, Duration> {
+    /// Returns the price range step for a model with the given average price: a twentieth of it,
+    /// so a €30 item is bucketed in €2 steps and a €3000 one in €150 steps (at least 1).
+    /// Falls back to the default €50 step when the average is not a positive, finite number.
+    pub fn step_for_average(avg_price: f64) -> u32 {
+        if !(avg_price > 0.0 && avg_price.is_finite()) {
+            return Self::DEFAULT_STEP;
+        }
+        ((avg_price / Self::RANGES_PER_AVERAGE).round() as u32).max(1)
+    }
+
+    /// Calculates the average lifespan (disappearance speed) of offers for each price range of `step` size.
+    /// Offers observed only once (first_seen == last_seen) have no measurable lifespan and are
+    /// excluded, so ranges containing only such offers are omitted from the result.
+    pub fn disappearance_speed(offers: &[OfferLifecycle], step: u32) -> HashMap<PriceRange, Duration> {
         let mut map: HashMap<PriceRange, Vec<Duration>> = HashMap::new();
 
-        for offer in offers {
-            let range = Self::get_price_range(offer.price);
+        for offer in offers.iter().filter(|o| o.last_seen > o.first_seen) {
+            let range = Self::get_price_range(offer.price, step);
             let lifespan = offer.last_seen - offer.first_seen;
             map.entry(range).or_default().push(lifespan);
         }
@@ -71,15 +85,266 @@ impl MarketAnalyzer {
         100.0 - (100.0 / (1.0 + rs))
     }
 
-    /// Returns the price range for a given price using the default step.
-    fn get_price_range(price: f64) -> PriceRange {
-        Self::get_price_range_with_step(price, Self::DEFAULT_STEP)
+    /// Calculates the simple moving average over windows of `period` values.
+    /// Returns an empty vector if `period` is zero or there are fewer than `period` values.
+    pub fn moving_average(data: &[f64], period: usize) -> Vec<f64> {
+        if period == 0 || data.len() < period {
+            return Vec::new();
+        }
+
+        data.windows(period)
+            .map(|window| window.iter().sum::<f64>() / period as f64)
+            .collect()
     }
 
-    /// Returns the price range for a given price and step.
-    pub fn get_price_range_with_step(price: f64, step: u32) -> PriceRange {
+    /// Calculates Bollinger Bands as (lower, middle, upper) for every window of `period` values:
+    /// the middle band is the simple moving average, the outer bands are `num_std` population
+    /// standard deviations away from it. Returns an empty vector for insufficient data.
+    pub fn bollinger_bands(data: &[f64], period: usize, num_std: f64) -> Vec<(f64, f64, f64)> {
+        if period == 0 || data.len() < period {
+            return Vec::new();
+        }
+
+        let averages = Self::moving_average(data, period);
+        data.windows(period)
+            .zip(averages)
+            .map(|(window, mean)| {
+                let variance = window.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / period as f64;
+                let band = num_std * variance.sqrt();
+                (mean - band, mean, mean + band)
+            })
+            .collect()
+    }
+
+    /// Calculates the exponential moving average with smoothing factor 2 / (period + 1).
+    /// The first value is seeded with the simple average of the first `period` values, so the result
+    /// has `data.len() - period + 1` values. Returns an empty vector for insufficient data.
+    pub fn exponential_moving_average(data: &[f64], period: usize) -> Vec<f64> {
+        if period == 0 || data.len() < period {
+            return Vec::new();
+        }
+
+        let alpha = 2.0 / (period as f64 + 1.0);
+        let seed = data[..period].iter().sum::<f64>() / period as f64;
+        let mut result = Vec::with_capacity(data.len() - period + 1);
+        result.push(seed);
+
+        for &value in &data[period..] {
+            let prev = *result.last().unwrap();
+            result.push(alpha * value + (1.0 - alpha) * prev);
+        }
+        result
+    }
+
+    /// Calculates the MACD line (EMA12 - EMA26) and its signal line (EMA9 of the MACD line).
+    /// The MACD line starts at the 26th value; the signal line starts 8 values later.
+    /// Returns empty vectors when there is not enough data for either line.
+    pub fn macd(data: &[f64]) -> (Vec<f64>, Vec<f64>) {
+        const FAST: usize = 12;
+        const SLOW: usize = 26;
+        const SIGNAL: usize = 9;
+
+        let fast = Self::exponential_moving_average(data, FAST);
+        let slow = Self::exponential_moving_average(data, SLOW);
+        if slow.is_empty() {
+            return (Vec::new(), Vec::new());
+        }
+
+        // Align the fast EMA with the slow one (the fast series starts SLOW - FAST values earlier).
+        let offset = SLOW - FAST;
+        let macd_line: Vec<f64> = slow
+            .iter()
+            .enumerate()
+            .map(|(i, slow_value)| fast[i + offset] - slow_value)
+            .collect();
+        let signal_line = Self::exponential_moving_average(&macd_line, SIGNAL);
+        (macd_line, signal_line)
+    }
+
+    /// Calculates the price standard deviation of the given prices within each price range of `step` size.
+    pub fn range_volatility(prices: &[f64], step: u32) -> HashMap<PriceRange, f64> {
+        let mut map: HashMap<PriceRange, Vec<f64>> = HashMap::new();
+        for &price in prices {
+            map.entry(Self::get_price_range(price, step)).or_default().push(price);
+        }
+
+        map.into_iter()
+            .map(|(range, values)| {
+                let count = values.len() as f64;
+                let mean = values.iter().sum::<f64>() / count;
+                let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / count;
+                (range, variance.sqrt())
+            })
+            .collect()
+    }
+
+    /// Returns the price range for a given price and step (a zero step is treated as 1).
+    pub fn get_price_range(price: f64, step: u32) -> PriceRange {
+        let step = step.max(1);
         let price_int = price.round() as u32;
         let lower = price_int / step * step;
         PriceRange(lower, lower + step)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn lifecycle(price: f64, lifespan_hours: i64) -> OfferLifecycle {
+        let first_seen = Utc.with_ymd_and_hms(2025, 4, 1, 12, 0, 0).unwrap();
+        OfferLifecycle {
+            price,
+            first_seen,
+            last_seen: first_seen + Duration::hours(lifespan_hours),
+            price_changes: 0,
+        }
+    }
+
+    #[test]
+    fn disappearance_speed_of_no_offers_is_empty() {
+        assert!(MarketAnalyzer::disappearance_speed(&[], 50).is_empty());
+    }
+
+    #[test]
+    fn disappearance_speed_skips_single_observations() {
+        let speeds = MarketAnalyzer::disappearance_speed(&[lifecycle(120.0, 0)], 50);
+        assert!(speeds.is_empty());
+
+        // A seen-once offer doesn't drag down the average of its range
+        let speeds = MarketAnalyzer::disappearance_speed(&[lifecycle(120.0, 0), lifecycle(130.0, 10)], 50);
+        assert_eq!(speeds.len(), 1);
+        assert_eq!(speeds[&PriceRange(100, 150)], Duration::hours(10));
+    }
+
+    #[test]
+    fn disappearance_speed_averages_per_range() {
+        let offers = [lifecycle(110.0, 4), lifecycle(140.0, 8), lifecycle(260.0, 1)];
+        let speeds = MarketAnalyzer::disappearance_speed(&offers, 50);
+        assert_eq!(speeds[&PriceRange(100, 150)], Duration::hours(6));
+        assert_eq!(speeds[&PriceRange(250, 300)], Duration::hours(1));
+    }
+
+    #[test]
+    fn rsi_of_empty_and_single_series_is_zero() {
+        assert_eq!(MarketAnalyzer::compute_rsi(&[]), 0.0);
+        assert_eq!(MarketAnalyzer::compute_rsi(&[100.0]), 0.0);
+        assert_eq!(MarketAnalyzer::compute_rsi(&[100.0, 100.0]), 50.0);
+    }
+
+    fn assert_close(actual: &[f64], expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len(), "{:?} vs {:?}", actual, expected);
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-9, "{:?} vs {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn ema_is_seeded_with_the_simple_average() {
+        // period 3: alpha 0.5, seed (1 + 2 + 3) / 3
+        let ema = MarketAnalyzer::exponential_moving_average(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0], 3);
+        assert_close(&ema, &[2.0, 3.0, 4.0, 5.0]);
+
+        let ema = MarketAnalyzer::exponential_moving_average(&[10.0, 20.0, 30.0, 20.0, 10.0], 3);
+        assert_close(&ema, &[20.0, 20.0, 15.0]);
+
+        // period 4: alpha 0.4, seed 5, then 0.4 * 10 + 0.6 * 5
+        let ema = MarketAnalyzer::exponential_moving_average(&[2.0, 4.0, 6.0, 8.0, 10.0], 4);
+        assert_close(&ema, &[5.0, 7.0]);
+    }
+
+    #[test]
+    fn ema_of_insufficient_data_is_empty() {
+        assert!(MarketAnalyzer::exponential_moving_average(&[], 3).is_empty());
+        assert!(MarketAnalyzer::exponential_moving_average(&[1.0, 2.0], 3).is_empty());
+        assert!(MarketAnalyzer::exponential_moving_average(&[1.0, 2.0], 0).is_empty());
+    }
+
+    #[test]
+    fn macd_of_a_falling_line() {
+        // On a straight line an SMA-seeded EMA lags by exactly (period - 1) / 2 steps, so
+        // EMA12 - EMA26 = (12.5 - 5.5) * slope = 7 * -2 and the signal line equals it.
+        let prices: Vec<f64> = (0..40).map(|i| 500.0 - 2.0 * i as f64).collect();
+        let (macd, signal) = MarketAnalyzer::macd(&prices);
+        assert_close(&macd, &[-14.0; 15]);
+        assert_close(&signal, &[-14.0; 7]);
+    }
+
+    #[test]
+    fn macd_turns_below_its_signal_when_prices_start_falling() {
+        let mut prices: Vec<f64> = (0..40).map(|i| 300.0 + i as f64).collect();
+        prices.extend((1..=5).map(|i| 339.0 - 10.0 * i as f64));
+        let (macd, signal) = MarketAnalyzer::macd(&prices);
+        assert!((macd[14] - 7.0).abs() < 1e-9);
+        assert!(macd.last().unwrap() < signal.last().unwrap());
+    }
+
+    #[test]
+    fn macd_needs_26_values_and_the_signal_34() {
+        let prices = |n: usize| vec![100.0; n];
+        assert_eq!(MarketAnalyzer::macd(&prices(25)), (vec![], vec![]));
+
+        let (macd, signal) = MarketAnalyzer::macd(&prices(26));
+        assert_close(&macd, &[0.0]);
+        assert!(signal.is_empty());
+
+        let (macd, signal) = MarketAnalyzer::macd(&prices(34));
+        assert_eq!((macd.len(), signal.len()), (9, 1));
+    }
+
+    #[test]
+    fn bollinger_bands_of_a_constant_series_collapse_to_the_mean() {
+        let bands = MarketAnalyzer::bollinger_bands(&[250.0; 6], 4, 2.0);
+        assert_eq!(bands, vec![(250.0, 250.0, 250.0); 3]);
+    }
+
+    #[test]
+    fn bollinger_bands_of_a_varying_series() {
+        // mean 5, population standard deviation 2
+        let bands = MarketAnalyzer::bollinger_bands(&[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0], 8, 2.0);
+        assert_eq!(bands, vec![(1.0, 5.0, 9.0)]);
+
+        // windows [1, 3] and [3, 5]: standard deviation 1 around 2 and 4
+        let bands = MarketAnalyzer::bollinger_bands(&[1.0, 3.0, 5.0], 2, 1.5);
+        assert_eq!(bands, vec![(0.5, 2.0, 3.5), (2.5, 4.0, 5.5)]);
+    }
+
+    #[test]
+    fn bollinger_bands_of_insufficient_data_are_empty() {
+        assert!(MarketAnalyzer::bollinger_bands(&[1.0, 2.0], 3, 2.0).is_empty());
+        assert!(MarketAnalyzer::bollinger_bands(&[1.0, 2.0], 0, 2.0).is_empty());
+    }
+
+    #[test]
+    fn step_scales_with_the_average_price() {
+        assert_eq!(MarketAnalyzer::step_for_average(30.0), 2);
+        assert_eq!(MarketAnalyzer::step_for_average(1000.0), 50);
+        assert_eq!(MarketAnalyzer::step_for_average(3000.0), 150);
+        assert_eq!(MarketAnalyzer::step_for_average(5.0), 1);
+        for broken in [0.0, -10.0, f64::NAN, f64::INFINITY] {
+            assert_eq!(MarketAnalyzer::step_for_average(broken), 50, "{}", broken);
+        }
+    }
+
+    #[test]
+    fn bucketing_adapts_to_the_model_scale() {
+        // A €30 item: 25 € and 29 € land in different €2 ranges instead of one €50 bucket
+        let cheap = MarketAnalyzer::step_for_average(30.0);
+        assert_eq!(MarketAnalyzer::get_price_range(25.0, cheap), PriceRange(24, 26));
+        assert_eq!(MarketAnalyzer::get_price_range(29.0, cheap), PriceRange(28, 30));
+        assert_eq!(MarketAnalyzer::get_price_range(25.0, 50), MarketAnalyzer::get_price_range(29.0, 50));
+
+        // A €3000 item: 2900 € and 2990 € share one €150 range instead of two €50 ones
+        let expensive = MarketAnalyzer::step_for_average(3000.0);
+        assert_eq!(MarketAnalyzer::get_price_range(2900.0, expensive), PriceRange(2850, 3000));
+        assert_eq!(MarketAnalyzer::get_price_range(2990.0, expensive), PriceRange(2850, 3000));
+        assert_ne!(MarketAnalyzer::get_price_range(2900.0, 50), MarketAnalyzer::get_price_range(2990.0, 50));
+
+        let speeds = MarketAnalyzer::disappearance_speed(&[lifecycle(2900.0, 2), lifecycle(2990.0, 4)], expensive);
+        assert_eq!(speeds.len(), 1);
+        assert_eq!(speeds[&PriceRange(2850, 3000)], Duration::hours(3));
+        let volatility = MarketAnalyzer::range_volatility(&[25.0, 25.0, 29.0], cheap);
+        assert_eq!((volatility[&PriceRange(24, 26)], volatility[&PriceRange(28, 30)]), (0.0, 0.0));
+    }
+}
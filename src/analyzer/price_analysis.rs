@@ -1,13 +1,16 @@
-use crate::model::{Offer, ModelStats};
-use crate::config::ModelConfig;
+use crate::model::{Deal, Offer, ModelStats, PriceObservation};
+use crate::config::{AppConfig, ModelConfig};
+use crate::storage::SqliteStorage;
 use chrono::Utc;
+use serde::{Serialize, Serializer};
 use crate::analyzer::market_indicators::{MarketAnalyzer, PriceRange};
 use crate::analyzer::lifecycle::build_lifecycle_data;
 
 /// Trait defining the interface for an offer analyzer.
 pub trait Analyzer {
-    fn calculate_stats(&self, offers: &[Offer]) -> ModelStats;
-    fn find_deals(&self, offers: &[Offer], stats: &ModelStats, cfg: &ModelConfig) -> Vec<Offer>;
+    fn calculate_stats(&self, offers: &[Offer]) -> Option<ModelStats>;
+    fn find_deals(&self, offers: &[Offer], stats: &ModelStats, cfg: &ModelConfig) -> Vec<Deal>;
+    fn score_offer(&self, offer: &Offer, stats: &ModelStats) -> f64;
 }
 
 /// Implementation of the offer analyzer.
@@ -17,61 +20,267 @@ impl AnalyzerImpl {
     pub fn new() -> Self {
         Self
     }
+
+    /// Finds the current deals from stored offers and stats (no scraping) for every configured model,
+    /// or only `model_filter`. Deals are deduplicated, best score first, with ignored offers removed.
+    /// Per-model storage failures are returned as messages next to the deals found elsewhere.
+    pub fn current_deals(
+        &self,
+        storage: &SqliteStorage,
+        config: &AppConfig,
+        model_filter: Option<&str>,
+    ) -> (Vec<Deal>, Vec<String>) {
+        let mut deals = Vec::new();
+        let mut errors = Vec::new();
+        for model_cfg in config
+            .models
+            .iter()
+            .filter(|m| model_filter.is_none_or(|f| m.query.eq_ignore_ascii_case(f)))
+        {
+            let stats = storage.get_stats(&model_cfg.query);
+            let offers = storage.get_offers_for_model(&model_cfg.query);
+            match (stats, offers) {
+                (Ok(Some(stats)), Ok(mut offers)) => {
+                    // Stored stats already exclude outliers; the outliers themselves stay deal candidates.
+                    offers.retain(|o| !model_cfg.is_suspicious_price(o.price));
+                    deals.extend(self.find_deals(&offers, &stats, model_cfg));
+                }
+                (Ok(None), _) => {}
+                (Err(e), _) | (_, Err(e)) => errors.push(format!("{}: {:?}", model_cfg.query, e)),
+            }
+        }
+        // Several configs may share a query; keep each offer once, best score first.
+        deals.sort_by(|a, b| b.score.total_cmp(&a.score));
+        let mut seen = std::collections::HashSet::new();
+        deals.retain(|deal| seen.insert(deal.offer.id.clone()));
+        match storage.get_ignored_ids() {
+            Ok(ignored) => deals.retain(|deal| !ignored.contains(&deal.offer.id)),
+            Err(e) => errors.push(format!("ignore list: {:?}", e)),
+        }
+        (deals, errors)
+    }
+
+    /// Like `calculate_stats`, but the average and standard deviation weight each price by
+    /// `exp(-ln 2 * age / half_life)` (halving every `half_life`), with the age taken from `fetched_at`,
+    /// so stale listings fade out of the baseline.
+    /// Median and quartiles stay unweighted. Returns `None` when no offer has a positive, finite price.
+    pub fn calculate_weighted_stats(&self, offers: &[Offer], half_life: chrono::Duration) -> Option<ModelStats> {
+        let mut stats = self.calculate_stats(offers)?;
+        let now = Utc::now();
+        let half_life_secs = half_life.num_seconds().max(1) as f64;
+        let weighted: Vec<(f64, f64)> = offers
+            .iter()
+            .filter(|o| o.price > 0.0 && o.price.is_finite())
+            .map(|o| {
+                let age_secs = (now - o.fetched_at).num_seconds().max(0) as f64;
+                (o.price, (-std::f64::consts::LN_2 * age_secs / half_life_secs).exp())
+            })
+            .collect();
+        let total_weight: f64 = weighted.iter().map(|(_, w)| w).sum();
+        // Every offer is so old its weight underflowed; the plain mean is the best we have.
+        if total_weight <= 0.0 || !total_weight.is_finite() {
+            return Some(stats);
+        }
+        let avg = weighted.iter().map(|(p, w)| p * w).sum::<f64>() / total_weight;
+        let variance = weighted.iter().map(|(p, w)| w * (p - avg).powi(2)).sum::<f64>() / total_weight;
+        stats.avg_price = avg;
+        stats.std_dev = variance.sqrt();
+        Some(stats)
+    }
+
+    /// Splits offers into (kept, outliers) using a robust z-score: a priced offer is an outlier when
+    /// it lies more than `sigma` standard deviations from the median, with the deviation estimated
+    /// from the median absolute deviation (MAD * 1.4826) so the outliers themselves can't inflate it.
+    /// Offers without a positive price are always kept, and nothing is rejected when MAD is zero.
+    pub fn reject_outliers(&self, offers: &[Offer], sigma: f64) -> (Vec<Offer>, Vec<Offer>) {
+        let mut prices: Vec<f64> = offers.iter().map(|o| o.price).filter(|&p| p > 0.0).collect();
+        prices.sort_by(|a, b| a.total_cmp(b));
+        let median = percentile(&prices, 0.5);
+
+        let mut deviations: Vec<f64> = prices.iter().map(|p| (p - median).abs()).collect();
+        deviations.sort_by(|a, b| a.total_cmp(b));
+        let robust_std = percentile(&deviations, 0.5) * 1.4826;
+
+        if robust_std <= f64::EPSILON {
+            return (offers.to_vec(), Vec::new());
+        }
+
+        offers.iter().cloned().partition(|offer| {
+            offer.price <= 0.0 || (offer.price - median).abs() <= sigma * robust_std
+        })
+    }
 }
 
 impl Analyzer for AnalyzerImpl {
-    /// Calculates statistical metrics for offers (average price and standard deviation).
-    fn calculate_stats(&self, offers: &[Offer]) -> ModelStats {
-        let prices: Vec<f64> = offers.iter().map(|o| o.price).filter(|&p| p > 0.0).collect();
+    /// Calculates statistical metrics for offers (average price, standard deviation, median and quartiles).
+    /// Returns `None` when no offer has a positive, finite price.
+    fn calculate_stats(&self, offers: &[Offer]) -> Option<ModelStats> {
+        let mut prices: Vec<f64> = offers
+            .iter()
+            .map(|o| o.price)
+            .filter(|&p| p > 0.0 && p.is_finite())
+            .collect();
+        if prices.is_empty() {
+            return None;
+        }
+        prices.sort_by(|a, b| a.total_cmp(b));
         let count = prices.len() as f64;
         let avg = prices.iter().sum::<f64>() / count;
         let stddev = (prices.iter().map(|p| (p - avg).powi(2)).sum::<f64>() / count).sqrt();
     
-        ModelStats {
+        Some(ModelStats {
             model: offers.first().map(|o| o.model.clone()).unwrap_or_else(|| "unknown".into()),
             avg_price: avg,
             std_dev: stddev,
+            median_price: percentile(&prices, 0.5),
+            p25: percentile(&prices, 0.25),
+            p75: percentile(&prices, 0.75),
             last_updated: Utc::now(),
-        }
+        })
     }
     
     /// Filters offers based on configuration thresholds and statistical metrics.
-    fn find_deals(&self, offers: &[Offer], stats: &ModelStats, cfg: &ModelConfig) -> Vec<Offer> {
+    /// When `volatility_threshold` is set, offers in price ranges that are too volatile
+    /// (std dev above that fraction of the average price) are skipped; the ranges scale with the average price.
+    /// Offers at or below the model's `alert_below_price` are always deals, whatever the stats say.
+    /// Deals are returned best-first by score.
+    fn find_deals(&self, offers: &[Offer], stats: &ModelStats, cfg: &ModelConfig) -> Vec<Deal> {
         let mut result = Vec::new();
+        let prices: Vec<f64> = offers.iter().map(|o| o.price).filter(|&p| p > 0.0).collect();
+        let step = MarketAnalyzer::step_for_average(stats.avg_price);
+        let volatility = MarketAnalyzer::range_volatility(&prices, step);
     
         for offer in offers {
             if offer.price < cfg.min_price || offer.price > cfg.max_price {
                 continue;
             }
+            if cfg.require_shipping && !offer.shipping_available {
+                continue;
+            }
+
+            if cfg.alert_below_price.is_some_and(|target| offer.price <= target) {
+                result.push(Deal {
+                    offer: offer.clone(),
+                    score: self.score_offer(offer, stats),
+                });
+                continue;
+            }
+
+            if let Some(threshold) = cfg.volatility_threshold {
+                let range = MarketAnalyzer::get_price_range(offer.price, step);
+                let range_volatility = volatility.get(&range).copied().unwrap_or(0.0);
+                if range_volatility > threshold * stats.avg_price {
+                    continue;
+                }
+            }
     
             let is_under_percent = offer.price < stats.avg_price * (1.0 - cfg.deviation_threshold);
             let is_under_absolute = (stats.avg_price - offer.price) >= cfg.min_price_delta;
     
             if is_under_percent || is_under_absolute {
-                result.push(offer.clone());
+                result.push(Deal {
+                    offer: offer.clone(),
+                    score: self.score_offer(offer, stats),
+                });
             }
         }
     
+        result.sort_by(|a, b| b.score.total_cmp(&a.score));
         result
     }
+
+    /// Scores an offer from 0 to 100 by how far it is below the model's average price.
+    /// 60% of the score comes from the percentage below average (50% or more below scores full),
+    /// 40% from the distance in standard deviations (3σ or more below scores full).
+    fn score_offer(&self, offer: &Offer, stats: &ModelStats) -> f64 {
+        if stats.avg_price <= 0.0 || !stats.avg_price.is_finite() {
+            return 0.0;
+        }
+        let delta = stats.avg_price - offer.price;
+
+        let percent_below = (delta / stats.avg_price).clamp(0.0, 1.0);
+        let percent_score = (percent_below / 0.5).min(1.0);
+
+        let sigma_score = if stats.std_dev > 0.0 {
+            (delta / stats.std_dev / 3.0).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+
+        ((0.6 * percent_score + 0.4 * sigma_score) * 100.0).round()
+    }
+}
+
+/// Returns the `q`-quantile (0.0..=1.0) of sorted values using linear interpolation.
+/// Returns 0.0 for an empty slice.
+pub fn percentile(sorted: &[f64], q: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = q.clamp(0.0, 1.0) * (sorted.len() - 1) as f64;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+    let weight = rank - lower as f64;
+    sorted[lower] + (sorted[upper] - sorted[lower]) * weight
+}
+
+/// Whether a price change from `previous` to `current` is a drop meeting every configured threshold.
+/// Both prices must be positive, and at least one threshold must be set.
+pub fn is_price_drop(previous: f64, current: f64, min_amount: Option<f64>, min_percent: Option<f64>) -> bool {
+    if min_amount.is_none() && min_percent.is_none() {
+        return false;
+    }
+    if !(previous > 0.0 && current > 0.0 && current < previous) {
+        return false;
+    }
+    let drop = previous - current;
+    min_amount.is_none_or(|amount| drop >= amount)
+        && min_percent.is_none_or(|percent| drop / previous * 100.0 >= percent)
+}
+
+/// Returns the lowest-priced offer, ignoring offers whose price is NaN or infinite.
+pub fn cheapest_offer(offers: &[Offer]) -> Option<&Offer> {
+    offers
+        .iter()
+        .filter(|o| o.price.is_finite())
+        .min_by(|a, b| a.price.total_cmp(&b.price))
 }
 
 /// Structure representing the overall analysis result.
+/// Serializes the disappearance map as `{"<from>-<to>": seconds}`.
+#[derive(Serialize)]
 pub struct AnalysisResult {
+    #[serde(serialize_with = "serialize_disappearance_map")]
     pub disappearance_map: std::collections::HashMap<PriceRange, chrono::Duration>,
     pub price_change_frequency: f64,
     pub rsi: f64,
+    /// Latest (MACD, signal) values of the daily average price, if there is enough history.
+    pub macd: Option<(f64, f64)>,
+}
+
+fn serialize_disappearance_map<S: Serializer>(
+    map: &std::collections::HashMap<PriceRange, chrono::Duration>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let sorted: std::collections::BTreeMap<u32, (String, i64)> = map
+        .iter()
+        .map(|(range, duration)| (range.0, (format!("{}-{}", range.0, range.1), duration.num_seconds())))
+        .collect();
+    serializer.collect_map(sorted.into_values())
 }
 
 impl AnalyzerImpl {
-    /// Asynchronously analyzes offers by building lifecycle data and computing various market indicators.
+    /// Asynchronously analyzes the recorded price history by building lifecycle data and computing various market indicators.
     /// The RSI is now computed based on the full series of prices extracted from the lifecycles.
-    pub async fn analyze_offers(&self, offers: &[Offer]) -> AnalysisResult {
+    pub async fn analyze_offers(&self, history: &[PriceObservation]) -> AnalysisResult {
         // Build lifecycle data for offers.
-        let lifecycles = build_lifecycle_data(offers).await;
+        let lifecycles = build_lifecycle_data(history).await;
         
-        // Calculate the disappearance map per price range.
-        let disappearance_map = MarketAnalyzer::disappearance_speed(&lifecycles);
+        // Calculate the disappearance map per price range, sized to the model's average price.
+        let priced: Vec<f64> = lifecycles.iter().map(|o| o.price).filter(|&p| p > 0.0).collect();
+        let avg_price = if priced.is_empty() { 0.0 } else { priced.iter().sum::<f64>() / priced.len() as f64 };
+        let step = MarketAnalyzer::step_for_average(avg_price);
+        let disappearance_map = MarketAnalyzer::disappearance_speed(&lifecycles, step);
         
         // Calculate the price change frequency.
         let freq = MarketAnalyzer::price_change_frequency(&lifecycles);
@@ -79,13 +288,232 @@ impl AnalyzerImpl {
         // Extract a series of prices from lifecycles to compute RSI.
         let mut price_series: Vec<f64> = lifecycles.iter().map(|o| o.price).collect();
         // Optionally, sort the price series for a more robust RSI calculation.
-        price_series.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        price_series.sort_by(|a, b| a.total_cmp(b));
         let rsi = MarketAnalyzer::compute_rsi(&price_series);
+
+        // Trend signals work on the chronological series of daily average prices.
+        let daily_averages = daily_average_prices(history);
+        let (macd_line, signal_line) = MarketAnalyzer::macd(&daily_averages);
+        let macd = macd_line.last().copied().zip(signal_line.last().copied());
     
         AnalysisResult {
             disappearance_map,
             price_change_frequency: freq,
             rsi,
+            macd,
         }
     }
-}
\ No newline at end of file
+}
+
+/// Averages the positive observed prices per calendar day, oldest day first.
+fn daily_average_prices(history: &[PriceObservation]) -> Vec<f64> {
+    let mut by_day: std::collections::BTreeMap<chrono::NaiveDate, (f64, usize)> = std::collections::BTreeMap::new();
+    for observation in history.iter().filter(|o| o.price > 0.0) {
+        let entry = by_day.entry(observation.observed_at.date_naive()).or_insert((0.0, 0));
+        entry.0 += observation.price;
+        entry.1 += 1;
+    }
+    by_day.into_values().map(|(sum, count)| sum / count as f64).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{model_stats, offers_at};
+
+    #[test]
+    fn outliers_are_dropped_from_stats_but_stay_deal_candidates() {
+        let analyzer = AnalyzerImpl::new();
+        let offers = offers_at("iphone", &[10.0, 98.0, 99.0, 100.0, 101.0, 102.0, 1000.0]);
+        let mut cfg = ModelConfig::permissive("iphone");
+        cfg.deviation_threshold = 0.3;
+        cfg.min_price_delta = f64::MAX;
+
+        let (kept, outliers) = analyzer.reject_outliers(&offers, 3.0);
+        let mut outlier_prices: Vec<f64> = outliers.iter().map(|o| o.price).collect();
+        outlier_prices.sort_by(|a, b| a.total_cmp(b));
+        assert_eq!(outlier_prices, vec![10.0, 1000.0]);
+
+        let all_stats = analyzer.calculate_stats(&offers).unwrap();
+        let stats = analyzer.calculate_stats(&kept).unwrap();
+        assert!((all_stats.avg_price - 215.714).abs() < 0.01);
+        assert!((stats.avg_price - 100.0).abs() < 1e-9);
+
+        // The cheap outlier is exactly the listing worth alerting on
+        let deals = analyzer.find_deals(&offers, &stats, &cfg);
+        assert_eq!(deals.len(), 1);
+        assert_eq!(deals[0].offer.price, 10.0);
+        assert!(analyzer.find_deals(&kept, &stats, &cfg).is_empty());
+    }
+
+    #[test]
+    fn percentile_interpolates_between_values() {
+        let sorted = [100.0, 200.0, 300.0, 400.0];
+        assert_eq!(percentile(&sorted, 0.0), 100.0);
+        assert_eq!(percentile(&sorted, 0.25), 175.0);
+        assert_eq!(percentile(&sorted, 0.5), 250.0);
+        assert_eq!(percentile(&sorted, 0.75), 325.0);
+        assert_eq!(percentile(&sorted, 1.0), 400.0);
+        assert_eq!(percentile(&sorted, 2.0), 400.0);
+        assert_eq!(percentile(&[42.0], 0.75), 42.0);
+        assert_eq!(percentile(&[], 0.5), 0.0);
+    }
+
+    #[test]
+    fn stats_include_median_and_quartiles() {
+        // Unsorted, with an unpriced offer that must not count
+        let offers = offers_at("iphone", &[50.0, 10.0, 0.0, 40.0, 20.0, 30.0]);
+        let stats = AnalyzerImpl::new().calculate_stats(&offers).unwrap();
+        assert_eq!(stats.model, "iphone");
+        assert_eq!(stats.avg_price, 30.0);
+        assert_eq!(stats.median_price, 30.0);
+        assert_eq!((stats.p25, stats.p75), (20.0, 40.0));
+        assert!((stats.std_dev - 200f64.sqrt()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn median_resists_a_single_expensive_offer() {
+        let offers = offers_at("iphone", &[400.0, 420.0, 440.0, 460.0, 5000.0]);
+        let stats = AnalyzerImpl::new().calculate_stats(&offers).unwrap();
+        assert_eq!(stats.avg_price, 1344.0);
+        assert_eq!(stats.median_price, 440.0);
+        assert_eq!((stats.p25, stats.p75), (420.0, 460.0));
+    }
+
+    fn deal_prices(deals: &[Deal]) -> Vec<f64> {
+        let mut prices: Vec<f64> = deals.iter().map(|d| d.offer.price).collect();
+        prices.sort_by(|a, b| a.total_cmp(b));
+        prices
+    }
+
+    #[test]
+    fn volatile_ranges_are_skipped_only_above_the_threshold() {
+        let analyzer = AnalyzerImpl::new();
+        // Average 1000 gives 50-wide ranges: 700 and 740 spread by σ = 20 in [700, 750),
+        // 800 and 801 by σ = 0.5 in [800, 850).
+        let offers = offers_at("iphone", &[700.0, 740.0, 800.0, 801.0]);
+        let mut cfg = ModelConfig::permissive("iphone");
+        cfg.deviation_threshold = 0.1;
+        cfg.min_price_delta = f64::MAX;
+
+        let deals = |threshold: Option<f64>| {
+            let mut cfg = cfg.clone();
+            cfg.volatility_threshold = threshold;
+            deal_prices(&analyzer.find_deals(&offers, &model_stats("iphone", 1000.0, 100.0), &cfg))
+        };
+        assert_eq!(deals(None), vec![700.0, 740.0, 800.0, 801.0]);
+        assert_eq!(deals(Some(0.03)), vec![700.0, 740.0, 800.0, 801.0]);
+        assert_eq!(deals(Some(0.01)), vec![800.0, 801.0]);
+        assert_eq!(deals(Some(0.0001)), Vec::<f64>::new());
+    }
+
+    #[test]
+    fn no_stats_without_a_positive_finite_price() {
+        let analyzer = AnalyzerImpl::new();
+        assert!(analyzer.calculate_stats(&[]).is_none());
+        assert!(analyzer.calculate_stats(&offers_at("iphone", &[0.0, 0.0])).is_none());
+        assert!(analyzer.calculate_stats(&offers_at("iphone", &[f64::NAN, f64::INFINITY, -5.0])).is_none());
+    }
+
+    #[test]
+    fn a_single_offer_is_its_own_average_with_zero_spread() {
+        let analyzer = AnalyzerImpl::new();
+        // Free listings (price 0) and broken prices are left out of the stats
+        let stats = analyzer.calculate_stats(&offers_at("iphone", &[0.0, 450.0, f64::NAN])).unwrap();
+        assert_eq!(stats.model, "iphone");
+        assert_eq!(
+            (stats.avg_price, stats.std_dev, stats.median_price, stats.p25, stats.p75),
+            (450.0, 0.0, 450.0, 450.0, 450.0)
+        );
+    }
+
+    #[test]
+    fn cheapest_offer_skips_nan_and_infinite_prices() {
+        let offers = offers_at("iphone", &[f64::NAN, 500.0, f64::NEG_INFINITY, 450.0, -f64::NAN, 480.0]);
+        assert_eq!(cheapest_offer(&offers).map(|o| o.id.as_str()), Some("o3"));
+        assert!(cheapest_offer(&offers_at("iphone", &[f64::NAN, f64::INFINITY])).is_none());
+        assert!(cheapest_offer(&[]).is_none());
+    }
+
+    #[test]
+    fn price_drops_must_meet_every_threshold() {
+        // 500 → 450 is a 50 € / 10 % drop
+        assert!(is_price_drop(500.0, 450.0, Some(50.0), None));
+        assert!(!is_price_drop(500.0, 450.0, Some(50.01), None));
+        assert!(is_price_drop(500.0, 450.0, None, Some(10.0)));
+        assert!(!is_price_drop(500.0, 450.0, None, Some(10.5)));
+        assert!(is_price_drop(500.0, 450.0, Some(20.0), Some(5.0)));
+        assert!(!is_price_drop(500.0, 450.0, Some(20.0), Some(15.0)));
+        assert!(!is_price_drop(500.0, 450.0, Some(60.0), Some(5.0)));
+    }
+
+    #[test]
+    fn rises_unknown_prices_and_unset_thresholds_are_not_drops() {
+        assert!(!is_price_drop(500.0, 450.0, None, None));
+        assert!(!is_price_drop(450.0, 500.0, Some(0.0), None));
+        assert!(!is_price_drop(500.0, 500.0, Some(0.0), None));
+        // A listing that became free or had no price before
+        assert!(!is_price_drop(500.0, 0.0, Some(10.0), None));
+        assert!(!is_price_drop(0.0, 450.0, Some(10.0), None));
+    }
+
+    #[test]
+    fn require_shipping_drops_pickup_only_deals() {
+        let analyzer = AnalyzerImpl::new();
+        let mut offers = offers_at("iphone", &[400.0, 420.0]);
+        offers[1].shipping_available = true;
+        let mut cfg = ModelConfig::permissive("iphone");
+        cfg.deviation_threshold = 0.1;
+
+        assert_eq!(deal_prices(&analyzer.find_deals(&offers, &model_stats("iphone", 1000.0, 100.0), &cfg)), vec![400.0, 420.0]);
+        cfg.require_shipping = true;
+        assert_eq!(deal_prices(&analyzer.find_deals(&offers, &model_stats("iphone", 1000.0, 100.0), &cfg)), vec![420.0]);
+    }
+
+    #[test]
+    fn recent_cheap_offers_pull_the_weighted_average_below_the_mean() {
+        let analyzer = AnalyzerImpl::new();
+        let mut offers = offers_at("iphone", &[600.0, 600.0, 400.0, 400.0]);
+        for old in &mut offers[..2] {
+            old.fetched_at = Utc::now() - chrono::Duration::days(14);
+        }
+
+        let naive = analyzer.calculate_stats(&offers).unwrap();
+        assert_eq!(naive.avg_price, 500.0);
+
+        // Two half-lives old: weight 1/4 against 1 for today's offers
+        let weighted = analyzer.calculate_weighted_stats(&offers, chrono::Duration::days(7)).unwrap();
+        assert!((weighted.avg_price - 440.0).abs() < 0.01, "{}", weighted.avg_price);
+        assert!(weighted.std_dev < naive.std_dev);
+        assert_eq!((weighted.median_price, weighted.p25, weighted.p75), (naive.median_price, naive.p25, naive.p75));
+
+        // A very long half-life is the plain mean again
+        let flat = analyzer.calculate_weighted_stats(&offers, chrono::Duration::days(36_500)).unwrap();
+        assert!((flat.avg_price - 500.0).abs() < 0.1, "{}", flat.avg_price);
+        assert!(analyzer.calculate_weighted_stats(&[], chrono::Duration::days(7)).is_none());
+    }
+
+    #[test]
+    fn offers_at_or_below_the_alert_price_are_deals_whatever_the_average() {
+        let analyzer = AnalyzerImpl::new();
+        let mut cfg = ModelConfig::permissive("iphone");
+        cfg.deviation_threshold = 0.5;
+        cfg.min_price_delta = f64::MAX;
+        cfg.volatility_threshold = Some(0.0);
+        cfg.alert_below_price = Some(300.0);
+
+        // The market is cheaper than the target, so nothing is a statistical deal
+        let offers = offers_at("iphone", &[250.0, 300.0, 301.0]);
+        let deals = analyzer.find_deals(&offers, &model_stats("iphone", 200.0, 20.0), &cfg);
+        assert_eq!(deal_prices(&deals), vec![250.0, 300.0]);
+        assert!(deals.iter().all(|d| d.score == 0.0));
+
+        // A single sample: stats are meaningless, the target still applies, and each offer is listed once
+        let single = offers_at("iphone", &[100.0]);
+        let single_stats = analyzer.calculate_stats(&single).unwrap();
+        assert_eq!(deal_prices(&analyzer.find_deals(&single, &single_stats, &cfg)), vec![100.0]);
+
+        cfg.alert_below_price = None;
+        assert!(analyzer.find_deals(&offers, &model_stats("iphone", 200.0, 20.0), &cfg).is_empty());
+    }
+}
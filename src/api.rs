@@ -0,0 +1,163 @@
+// Read-only JSON API over stored offers, stats and current deals.
+use crate::analyzer::AnalyzerImpl;
+use crate::config::SharedConfig;
+use crate::http_server::{self, HttpRequest, HttpResponse};
+use crate::model::SortKey;
+use crate::storage::SqliteStorage;
+use std::sync::Arc;
+use tracing::warn;
+
+/// Page size for `/offers` when `limit` is not given, and the largest one accepted.
+const DEFAULT_PAGE_SIZE: usize = 100;
+const MAX_PAGE_SIZE: usize = 1000;
+
+/// Serves the API on `addr` until the task is aborted.
+pub async fn serve_api(addr: String, config: Arc<SharedConfig>, storage: Arc<SqliteStorage>) {
+    let result = http_server::serve(&addr, move |request| {
+        let config = config.clone();
+        let storage = storage.clone();
+        async move { handle_request(&request, &config, &storage) }
+    })
+    .await;
+    if let Err(e) = result {
+        warn!("❌ API server on {} stopped: {}", addr, e);
+    }
+}
+
+/// Routes `GET /offers?model=&sort=&limit=&offset=`, `GET /stats` and `GET /deals?model=`.
+pub fn handle_request(request: &HttpRequest, config: &SharedConfig, storage: &SqliteStorage) -> HttpResponse {
+    if request.method != "GET" {
+        return HttpResponse::text(405, "Method Not Allowed");
+    }
+    let model = request.query_param("model").filter(|m| !m.trim().is_empty());
+
+    match request.route() {
+        "/offers" => {
+            let sort = match request.query_param("sort") {
+                None => SortKey::PriceAsc,
+                Some(value) => match SortKey::parse(&value) {
+                    Some(sort) => sort,
+                    None => return HttpResponse::text(400, "sort must be price_asc, price_desc or newest"),
+                },
+            };
+            let limit = query_usize(request, "limit").unwrap_or(DEFAULT_PAGE_SIZE).min(MAX_PAGE_SIZE);
+            let offset = query_usize(request, "offset").unwrap_or(0);
+            match storage.get_offers(model.as_deref(), sort, limit, offset) {
+                Ok(offers) => HttpResponse::json(200, &offers),
+                Err(e) => storage_error(e),
+            }
+        }
+        "/stats" => {
+            let mut stats = Vec::new();
+            for model_cfg in config.load().models.iter() {
+                match storage.get_stats(&model_cfg.query) {
+                    Ok(Some(s)) => stats.push(s),
+                    Ok(None) => {}
+                    Err(e) => return storage_error(e),
+                }
+            }
+            HttpResponse::json(200, &stats)
+        }
+        "/deals" => {
+            let config = config.load();
+            let (deals, errors) = AnalyzerImpl::new().current_deals(storage, &config, model.as_deref());
+            HttpResponse::json(200, &serde_json::json!({ "deals": deals, "errors": errors }))
+        }
+        _ => HttpResponse::text(404, "Not Found"),
+    }
+}
+
+fn query_usize(request: &HttpRequest, name: &str) -> Option<usize> {
+    request.query_param(name)?.parse().ok()
+}
+
+fn storage_error(e: crate::model::StorageError) -> HttpResponse {
+    warn!("❌ API storage error: {:?}", e);
+    HttpResponse::json(500, &serde_json::json!({ "error": e.to_string() }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ModelConfig;
+    use crate::test_support::{app_config, model_stats, offer, TempStorage};
+    use serde_json::Value;
+
+    fn get(path: &str) -> HttpRequest {
+        HttpRequest {
+            method: "GET".to_string(),
+            path: path.to_string(),
+            headers: Default::default(),
+            body: Vec::new(),
+        }
+    }
+
+    /// Two "test" offers at 300 and 500 € and one "other" offer, with stats for "test" and "other".
+    fn fixture() -> (SharedConfig, TempStorage) {
+        let mut model_cfg = ModelConfig::permissive("test");
+        model_cfg.deviation_threshold = 0.2;
+        let config = SharedConfig::new("config.json", app_config(vec![model_cfg]));
+        let storage = TempStorage::new();
+        storage.save_offer(&offer("cheap", "iPhone 13", 300.0)).unwrap();
+        storage.save_offer(&offer("normal", "iPhone 13", 500.0)).unwrap();
+        let mut other = offer("other", "Pixel 7", 200.0);
+        other.model = "other".to_string();
+        storage.save_offer(&other).unwrap();
+        storage.update_stats(&model_stats("test", 480.0, 50.0)).unwrap();
+        storage.update_stats(&model_stats("other", 250.0, 50.0)).unwrap();
+        (config, storage)
+    }
+
+    fn json(response: HttpResponse) -> Value {
+        assert_eq!(response.status, 200, "{}", response.body);
+        assert_eq!(response.content_type, "application/json");
+        serde_json::from_str(&response.body).unwrap()
+    }
+
+    fn ids(offers: &Value) -> Vec<&str> {
+        offers.as_array().unwrap().iter().map(|o| o["id"].as_str().unwrap()).collect()
+    }
+
+    #[test]
+    fn offers_are_filtered_sorted_and_paged() {
+        let (config, storage) = fixture();
+        let offers = |path: &str| json(handle_request(&get(path), &config, &storage));
+
+        assert_eq!(ids(&offers("/offers")), vec!["other", "cheap", "normal"]);
+        assert_eq!(ids(&offers("/offers?model=test&sort=price_desc")), vec!["normal", "cheap"]);
+        assert_eq!(ids(&offers("/offers?limit=1&offset=1")), vec!["cheap"]);
+        assert_eq!(offers("/offers?model=test")[0]["price"], 300.0);
+        assert_eq!(handle_request(&get("/offers?sort=cheapest"), &config, &storage).status, 400);
+    }
+
+    #[test]
+    fn stats_cover_the_configured_models() {
+        let (config, storage) = fixture();
+        let stats = json(handle_request(&get("/stats"), &config, &storage));
+        let models: Vec<&str> = stats.as_array().unwrap().iter().map(|s| s["model"].as_str().unwrap()).collect();
+        assert_eq!(models, vec!["test"]);
+        assert_eq!(stats[0]["avg_price"], 480.0);
+    }
+
+    #[test]
+    fn deals_come_from_the_stored_offers_and_stats() {
+        let (config, storage) = fixture();
+        let deals = json(handle_request(&get("/deals?model=test"), &config, &storage));
+        let ids: Vec<&str> = deals["deals"].as_array().unwrap().iter().map(|d| d["offer"]["id"].as_str().unwrap()).collect();
+        assert_eq!(ids, vec!["cheap"]);
+        assert!(deals["deals"][0]["score"].as_f64().unwrap() > 0.0);
+        assert_eq!(deals["errors"], serde_json::json!([]));
+
+        storage.ignore_offer("cheap").unwrap();
+        let deals = json(handle_request(&get("/deals"), &config, &storage));
+        assert_eq!(deals["deals"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn only_get_on_known_routes() {
+        let (config, storage) = fixture();
+        let post = HttpRequest { method: "POST".to_string(), ..get("/offers") };
+        assert_eq!(handle_request(&post, &config, &storage).status, 405);
+        assert_eq!(handle_request(&get("/admin"), &config, &storage).status, 404);
+    }
+}
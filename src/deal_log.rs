@@ -0,0 +1,109 @@
+// Append-only JSONL log of flagged deals for offline analysis.
+use crate::model::{Deal, ModelStats, Offer};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::sync::Mutex;
+use tracing::warn;
+
+/// Serializes appends so lines from concurrent model tasks never interleave.
+static WRITE_LOCK: Mutex<()> = Mutex::new(());
+
+/// One line of the deal log: the offer, its score and the stats it was judged against.
+#[derive(Debug, Serialize)]
+pub struct DealEvent<'a> {
+    pub detected_at: DateTime<Utc>,
+    pub score: f64,
+    pub offer: &'a Offer,
+    pub stats: &'a ModelStats,
+}
+
+impl<'a> DealEvent<'a> {
+    pub fn new(deal: &'a Deal, stats: &'a ModelStats) -> Self {
+        Self {
+            detected_at: Utc::now(),
+            score: deal.score,
+            offer: &deal.offer,
+            stats,
+        }
+    }
+}
+
+/// Appends `event` as one JSON line to `path`. Failures are logged, never fatal.
+pub fn append(path: &str, event: &DealEvent) {
+    let mut line = match serde_json::to_string(event) {
+        Ok(line) => line,
+        Err(e) => {
+            warn!("❌ Deal log serialization failed: {}", e);
+            return;
+        }
+    };
+    line.push('\n');
+
+    let _guard = WRITE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let result = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(line.as_bytes()));
+    if let Err(e) = result {
+        warn!("❌ Deal log write to {} failed: {}", path, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{model_stats, offer, TempFile};
+
+    #[test]
+    fn each_deal_is_one_json_line() {
+        let log = TempFile::new("jsonl");
+        let stats = ModelStats { median_price: 495.0, ..model_stats("iphone 13", 500.0, 40.0) };
+        let first = Deal { offer: offer("1", "iPhone 13", 400.0), score: 87.5 };
+        let second = Deal { offer: offer("2", "iPhone 13 \"neu\"\nmit OVP", 420.0), score: 60.0 };
+        append(log.path(), &DealEvent::new(&first, &stats));
+        append(log.path(), &DealEvent::new(&second, &stats));
+
+        let contents = std::fs::read_to_string(log.path()).unwrap();
+        let lines: Vec<serde_json::Value> = contents.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["score"], 87.5);
+        assert_eq!(lines[0]["offer"]["id"], "1");
+        assert_eq!(lines[0]["offer"]["price"], 400.0);
+        assert_eq!(lines[0]["stats"]["avg_price"], 500.0);
+        assert_eq!(lines[0]["stats"]["median_price"], 495.0);
+        assert!(lines[0]["detected_at"].as_str().unwrap().parse::<DateTime<Utc>>().is_ok());
+        assert_eq!(lines[1]["offer"]["title"], "iPhone 13 \"neu\"\nmit OVP");
+    }
+
+    #[test]
+    fn concurrent_appends_never_interleave() {
+        let log = TempFile::new("jsonl");
+        let stats = model_stats("iphone 13", 500.0, 40.0);
+        std::thread::scope(|scope| {
+            for worker in 0..8 {
+                let (path, stats) = (log.path(), &stats);
+                scope.spawn(move || {
+                    let mut deal = Deal { offer: offer(&worker.to_string(), "iPhone 13", 400.0), score: 50.0 };
+                    deal.offer.description = "x".repeat(10_000);
+                    for _ in 0..10 {
+                        append(path, &DealEvent::new(&deal, stats));
+                    }
+                });
+            }
+        });
+
+        let contents = std::fs::read_to_string(log.path()).unwrap();
+        assert_eq!(contents.lines().count(), 80);
+        assert!(contents.lines().all(|line| serde_json::from_str::<serde_json::Value>(line).is_ok()));
+    }
+
+    #[test]
+    fn an_unwritable_path_is_not_fatal() {
+        let stats = model_stats("iphone 13", 500.0, 40.0);
+        let deal = Deal { offer: offer("1", "iPhone 13", 400.0), score: 50.0 };
+        append("/nonexistent/dir/deals.jsonl", &DealEvent::new(&deal, &stats));
+    }
+}
@@ -0,0 +1,288 @@
+// Minimal HTTP/1.1 server on top of tokio, used for the Telegram webhook, metrics and the REST API.
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::time::{sleep, timeout, Duration};
+use tracing::{info, warn};
+
+/// Upper bound for headers + body of a single request.
+const MAX_REQUEST_BYTES: usize = 1024 * 1024;
+/// A client that hasn't sent its whole request by then gets a 408 and is disconnected.
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+/// Pause after a failed `accept`, so running out of file descriptors doesn't turn into a busy loop.
+const ACCEPT_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// Incoming request. Header names are lower-cased.
+#[derive(Debug)]
+pub struct HttpRequest {
+    pub method: String,
+    pub path: String,
+    pub headers: HashMap<String, String>,
+    pub body: Vec<u8>,
+}
+
+impl HttpRequest {
+    /// The path without the query string.
+    pub fn route(&self) -> &str {
+        self.path.split_once('?').map_or(self.path.as_str(), |(route, _)| route)
+    }
+
+    /// First value of a (percent-decoded) query parameter.
+    pub fn query_param(&self, name: &str) -> Option<String> {
+        let url = reqwest::Url::parse(&format!("http://localhost{}", self.path)).ok()?;
+        url.query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+    }
+}
+
+/// Outgoing response.
+#[derive(Debug)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+impl HttpResponse {
+    pub fn text(status: u16, body: impl Into<String>) -> Self {
+        Self {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body: body.into(),
+        }
+    }
+
+    /// Serializes `value` as a JSON response, or a 500 if that fails.
+    pub fn json<T: serde::Serialize>(status: u16, value: &T) -> Self {
+        match serde_json::to_string(value) {
+            Ok(body) => Self {
+                status,
+                content_type: "application/json",
+                body,
+            },
+            Err(e) => Self::text(500, format!("serialization failed: {}", e)),
+        }
+    }
+}
+
+/// Binds `addr` and serves every connection with `handler` until the task is aborted.
+/// Only fails when `addr` can't be bound.
+pub async fn serve<F, Fut>(addr: &str, handler: F) -> std::io::Result<()>
+where
+    F: Fn(HttpRequest) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = HttpResponse> + Send,
+{
+    let listener = TcpListener::bind(addr).await?;
+    info!("🌐 HTTP server listening on {}", addr);
+    serve_listener(listener, handler).await;
+    Ok(())
+}
+
+/// Serves every connection accepted on `listener` with `handler` until the task is aborted.
+/// Failed accepts (e.g. a connection reset before it was accepted, or too many open files)
+/// are logged and the server keeps going.
+pub async fn serve_listener<F, Fut>(listener: TcpListener, handler: F)
+where
+    F: Fn(HttpRequest) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = HttpResponse> + Send,
+{
+    let handler = Arc::new(handler);
+
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                warn!("HTTP accept failed: {}", e);
+                sleep(ACCEPT_RETRY_DELAY).await;
+                continue;
+            }
+        };
+        let handler = handler.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream, handler.as_ref()).await {
+                warn!("HTTP connection from {} failed: {}", peer, e);
+            }
+        });
+    }
+}
+
+async fn handle_connection<F, Fut>(mut stream: TcpStream, handler: &F) -> std::io::Result<()>
+where
+    F: Fn(HttpRequest) -> Fut,
+    Fut: Future<Output = HttpResponse>,
+{
+    let response = match timeout(READ_TIMEOUT, read_request(&mut stream)).await {
+        Ok(request) => match request? {
+            Some(request) => handler(request).await,
+            None => HttpResponse::text(400, "Bad Request"),
+        },
+        Err(_) => HttpResponse::text(408, "Request Timeout"),
+    };
+
+    let head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        response.status,
+        reason_phrase(response.status),
+        response.content_type,
+        response.body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(response.body.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+/// Reads one request. Returns `None` when the request is malformed or too large.
+async fn read_request(stream: &mut TcpStream) -> std::io::Result<Option<HttpRequest>> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 4096];
+
+    let header_end = loop {
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos;
+        }
+        if buf.len() > MAX_REQUEST_BYTES {
+            return Ok(None);
+        }
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            return Ok(None);
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    };
+
+    let head = String::from_utf8_lossy(&buf[..header_end]).to_string();
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next().unwrap_or("").split_whitespace();
+    let (Some(method), Some(path)) = (request_line.next(), request_line.next()) else {
+        return Ok(None);
+    };
+
+    let headers: HashMap<String, String> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+        .collect();
+
+    let content_length = headers
+        .get("content-length")
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(0);
+    if content_length > MAX_REQUEST_BYTES {
+        return Ok(None);
+    }
+
+    let mut body = buf[header_end + 4..].to_vec();
+    while body.len() < content_length {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            return Ok(None);
+        }
+        body.extend_from_slice(&chunk[..n]);
+    }
+    body.truncate(content_length);
+
+    Ok(Some(HttpRequest {
+        method: method.to_string(),
+        path: path.to_string(),
+        headers,
+        body,
+    }))
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        500 => "Internal Server Error",
+        503 => "Service Unavailable",
+        _ => "",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::spawn_http_server;
+
+    async fn echo_server() -> String {
+        spawn_http_server(|request: HttpRequest| async move {
+            let body = format!(
+                "{} {} q={:?} type={:?} body={}",
+                request.method,
+                request.route(),
+                request.query_param("q"),
+                request.headers.get("content-type"),
+                String::from_utf8_lossy(&request.body)
+            );
+            HttpResponse::text(200, body)
+        })
+        .await
+    }
+
+    /// Sends raw bytes and returns the whole response.
+    async fn raw_request(base: &str, bytes: &[u8]) -> String {
+        let mut stream = TcpStream::connect(base.trim_start_matches("http://")).await.unwrap();
+        stream.write_all(bytes).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn serves_get_and_post_requests() {
+        let base = echo_server().await;
+        let client = reqwest::Client::new();
+
+        let body = client.get(format!("{}/offers?q=iphone%2013&x=1", base)).send().await.unwrap().text().await.unwrap();
+        assert_eq!(body, "GET /offers q=Some(\"iphone 13\") type=None body=");
+
+        let response = client
+            .post(format!("{}/webhook", base))
+            .header("Content-Type", "application/json")
+            .body("{\"ok\":true}")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(
+            response.text().await.unwrap(),
+            "POST /webhook q=None type=Some(\"application/json\") body={\"ok\":true}"
+        );
+    }
+
+    #[tokio::test]
+    async fn malformed_and_oversized_requests_get_400() {
+        let base = echo_server().await;
+        assert!(raw_request(&base, b"garbage\r\n\r\n").await.starts_with("HTTP/1.1 400 Bad Request"));
+
+        let oversized = format!("POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\n", MAX_REQUEST_BYTES + 1);
+        assert!(raw_request(&base, oversized.as_bytes()).await.starts_with("HTTP/1.1 400"));
+    }
+
+    #[tokio::test]
+    async fn a_stalled_client_does_not_block_others() {
+        let base = echo_server().await;
+        let mut stalled = TcpStream::connect(base.trim_start_matches("http://")).await.unwrap();
+        stalled.write_all(b"GET /slow HTTP/1.1\r\n").await.unwrap();
+
+        let response = timeout(Duration::from_secs(5), reqwest::get(format!("{}/fast", base))).await;
+        assert_eq!(response.unwrap().unwrap().status(), 200);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_stalled_client_times_out_with_408() {
+        let base = echo_server().await;
+        let mut stalled = TcpStream::connect(base.trim_start_matches("http://")).await.unwrap();
+        stalled.write_all(b"POST /slow HTTP/1.1\r\nContent-Length: 10\r\n\r\n12345").await.unwrap();
+
+        let mut response = String::new();
+        stalled.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 408 Request Timeout"), "{}", response);
+    }
+}
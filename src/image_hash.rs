@@ -0,0 +1,572 @@
+// Perceptual hashing (dHash) of listing photos, to recognize reposts that reuse the same pictures.
+//
+// Only baseline JPEG is supported, which is what the listing thumbnails are served as. The hash
+// needs a tiny grayscale image, so just the DC coefficient of each 8x8 luma block is decoded
+// (one value per block, i.e. the image at 1/8 scale); the AC coefficients are skipped and no
+// inverse DCT is done.
+
+/// dHash compares horizontally adjacent pixels of the image shrunk to this size (64 bits).
+const HASH_WIDTH: usize = 9;
+const HASH_HEIGHT: usize = 8;
+/// Larger images are rejected rather than decoded; thumbnails are a few hundred pixels wide.
+const MAX_DIMENSION: usize = 8192;
+
+/// Computes the 64-bit difference hash of a JPEG image.
+/// Returns `None` for anything that isn't a well-formed baseline JPEG.
+pub fn dhash(image: &[u8]) -> Option<u64> {
+    let (pixels, width, height) = decode_luma_blocks(image)?;
+    Some(dhash_luma(&pixels, width, height))
+}
+
+/// Number of differing bits; near-identical images stay within a few bits.
+pub fn hamming_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// dHash of a grayscale image given row by row: shrink to 9x8 by box averaging, then set one bit
+/// per pixel that is brighter than its right neighbour.
+fn dhash_luma(pixels: &[f32], width: usize, height: usize) -> u64 {
+    let small = resize_box(pixels, width, height, HASH_WIDTH, HASH_HEIGHT);
+    let mut hash = 0u64;
+    for y in 0..HASH_HEIGHT {
+        for x in 0..HASH_WIDTH - 1 {
+            hash <<= 1;
+            if small[y * HASH_WIDTH + x] > small[y * HASH_WIDTH + x + 1] {
+                hash |= 1;
+            }
+        }
+    }
+    hash
+}
+
+/// Resizes by averaging the source pixels that fall into each target pixel.
+/// A source smaller than the target repeats its pixels.
+fn resize_box(pixels: &[f32], width: usize, height: usize, target_w: usize, target_h: usize) -> Vec<f32> {
+    let span = |i: usize, source: usize, target: usize| {
+        let start = i * source / target;
+        let end = ((i + 1) * source / target).max(start + 1).min(source);
+        start..end
+    };
+    let mut out = Vec::with_capacity(target_w * target_h);
+    for ty in 0..target_h {
+        for tx in 0..target_w {
+            let (rows, cols) = (span(ty, height, target_h), span(tx, width, target_w));
+            let count = (rows.len() * cols.len()) as f32;
+            let sum: f32 = rows.flat_map(|y| cols.clone().map(move |x| pixels[y * width + x])).sum();
+            out.push(sum / count);
+        }
+    }
+    out
+}
+
+struct Component {
+    id: u8,
+    h: usize,
+    v: usize,
+}
+
+struct Frame {
+    width: usize,
+    height: usize,
+    components: Vec<Component>,
+}
+
+/// Canonical Huffman table in the decoding form of the JPEG spec (F.2.2.3).
+struct HuffmanTable {
+    max_code: [i32; 17],
+    min_code: [i32; 17],
+    value_offset: [i32; 17],
+    values: Vec<u8>,
+}
+
+impl HuffmanTable {
+    fn new(counts: &[u8], values: &[u8]) -> Option<Self> {
+        let mut table = Self {
+            max_code: [-1; 17],
+            min_code: [0; 17],
+            value_offset: [0; 17],
+            values: values.to_vec(),
+        };
+        let (mut code, mut index) = (0i32, 0i32);
+        for len in 1..=16 {
+            let count = i32::from(counts[len - 1]);
+            if count > 0 {
+                table.value_offset[len] = index;
+                table.min_code[len] = code;
+                code += count;
+                index += count;
+                table.max_code[len] = code - 1;
+            }
+            code <<= 1;
+        }
+        (index as usize == values.len()).then_some(table)
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> Option<u8> {
+        let mut code = 0i32;
+        for len in 1..=16 {
+            code = (code << 1) | reader.bit()? as i32;
+            if code <= self.max_code[len] {
+                let index = self.value_offset[len] + code - self.min_code[len];
+                return self.values.get(index as usize).copied();
+            }
+        }
+        None
+    }
+}
+
+/// Reads entropy-coded scan data bit by bit, dropping the stuffed zero after each 0xFF.
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    byte: u8,
+    bits_left: u8,
+}
+
+impl BitReader<'_> {
+    fn bit(&mut self) -> Option<u8> {
+        if self.bits_left == 0 {
+            let byte = *self.data.get(self.pos)?;
+            if byte == 0xFF {
+                // Any other byte after 0xFF is a marker: the scan ended early
+                if *self.data.get(self.pos + 1)? != 0x00 {
+                    return None;
+                }
+                self.pos += 2;
+            } else {
+                self.pos += 1;
+            }
+            self.byte = byte;
+            self.bits_left = 8;
+        }
+        self.bits_left -= 1;
+        Some((self.byte >> self.bits_left) & 1)
+    }
+
+    fn receive(&mut self, bits: u8) -> Option<i32> {
+        let mut value = 0i32;
+        for _ in 0..bits {
+            value = (value << 1) | i32::from(self.bit()?);
+        }
+        Some(value)
+    }
+
+    /// Skips the RSTn marker expected after every restart interval.
+    fn restart(&mut self) -> Option<()> {
+        self.bits_left = 0;
+        while self.data.get(self.pos) == Some(&0xFF) && self.data.get(self.pos + 1) == Some(&0xFF) {
+            self.pos += 1;
+        }
+        match (self.data.get(self.pos), self.data.get(self.pos + 1)) {
+            (Some(0xFF), Some(0xD0..=0xD7)) => {
+                self.pos += 2;
+                Some(())
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Decodes one block and returns its DC difference; the AC coefficients are read and discarded.
+fn decode_block(reader: &mut BitReader, dc: &HuffmanTable, ac: &HuffmanTable) -> Option<i32> {
+    let size = dc.decode(reader)?;
+    if size > 11 {
+        return None;
+    }
+    let diff = extend(reader.receive(size)?, size);
+    let mut k = 1;
+    while k < 64 {
+        let rs = ac.decode(reader)?;
+        let (run, size) = (usize::from(rs >> 4), rs & 0x0F);
+        if size == 0 {
+            if run != 15 {
+                break; // end of block
+            }
+            k += 16;
+            continue;
+        }
+        k += run + 1;
+        reader.receive(size)?;
+    }
+    Some(diff)
+}
+
+/// Turns the `bits`-bit magnitude category value into a signed coefficient.
+fn extend(value: i32, bits: u8) -> i32 {
+    if bits == 0 || value >= 1 << (bits - 1) {
+        value
+    } else {
+        value - (1 << bits) + 1
+    }
+}
+
+fn read_u16(data: &[u8], pos: usize) -> Option<usize> {
+    Some(usize::from(*data.get(pos)?) << 8 | usize::from(*data.get(pos + 1)?))
+}
+
+/// Decodes the luma DC values of a baseline JPEG: one value per 8x8 block, row by row,
+/// with the block grid's width and height.
+fn decode_luma_blocks(data: &[u8]) -> Option<(Vec<f32>, usize, usize)> {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+    let mut pos = 2;
+    let mut frame: Option<Frame> = None;
+    let mut dc_tables: [Option<HuffmanTable>; 4] = Default::default();
+    let mut ac_tables: [Option<HuffmanTable>; 4] = Default::default();
+    let mut restart_interval = 0;
+
+    loop {
+        if *data.get(pos)? != 0xFF {
+            return None;
+        }
+        while *data.get(pos + 1)? == 0xFF {
+            pos += 1;
+        }
+        let marker = data[pos + 1];
+        pos += 2;
+        match marker {
+            0x01 | 0xD0..=0xD8 => continue,
+            0xD9 => return None,
+            _ => {}
+        }
+        let length = read_u16(data, pos)?;
+        if length < 2 {
+            return None;
+        }
+        let segment = data.get(pos + 2..pos + length)?;
+        pos += length;
+
+        match marker {
+            // Baseline and extended sequential Huffman frames
+            0xC0 | 0xC1 => frame = Some(parse_frame(segment)?),
+            // Progressive, lossless and arithmetic-coded frames
+            0xC2 | 0xC3 | 0xC5..=0xC7 | 0xC9..=0xCB | 0xCD..=0xCF => return None,
+            0xC4 => parse_huffman_tables(segment, &mut dc_tables, &mut ac_tables)?,
+            0xDD => restart_interval = read_u16(segment, 0)?,
+            0xDA => {
+                let frame = frame.as_ref()?;
+                let scan = parse_scan(segment, frame)?;
+                if scan.iter().any(|&(index, _, _)| index == 0) {
+                    let mut tables = Vec::with_capacity(scan.len());
+                    for &(index, dc, ac) in &scan {
+                        tables.push((index, dc_tables[dc].as_ref()?, ac_tables[ac].as_ref()?));
+                    }
+                    return decode_scan(&data[pos..], frame, &tables, restart_interval);
+                }
+                pos = skip_entropy_data(data, pos);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn parse_frame(segment: &[u8]) -> Option<Frame> {
+    let height = read_u16(segment, 1)?;
+    let width = read_u16(segment, 3)?;
+    let count = usize::from(*segment.get(5)?);
+    if width == 0 || height == 0 || width > MAX_DIMENSION || height > MAX_DIMENSION || count == 0 {
+        return None;
+    }
+    let mut components = Vec::with_capacity(count);
+    for i in 0..count {
+        let spec = segment.get(6 + i * 3..9 + i * 3)?;
+        let (h, v) = (usize::from(spec[1] >> 4), usize::from(spec[1] & 0x0F));
+        if !(1..=4).contains(&h) || !(1..=4).contains(&v) {
+            return None;
+        }
+        components.push(Component { id: spec[0], h, v });
+    }
+    Some(Frame { width, height, components })
+}
+
+fn parse_huffman_tables(
+    mut segment: &[u8],
+    dc_tables: &mut [Option<HuffmanTable>; 4],
+    ac_tables: &mut [Option<HuffmanTable>; 4],
+) -> Option<()> {
+    while !segment.is_empty() {
+        let (class, id) = (segment[0] >> 4, usize::from(segment[0] & 0x0F));
+        let counts = segment.get(1..17)?;
+        let total: usize = counts.iter().map(|&c| usize::from(c)).sum();
+        let table = HuffmanTable::new(counts, segment.get(17..17 + total)?)?;
+        match (class, id) {
+            (0, 0..=3) => dc_tables[id] = Some(table),
+            (1, 0..=3) => ac_tables[id] = Some(table),
+            _ => return None,
+        }
+        segment = &segment[17 + total..];
+    }
+    Some(())
+}
+
+/// Returns the scan's components as (frame component index, DC table id, AC table id).
+fn parse_scan(segment: &[u8], frame: &Frame) -> Option<Vec<(usize, usize, usize)>> {
+    let count = usize::from(*segment.first()?);
+    let mut scan = Vec::with_capacity(count);
+    for i in 0..count {
+        let spec = segment.get(1 + i * 2..3 + i * 2)?;
+        let index = frame.components.iter().position(|c| c.id == spec[0])?;
+        let (dc, ac) = (usize::from(spec[1] >> 4), usize::from(spec[1] & 0x0F));
+        if dc > 3 || ac > 3 {
+            return None;
+        }
+        scan.push((index, dc, ac));
+    }
+    Some(scan)
+}
+
+/// Returns the position of the marker that ends the entropy-coded data starting at `pos`.
+fn skip_entropy_data(data: &[u8], mut pos: usize) -> usize {
+    while pos + 1 < data.len() {
+        if data[pos] == 0xFF && !matches!(data[pos + 1], 0x00 | 0xD0..=0xD7) {
+            return pos;
+        }
+        pos += 1;
+    }
+    data.len()
+}
+
+/// Decodes a scan containing the luma component (frame component 0) and collects its DC values.
+fn decode_scan(
+    data: &[u8],
+    frame: &Frame,
+    tables: &[(usize, &HuffmanTable, &HuffmanTable)],
+    restart_interval: usize,
+) -> Option<(Vec<f32>, usize, usize)> {
+    let h_max = frame.components.iter().map(|c| c.h).max()?;
+    let v_max = frame.components.iter().map(|c| c.v).max()?;
+    let luma = &frame.components[0];
+    let blocks_w = (frame.width * luma.h).div_ceil(h_max).div_ceil(8);
+    let blocks_h = (frame.height * luma.v).div_ceil(v_max).div_ceil(8);
+
+    // A single-component scan codes one block per MCU; an interleaved one h x v blocks per component
+    let (mcus_x, mcus_y, grid_w, grid_h) = if tables.len() == 1 {
+        (blocks_w, blocks_h, blocks_w, blocks_h)
+    } else {
+        let mcus_x = frame.width.div_ceil(8 * h_max);
+        let mcus_y = frame.height.div_ceil(8 * v_max);
+        (mcus_x, mcus_y, mcus_x * luma.h, mcus_y * luma.v)
+    };
+    let blocks_per_mcu = |index: usize| {
+        if tables.len() == 1 {
+            (1, 1)
+        } else {
+            (frame.components[index].h, frame.components[index].v)
+        }
+    };
+
+    let mut reader = BitReader { data, pos: 0, byte: 0, bits_left: 0 };
+    let mut predictions = vec![0i32; frame.components.len()];
+    let mut grid = vec![0f32; grid_w * grid_h];
+    for mcu in 0..mcus_x * mcus_y {
+        if restart_interval > 0 && mcu > 0 && mcu % restart_interval == 0 {
+            reader.restart()?;
+            predictions.iter_mut().for_each(|p| *p = 0);
+        }
+        let (mcu_x, mcu_y) = (mcu % mcus_x, mcu / mcus_x);
+        for &(index, dc, ac) in tables {
+            let (h, v) = blocks_per_mcu(index);
+            for block_y in 0..v {
+                for block_x in 0..h {
+                    predictions[index] += decode_block(&mut reader, dc, ac)?;
+                    if index == 0 {
+                        let (x, y) = (mcu_x * h + block_x, mcu_y * v + block_y);
+                        grid[y * grid_w + x] = predictions[index] as f32;
+                    }
+                }
+            }
+        }
+    }
+
+    // Interleaved MCUs may pad the grid past the image edge
+    let pixels = (0..blocks_h)
+        .flat_map(|y| grid[y * grid_w..y * grid_w + blocks_w].iter().copied())
+        .collect();
+    Some((pixels, blocks_w, blocks_h))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Standard luminance DC table (JPEG spec K.3): code lengths per size category 0..=11.
+    const DC_COUNTS: [u8; 16] = [0, 1, 5, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0];
+    const DC_VALUES: [u8; 12] = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11];
+
+    struct BitWriter {
+        out: Vec<u8>,
+        byte: u8,
+        bits: u8,
+    }
+
+    impl BitWriter {
+        fn put(&mut self, value: u32, len: u8) {
+            for i in (0..len).rev() {
+                self.byte = (self.byte << 1) | ((value >> i) & 1) as u8;
+                self.bits += 1;
+                if self.bits == 8 {
+                    self.out.push(self.byte);
+                    if self.byte == 0xFF {
+                        self.out.push(0x00);
+                    }
+                    self.byte = 0;
+                    self.bits = 0;
+                }
+            }
+        }
+
+        /// Pads the last byte with 1 bits, as encoders do before a marker.
+        fn flush(&mut self) {
+            while self.bits != 0 {
+                self.put(1, 1);
+            }
+        }
+    }
+
+    /// Canonical Huffman code (code, length) of every DC size category.
+    fn dc_codes() -> Vec<(u32, u8)> {
+        let mut codes = Vec::new();
+        let mut code = 0u32;
+        for (len, &count) in DC_COUNTS.iter().enumerate() {
+            for _ in 0..count {
+                codes.push((code, len as u8 + 1));
+                code += 1;
+            }
+            code <<= 1;
+        }
+        codes
+    }
+
+    /// Encodes a grayscale baseline JPEG whose 8x8 blocks are flat, with the given DC values
+    /// (`blocks_w` per row). `sof` is the frame marker; `restart_interval` adds DRI and RSTn markers.
+    fn encode_jpeg(dc_values: &[i32], blocks_w: usize, sof: u8, restart_interval: usize) -> Vec<u8> {
+        let blocks_h = dc_values.len() / blocks_w;
+        let (width, height) = (blocks_w * 8, blocks_h * 8);
+        let mut jpeg = vec![0xFF, 0xD8];
+        if restart_interval > 0 {
+            jpeg.extend([0xFF, 0xDD, 0x00, 0x04, (restart_interval >> 8) as u8, restart_interval as u8]);
+        }
+        jpeg.extend([0xFF, sof, 0x00, 0x0B, 0x08]);
+        jpeg.extend([(height >> 8) as u8, height as u8, (width >> 8) as u8, width as u8]);
+        jpeg.extend([0x01, 0x01, 0x11, 0x00]);
+        // DC table 0, and AC table 0 with the end-of-block symbol only
+        jpeg.extend([0xFF, 0xC4, 0x00, (2 + 17 + DC_VALUES.len()) as u8, 0x00]);
+        jpeg.extend(DC_COUNTS);
+        jpeg.extend(DC_VALUES);
+        jpeg.extend([0xFF, 0xC4, 0x00, 0x14, 0x10, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x00]);
+        jpeg.extend([0xFF, 0xDA, 0x00, 0x08, 0x01, 0x01, 0x00, 0x00, 0x3F, 0x00]);
+
+        let codes = dc_codes();
+        let mut writer = BitWriter { out: Vec::new(), byte: 0, bits: 0 };
+        let mut prediction = 0;
+        for (i, &dc) in dc_values.iter().enumerate() {
+            if restart_interval > 0 && i > 0 && i % restart_interval == 0 {
+                writer.flush();
+                writer.out.extend([0xFF, 0xD0 + ((i / restart_interval - 1) % 8) as u8]);
+                prediction = 0;
+            }
+            let diff = dc - prediction;
+            prediction = dc;
+            let size = (32 - diff.unsigned_abs().leading_zeros()) as u8;
+            let (code, len) = codes[size as usize];
+            writer.put(code, len);
+            let bits = if diff < 0 { diff - 1 } else { diff };
+            writer.put(bits as u32 & ((1u32 << size) - 1), size);
+            writer.put(0, 1); // end of block
+        }
+        writer.flush();
+        jpeg.extend(writer.out);
+        jpeg.extend([0xFF, 0xD9]);
+        jpeg
+    }
+
+    /// A 9x8 block grid whose dHash is `hash`: each row steps down for a 1 bit and up for a 0 bit.
+    fn grid_for_hash(hash: u64) -> Vec<i32> {
+        let mut grid = Vec::with_capacity(HASH_WIDTH * HASH_HEIGHT);
+        for y in 0..HASH_HEIGHT {
+            let mut value = 0;
+            grid.push(value);
+            for x in 0..HASH_WIDTH - 1 {
+                let bit = hash >> (63 - (y * 8 + x)) & 1;
+                value += if bit == 1 { -12 } else { 12 };
+                grid.push(value);
+            }
+        }
+        grid
+    }
+
+    const KNOWN_HASH: u64 = 0x0123_4567_89AB_CDEF;
+
+    #[test]
+    fn baseline_jpeg_has_the_expected_hash() {
+        let jpeg = encode_jpeg(&grid_for_hash(KNOWN_HASH), HASH_WIDTH, 0xC0, 0);
+        assert_eq!(dhash(&jpeg), Some(KNOWN_HASH));
+        assert_eq!(dhash(&encode_jpeg(&grid_for_hash(!0), HASH_WIDTH, 0xC0, 0)), Some(!0));
+        assert_eq!(dhash(&encode_jpeg(&grid_for_hash(0), HASH_WIDTH, 0xC0, 0)), Some(0));
+    }
+
+    #[test]
+    fn restart_intervals_are_decoded() {
+        let grid = grid_for_hash(KNOWN_HASH);
+        for interval in [1, 2, 5, 9, 100] {
+            assert_eq!(dhash(&encode_jpeg(&grid, HASH_WIDTH, 0xC0, interval)), Some(KNOWN_HASH), "interval {}", interval);
+        }
+    }
+
+    #[test]
+    fn larger_images_are_shrunk_before_hashing() {
+        // Every block of the 9x8 grid repeated 2x2
+        let small = grid_for_hash(KNOWN_HASH);
+        let large: Vec<i32> = (0..HASH_HEIGHT * 2)
+            .flat_map(|y| (0..HASH_WIDTH * 2).map(move |x| (y, x)))
+            .map(|(y, x)| small[(y / 2) * HASH_WIDTH + x / 2])
+            .collect();
+        assert_eq!(dhash(&encode_jpeg(&large, HASH_WIDTH * 2, 0xC0, 0)), Some(KNOWN_HASH));
+    }
+
+    #[test]
+    fn slightly_changed_images_stay_close() {
+        let original = dhash(&encode_jpeg(&grid_for_hash(KNOWN_HASH), HASH_WIDTH, 0xC0, 0)).unwrap();
+        // Brightening the whole image keeps every comparison
+        let brighter: Vec<i32> = grid_for_hash(KNOWN_HASH).iter().map(|v| v + 40).collect();
+        assert_eq!(dhash(&encode_jpeg(&brighter, HASH_WIDTH, 0xC0, 0)), Some(original));
+        // Flipping one comparison changes one bit
+        let edited = dhash(&encode_jpeg(&grid_for_hash(KNOWN_HASH ^ 1), HASH_WIDTH, 0xC0, 0)).unwrap();
+        assert_eq!(hamming_distance(original, edited), 1);
+    }
+
+    #[test]
+    fn unsupported_or_broken_input_is_none() {
+        let grid = grid_for_hash(KNOWN_HASH);
+        let jpeg = encode_jpeg(&grid, HASH_WIDTH, 0xC0, 0);
+
+        // Progressive
+        assert_eq!(dhash(&encode_jpeg(&grid, HASH_WIDTH, 0xC2, 0)), None);
+        // Truncated in the headers and in the scan data
+        assert_eq!(dhash(&jpeg[..20]), None);
+        assert_eq!(dhash(&jpeg[..jpeg.len() - 20]), None);
+        // Missing restart marker
+        let mut restarted = encode_jpeg(&grid, HASH_WIDTH, 0xC0, 4);
+        let rst = restarted.windows(2).position(|w| w == [0xFF, 0xD0]).unwrap();
+        restarted.drain(rst..rst + 2);
+        assert_eq!(dhash(&restarted), None);
+        // Not a JPEG at all
+        assert_eq!(dhash(b""), None);
+        assert_eq!(dhash(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"), None);
+        assert_eq!(dhash(&[0xFF, 0xD8, 0x12, 0x34, 0x56]), None);
+        assert_eq!(dhash(&[0xFF, 0xD8, 0xFF, 0xD9]), None);
+        let garbage: Vec<u8> = (0..4096u32).map(|i| (i.wrapping_mul(2_654_435_761) >> 13) as u8).collect();
+        assert_eq!(dhash(&garbage), None);
+        assert_eq!(dhash(&[&[0xFF, 0xD8][..], &garbage].concat()), None);
+    }
+
+    #[test]
+    fn hamming_distance_counts_differing_bits() {
+        assert_eq!(hamming_distance(0, 0), 0);
+        assert_eq!(hamming_distance(KNOWN_HASH, KNOWN_HASH), 0);
+        assert_eq!(hamming_distance(0b1011, 0b0001), 2);
+        assert_eq!(hamming_distance(0, u64::MAX), 64);
+        assert_eq!(hamming_distance(KNOWN_HASH, !KNOWN_HASH), 64);
+    }
+}
@@ -6,25 +6,39 @@ mod analyzer;
 mod normalizer;
 mod notifier;
 mod storage;
+mod http_server;
+mod metrics;
+mod deal_log;
+mod image_hash;
+mod api;
+#[cfg(test)]
+mod test_support;
 
 use analyzer::AnalyzerImpl;
-use notifier::TelegramNotifier;
-use crate::analyzer::price_analysis::Analyzer;
-use config::{load_config, AppConfig, ModelConfig};
-use model::ScrapeRequest;
-use scraper::{Scraper, ScraperImpl};
+use notifier::{Notifier, TelegramNotifier, WebhookNotifier};
+use crate::analyzer::price_analysis::{is_price_drop, Analyzer};
+use config::{load_config, AppConfig, ModelConfig, NotifierKind, SharedConfig};
+use model::{Offer, ScrapeRequest};
+use scraper::{CircuitBreaker, Scraper, ScraperImpl};
 use parser::KleinanzeigenParser;
-use normalizer::normalize_all;
+use normalizer::{content_hash, normalize_all};
 use storage::SqliteStorage;
+use metrics::METRICS;
 use std::collections::HashSet;
 use std::fs;
+use std::future::Future;
 use std::path::Path;
 use std::sync::Arc;
-use tokio::sync::{Mutex, Notify};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+use tokio::sync::Notify;
 use tokio::time::{sleep, Duration};
-use tracing::{error, info, warn};
+use tracing::{debug, error, info, warn};
 use tracing_subscriber;
-use futures::future::join_all;
+use futures::stream::{self, StreamExt};
+
+/// A deal for the same item (a repost or the same content hash) as an offer notified within this many hours is suppressed.
+const REPOST_COOLDOWN_HOURS: i64 = 24;
 
 #[tokio::main]
 async fn main() {
@@ -36,66 +50,151 @@ async fn main() {
         eprintln!("😱 Panic occurred: {:?}", panic_info);
     }));
 
-    // Load configuration from file
-    let config: Arc<AppConfig> = match load_config("config.json") {
-        Ok(cfg) => Arc::new(cfg),
+    // Load configuration from file; /reload swaps it at runtime
+    let shared_config = match load_config("config.json") {
+        Ok(cfg) => Arc::new(SharedConfig::new("config.json", cfg)),
         Err(e) => {
             error!("Config load error: {}", e);
             return;
         }
     };
+    let mut config = shared_config.load();
 
     // Create the base scraper instance
-    let base_scraper = ScraperImpl::new();
-    let parser = KleinanzeigenParser::new();
+    let mut base_scraper = match ScraperImpl::new(&config) {
+        Ok(s) => s,
+        Err(e) => {
+            error!("Failed to initialize scraper: {}", e);
+            return;
+        }
+    };
+    let mut parser = match KleinanzeigenParser::new() {
+        Ok(p) => p.with_number_format(config.number_format),
+        Err(e) => {
+            error!("Failed to initialize parser: {:?}", e);
+            return;
+        }
+    };
     let analyzer = AnalyzerImpl::new();
+    let breaker = CircuitBreaker::new();
 
-    // Initialize storage (SQLite) with async access (wrapped in a Mutex)
+    // Initialize storage (SQLite); it pools connections internally, so it is shared without a lock
     let storage = match SqliteStorage::new("data.db") {
-        Ok(s) => Arc::new(Mutex::new(s)),
+        Ok(s) => Arc::new(s),
         Err(e) => {
             error!("Failed to initialize storage: {:?}", e);
             return;
         }
     };
 
-    // Initialize notifier (Telegram) and refresh notifier
+    // `import-html <file> <model>` seeds the database from a saved page and exits
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("import-html") {
+        let (Some(path), Some(model)) = (args.get(1), args.get(2)) else {
+            error!("Usage: klein-sniper import-html <file> <model>");
+            std::process::exit(2);
+        };
+        match import_html(Path::new(path), model, &config, &parser, &storage) {
+            Ok(count) => info!("📥 Imported {} offers from {}", count, path),
+            Err(e) => {
+                error!("Import failed: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    // Initialize the Telegram bot (commands, digest), the alert notifier and the refresh notifier
     let refresh_notify = Arc::new(Notify::new());
-    let notifier = Arc::new(TelegramNotifier::new(
+    let telegram = Arc::new(TelegramNotifier::new(
         config.telegram_bot_token.clone(),
-        config.telegram_chat_id,
         storage.clone(),
-        config.clone(),
+        shared_config.clone(),
         refresh_notify.clone(),
     ));
+    let telegram_enabled = !config.telegram_bot_token.trim().is_empty();
+    let notifier: Arc<dyn Notifier> = match config.notifier {
+        NotifierKind::Telegram => telegram.clone(),
+        NotifierKind::Webhook => {
+            info!("📡 Sending notifications to the configured webhook");
+            Arc::new(WebhookNotifier::new(storage.clone(), shared_config.clone()))
+        }
+    };
+
+    // `--once`: a single pass for cron / systemd timers, without the listener, digest or metrics
+    if args.iter().any(|a| a == "--once") {
+        let failed = run_pass(&base_scraper, &parser, &analyzer, &storage, &config, &notifier, &breaker).await;
+        if let Err(e) = storage.flush() {
+            warn!("Storage flush failed: {:?}", e);
+        }
+        if failed > 0 {
+            error!("{} model(s) failed in this pass", failed);
+            std::process::exit(1);
+        }
+        info!("Single pass finished.");
+        return;
+    }
+
+    // Spawn listener for manual refresh (e.g. via /refresh command); without a bot token there are no commands
+    let listener_handle = telegram_enabled.then(|| TelegramNotifier::spawn_listener(telegram.clone()));
+
+    // Expose Prometheus metrics and the health check if configured
+    let metrics_handle = config
+        .metrics_listen_addr
+        .clone()
+        .map(|addr| tokio::spawn(metrics::serve_metrics(addr, shared_config.clone(), storage.clone())));
+
+    // Serve the read-only JSON API if enabled
+    let api_handle = config.api_enabled.then(|| {
+        let addr = format!("{}:{}", config.api_host, config.api_port);
+        tokio::spawn(api::serve_api(addr, shared_config.clone(), storage.clone()))
+    });
+
+    // Schedule the daily digest if enabled
+    let digest_handle = config
+        .digest_enabled
+        .then(|| tokio::spawn(notifier::telegram::digest::run_digest_scheduler(telegram.clone())));
+
+    // Watch for SIGINT/SIGTERM; the main loop checks the flag between cycles
+    // so a model that is being processed is always finished first.
+    let shutdown = Shutdown::watch(wait_for_shutdown_signal());
 
-    // Spawn listener for manual refresh (e.g. via /refresh command)
-    TelegramNotifier::spawn_listener(notifier.clone());
+    if config.dry_run {
+        warn!("🧪 DRY RUN: deals are only logged, no offer notifications will be sent");
+    }
 
     info!("Sending startup message...");
-    if let Err(e) = notifier.notify_text("🚀 KleinSniper started!").await {
+    let startup_msg = if config.dry_run {
+        "🚀 KleinSniper started! (🧪 dry run)"
+    } else {
+        "🚀 KleinSniper started!"
+    };
+    if let Err(e) = notifier.notify_text(startup_msg).await {
         warn!("Startup notification failed: {:?}", e);
     }
 
     // Main processing loop
     loop {
         info!("Entering main loop...");
-        info!("Models to process: {}", config.models.len());
-
-        // Process all models concurrently
-        let tasks: Vec<_> = config.models.iter().map(|model_cfg| {
-            process_model(
-                model_cfg,
-                &base_scraper,
-                &parser,
-                &analyzer,
-                storage.clone(),
-                config.clone(),
-                refresh_notify.clone(),
-                notifier.clone(),
-            )
-        }).collect();
-        join_all(tasks).await;
+
+        // Pick up a configuration swapped in by /reload
+        let latest = shared_config.load();
+        if !Arc::ptr_eq(&latest, &config) {
+            match ScraperImpl::new(&latest) {
+                Ok(s) => {
+                    info!("🔁 Applying reloaded configuration");
+                    base_scraper = s;
+                    parser = parser.with_number_format(latest.number_format);
+                    config = latest;
+                }
+                Err(e) => warn!("Reloaded config rejected by scraper, keeping the old one: {}", e),
+            }
+        }
+        run_pass(&base_scraper, &parser, &analyzer, &storage, &config, &notifier, &breaker).await;
+
+        if shutdown.is_requested() {
+            break;
+        }
 
         info!(
             "Waiting for timer ({}s) or manual refresh...",
@@ -108,98 +207,336 @@ async fn main() {
             _ = refresh_notify.notified() => {
                 info!("Manual refresh triggered.");
             }
+            _ = shutdown.wait() => {
+                break;
+            }
         }
         info!("Restarting main loop...");
     }
+
+    info!("Shutting down...");
+    for handle in [listener_handle, metrics_handle, api_handle, digest_handle].into_iter().flatten() {
+        handle.abort();
+    }
+
+    if let Err(e) = storage.flush() {
+        warn!("Storage flush failed: {:?}", e);
+    }
+
+    if let Err(e) = notifier.notify_text("🛑 KleinSniper stopping").await {
+        warn!("Shutdown notification failed: {:?}", e);
+    }
+    info!("Bye.");
 }
 
-/// Processes a single model, performing scraping, parsing, normalization, analysis and notifications.
-/// The functionality remains the same as in the original main loop.
-async fn process_model(
-    model_cfg: &ModelConfig,
+/// Runs one full pass over all enabled models (scrape, analyze, notify) and applies retention.
+/// Does nothing while paused by /pause_all. Returns the number of models that failed.
+async fn run_pass(
     base_scraper: &ScraperImpl,
     parser: &KleinanzeigenParser,
     analyzer: &AnalyzerImpl,
-    storage: Arc<Mutex<SqliteStorage>>,
+    storage: &Arc<SqliteStorage>,
+    config: &Arc<AppConfig>,
+    notifier: &Arc<dyn Notifier>,
+    breaker: &CircuitBreaker,
+) -> usize {
+    if notifier.is_paused() {
+        info!("⏸ Paused via /pause_all, skipping this pass");
+        return 0;
+    }
+    let disabled = storage.get_disabled_models().unwrap_or_else(|e| {
+        warn!("Loading disabled models failed: {:?}", e);
+        HashSet::new()
+    });
+    if !disabled.is_empty() {
+        info!("Skipping disabled models: {:?}", disabled);
+    }
+    let active_models: Vec<&ModelConfig> =
+        config.models.iter().filter(|m| !disabled.contains(&m.query)).collect();
+    info!("Models to process: {}", active_models.len());
+
+    // Process models concurrently, at most `max_concurrent_models` at a time
+    let ctx = PassContext { base_scraper, parser, analyzer, breaker };
+    let tasks = active_models
+        .into_iter()
+        .map(|model_cfg| process_model(model_cfg, &ctx, storage.clone(), config.clone(), notifier.clone()));
+    let failed = stream::iter(tasks)
+        .buffer_unordered(config.max_concurrent_models)
+        .filter(|ok| futures::future::ready(!ok))
+        .count()
+        .await;
+
+    if let Some(days) = config.retention_days {
+        apply_retention(storage, days);
+    }
+    failed
+}
+
+/// Parses a saved search page (e.g. a `logs/html/` dump) with the filters of `model`
+/// and stores the offers, without any network access. Returns the number of saved offers.
+fn import_html(
+    path: &Path,
+    model: &str,
+    config: &AppConfig,
+    parser: &KleinanzeigenParser,
+    storage: &SqliteStorage,
+) -> Result<usize, String> {
+    let model_cfg = config
+        .models
+        .iter()
+        .find(|m| m.query.eq_ignore_ascii_case(model))
+        .ok_or_else(|| format!("unknown model '{}'", model))?;
+    let html = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let (mut offers, report) = parser
+        .parse_filtered(&html, model_cfg)
+        .map_err(|e| format!("parse error: {:?}", e))?;
+    info!("📄 {}: {}", path.display(), report);
+
+    let unknown = normalize_all(&mut offers, &config.models);
+    if config.keep_unknown {
+        offers.extend(unknown);
+    } else if !unknown.is_empty() {
+        info!("Skipping {} offers that match no model", unknown.len());
+    }
+
+    let mut saved = 0;
+    for offer in &offers {
+        match storage.save_offer(offer) {
+            Ok(_) => saved += 1,
+            Err(e) => warn!("DB save error for {}: {:?}", offer.id, e),
+        }
+    }
+    Ok(saved)
+}
+
+/// Deletes offers and notification records older than `days` days.
+fn apply_retention(storage: &SqliteStorage, days: u32) {
+    match storage.purge_offers_older_than(days) {
+        Ok(0) => {}
+        Ok(n) => info!("🧹 Purged {} offers older than {} days", n, days),
+        Err(e) => warn!("Offer purge failed: {:?}", e),
+    }
+    match storage.purge_notified_older_than(days) {
+        Ok(0) => {}
+        Ok(n) => info!("🧹 Purged {} stale notification records", n),
+        Err(e) => warn!("Notified purge failed: {:?}", e),
+    }
+}
+
+/// Shutdown requested by a signal: a flag the main loop checks between passes,
+/// plus a wake-up for the wait between them.
+#[derive(Default)]
+struct Shutdown {
+    requested: AtomicBool,
+    notify: Notify,
+}
+
+impl Shutdown {
+    /// Spawns a task that requests shutdown once `signal` resolves.
+    fn watch(signal: impl Future<Output = ()> + Send + 'static) -> Arc<Self> {
+        let shutdown = Arc::new(Self::default());
+        let watcher = shutdown.clone();
+        tokio::spawn(async move {
+            signal.await;
+            info!("🛑 Shutdown signal received, finishing current work...");
+            watcher.requested.store(true, Ordering::SeqCst);
+            watcher.notify.notify_one();
+        });
+        shutdown
+    }
+
+    fn is_requested(&self) -> bool {
+        self.requested.load(Ordering::SeqCst)
+    }
+
+    /// Resolves once shutdown is requested, including when that happened before the call.
+    async fn wait(&self) {
+        if !self.is_requested() {
+            self.notify.notified().await;
+        }
+    }
+}
+
+/// Resolves on Ctrl-C (SIGINT) or, on Unix, SIGTERM.
+async fn wait_for_shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            warn!("Failed to listen for Ctrl-C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                warn!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
+
+/// The collaborators every model of a pass shares.
+struct PassContext<'a> {
+    base_scraper: &'a ScraperImpl,
+    parser: &'a KleinanzeigenParser,
+    analyzer: &'a AnalyzerImpl,
+    breaker: &'a CircuitBreaker,
+}
+
+/// Processes a single model, performing scraping, parsing, normalization, analysis and notifications.
+/// Returns false when the model was skipped or its page could not be fetched or parsed.
+async fn process_model(
+    model_cfg: &ModelConfig,
+    ctx: &PassContext<'_>,
+    storage: Arc<SqliteStorage>,
     config: Arc<AppConfig>,
-    _refresh_notify: Arc<Notify>,
-    notifier: Arc<TelegramNotifier>,
-) {
+    notifier: Arc<dyn Notifier>,
+) -> bool {
+    let PassContext { base_scraper, parser, analyzer, breaker } = *ctx;
     info!("Processing model: {}", model_cfg.query);
+    if let Some(remaining) = breaker.open_for(&model_cfg.query) {
+        info!("⏸ Circuit open for {}, skipping for another {}s", model_cfg.query, remaining.as_secs());
+        return false;
+    }
     let request = ScrapeRequest {
         query: model_cfg.query.clone(),
         category_id: model_cfg.category_id.clone(),
     };
 
     // Create a scraper instance for the current model (cloning the client)
-    let scraper = ScraperImpl {
-        client: base_scraper.client.clone(),
-        category_id: model_cfg.category_id.clone(),
-        min_price: model_cfg.min_price,
-        max_price: model_cfg.max_price,
-    };
+    let scraper = base_scraper.for_model(model_cfg);
 
     // Optionally, retrieve previous stats from storage for logging
-    {
-        let storage_guard = storage.lock().await;
-        if let Ok(Some(prev_stats)) = storage_guard.get_stats(&model_cfg.query) {
-            info!(
-                "Previous stats: {:.2} € | Updated: {}",
-                prev_stats.avg_price, prev_stats.last_updated
-            );
-        }
+    if let Ok(Some(prev_stats)) = storage.get_stats(&model_cfg.query) {
+        info!(
+            "Previous stats: {:.2} € | Updated: {}",
+            prev_stats.avg_price, prev_stats.last_updated
+        );
     }
 
     info!("Fetching offers...");
+    let run_started_at = chrono::Utc::now();
+    let run_timer = Instant::now();
     // Fetch HTML page for the current request
-    let html = match scraper.fetch(&request).await {
+    let fetched = scraper.fetch(&request).await;
+    breaker.record(
+        &model_cfg.query,
+        fetched.as_ref().map(|_| ()),
+        config.circuit_breaker_threshold,
+        Duration::from_secs(config.circuit_breaker_cooldown_secs),
+    );
+    let html = match fetched {
         Ok(html) => html,
         Err(model::ScraperError::InvalidResponse(html)) => {
             log_and_save_html(&html, &model_cfg.query);
-            return;
+            return false;
+        }
+        Err(model::ScraperError::Blocked) => {
+            warn!("⛔ Blocked by Kleinanzeigen (captcha / access denied), skipping model {}", model_cfg.query);
+            return false;
+        }
+        Err(e @ model::ScraperError::HtmlParseError(_)) => {
+            // No listings on the first page: a quiet market or broken selectors / category id
+            warn!("Scraper error: {:?}", e);
+            track_empty_runs(&model_cfg.query, 0, &config, &storage, notifier.as_ref()).await;
+            return false;
         }
         Err(e) => {
             warn!("Scraper error: {:?}", e);
-            return;
+            return false;
         }
     };
 
     info!("Parsing HTML...");
     // Parse offers from the HTML
     let mut offers = match parser.parse_filtered(&html, model_cfg) {
-        Ok(o) => o,
+        Ok((offers, report)) => {
+            if offers.is_empty() && report.total_items > 0 {
+                warn!("⚠️ {}: every listing was skipped: {}", model_cfg.query, report);
+            }
+            offers
+        }
         Err(e) => {
             log_and_save_html(&html, &model_cfg.query);
             warn!("Parse error: {:?}", e);
-            return;
+            return false;
+        }
+    };
+
+    let watched = watched_seller_offers(parser, &html, model_cfg, &config);
+
+    METRICS.add_offers_scraped(offers.len());
+    let scraped_count = offers.len();
+    track_empty_runs(&model_cfg.query, scraped_count, &config, &storage, notifier.as_ref()).await;
+    let record_run = || {
+        let duration_ms = run_timer.elapsed().as_millis() as u64;
+        info!("⏱ {}: {} offers in {} ms", model_cfg.query, scraped_count, duration_ms);
+        if let Err(e) = storage.record_scrape_run(&model_cfg.query, run_started_at, duration_ms, scraped_count) {
+            warn!("Recording scrape run failed: {:?}", e);
         }
     };
 
     // Normalize offers based on configuration settings
-    normalize_all(&mut offers, &config.models);
+    let unknown = normalize_all(&mut offers, &config.models);
+    if !unknown.is_empty() {
+        if config.keep_unknown {
+            info!("Keeping {} offers that match no model", unknown.len());
+            offers.extend(unknown);
+        } else {
+            info!("Skipping {} offers that match no model", unknown.len());
+            for offer in &unknown {
+                debug!("Unmatched offer: {} — {}", offer.id, offer.title);
+            }
+        }
+    }
 
     // Save offers into storage and record seen IDs
     let mut seen_ids = HashSet::new();
+    let mut price_drops = Vec::new();
     for offer in &offers {
         seen_ids.insert(offer.id.clone());
-        if let Err(e) = storage.lock().await.save_offer(offer) {
-            warn!("DB save error: {:?}", e);
+        match storage.save_offer(offer) {
+            Ok(Some(previous))
+                if is_price_drop(previous, offer.price, config.price_drop_min_amount, config.price_drop_min_percent) =>
+            {
+                price_drops.push((offer, previous));
+            }
+            Ok(_) => {}
+            Err(e) => warn!("DB save error: {:?}", e),
         }
     }
+    notify_price_drops(&price_drops, &config, &storage, notifier.as_ref()).await;
+    notify_watched_sellers(&watched, &config, &storage, notifier.as_ref()).await;
     let seen_vec: Vec<String> = seen_ids.into_iter().collect();
 
-    info!("Cleaning up old offers for model {}...", model_cfg.query);
-    if let Err(e) = storage
-        .lock()
-        .await
-        .delete_missing_offers_for_model(&model_cfg.query, &seen_vec)
+    info!("Marking disappeared offers for model {}...", model_cfg.query);
+    if let Err(e) = storage.mark_missing_offers_for_model(&model_cfg.query, &seen_vec)
     {
-        warn!("Delete missing error: {:?}", e);
+        warn!("Mark missing error: {:?}", e);
     }
 
     // Perform asynchronous extended analysis of the offers
     info!("Performing extended asynchronous analysis...");
-    let analysis_result = analyzer.analyze_offers(&offers).await;
+    let history = match storage.get_price_history_for_model(&model_cfg.query) {
+        Ok(h) => h,
+        Err(e) => {
+            warn!("Price history load error: {:?}", e);
+            Vec::new()
+        }
+    };
+    let analysis_result = analyzer.analyze_offers(&history).await;
     info!("Advanced Analysis Results:");
     for (range, duration) in analysis_result.disappearance_map.iter() {
         info!(
@@ -211,36 +548,98 @@ async fn process_model(
     }
     info!("Price Change Frequency: {}", analysis_result.price_change_frequency);
     info!("RSI: {}", analysis_result.rsi);
+    if let Some((macd, signal)) = analysis_result.macd {
+        let trend = if macd < signal { "downward" } else { "upward" };
+        info!("MACD: {:.2} | Signal: {:.2} | Trend: {}", macd, signal, trend);
+    }
+
+    // Implausibly cheap listings (scams, typos) never count for stats or deals
+    let (offers, suspicious): (Vec<_>, Vec<_>) =
+        offers.into_iter().partition(|o| !model_cfg.is_suspicious_price(o.price));
+    for offer in &suspicious {
+        info!("Suspicious price ignored: {} — {:.2} € ({})", offer.id, offer.price, offer.title);
+    }
+
+    // Drop extreme prices so a single mispriced listing doesn't skew the stats; deals still see every offer
+    let stats_offers = match model_cfg.outlier_sigma {
+        Some(sigma) => {
+            let (kept, outliers) = analyzer.reject_outliers(&offers, sigma);
+            for outlier in &outliers {
+                info!(
+                    "Outlier ignored for stats: {} — {:.2} € ({})",
+                    outlier.id, outlier.price, outlier.title
+                );
+            }
+            kept
+        }
+        None => offers.clone(),
+    };
 
-    // Calculate basic statistics for the offers
-    let stats = analyzer.calculate_stats(&offers);
+    // Calculate basic statistics for the offers, recency-weighted when a half-life is configured
+    let stats = match config.stats_half_life_days {
+        Some(days) => {
+            let half_life = chrono::Duration::seconds((days * 86_400.0).round() as i64);
+            analyzer.calculate_weighted_stats(&stats_offers, half_life)
+        }
+        None => analyzer.calculate_stats(&stats_offers),
+    };
+    let Some(stats) = stats else {
+        warn!("No priced offers for {}, skipping stats and deals", model_cfg.query);
+        record_run();
+        METRICS.set_model_last_run(&model_cfg.query);
+        return true;
+    };
     info!(
-        "Base Stats: avg = {:.2}, std_dev = {:.2}",
-        stats.avg_price, stats.std_dev
+        "Base Stats: avg = {:.2}, std_dev = {:.2}, median = {:.2}, p25 = {:.2}, p75 = {:.2}",
+        stats.avg_price, stats.std_dev, stats.median_price, stats.p25, stats.p75
     );
 
     info!("Updating stats in storage...");
-    if let Err(e) = storage.lock().await.update_stats(&stats) {
+    if let Err(e) = storage.update_stats(&stats) {
         warn!("Stats update failed: {:?}", e);
     }
 
-    info!("Notifying cheapest offers...");
-    TelegramNotifier::check_and_notify_cheapest_for_model(
-        &model_cfg.query,
-        storage.clone(),
-        notifier.clone(),
-    )
-    .await;
+    if config.dry_run {
+        info!("🧪 [dry run] Skipping cheapest-offer notification for {}", model_cfg.query);
+    } else if config.digest_only {
+        info!("📰 Digest-only mode, skipping cheapest-offer notification for {}", model_cfg.query);
+    } else {
+        info!("Notifying cheapest offers...");
+        notifier::cheapest::check_and_notify_cheapest_for_model(
+            &model_cfg.query,
+            &storage,
+            &config,
+            notifier.as_ref(),
+        )
+        .await;
+    }
 
     // Find "good" offers using the analyzer's deal finding method
-    let good_offers = analyzer.find_deals(&offers, &stats, model_cfg);
-    info!("Found {} good offers", good_offers.len());
+    let mut deals = analyzer.find_deals(&offers, &stats, model_cfg);
+    match storage.get_ignored_ids() {
+        Ok(ignored) => deals.retain(|deal| !ignored.contains(&deal.offer.id)),
+        Err(e) => warn!("Loading ignored offers failed: {:?}", e),
+    }
+    info!("Found {} good offers", deals.len());
+    METRICS.add_deals_found(deals.len());
+    record_run();
 
-    // Process each good offer and send notifications if necessary
-    for offer in good_offers {
-        info!("Checking offer: {} — {:.2} €", offer.id, offer.price);
+    // Reposts (same item re-listed under a new id) of a recently notified offer are not notified again
+    let since = chrono::Utc::now() - chrono::Duration::hours(REPOST_COOLDOWN_HOURS);
+    let reposts = match storage.find_probable_reposts_for_model(&model_cfg.query, config.repost_price_window, since) {
+        Ok(r) => r,
+        Err(e) => {
+            warn!("Repost detection failed: {:?}", e);
+            Default::default()
+        }
+    };
 
-        match storage.lock().await.is_notified(&offer.id) {
+    // Process each good offer (best score first) and send notifications if necessary
+    for deal in deals {
+        let offer = &deal.offer;
+        info!("Checking offer: {} — {:.2} € (score {:.0})", offer.id, offer.price, deal.score);
+
+        match storage.is_notified(&offer.id) {
             Ok(true) => {
                 info!("Already notified: {}", offer.id);
                 continue;
@@ -252,17 +651,297 @@ async fn process_model(
             }
         }
 
-        info!("Sending Telegram notification...");
-        if let Err(e) = notifier.notify(&offer).await {
-            warn!("Telegram send error: {:?}", e);
-        } else if let Err(e) = storage.lock().await.mark_notified(&offer.id) {
-            warn!("Mark notified failed: {:?}", e);
-        } else {
-            info!("Offer notified and marked.");
+        if let Some(original_id) = reposts.get(&offer.id) {
+            info!("♻️ Repost of already notified offer {}: {}", original_id, offer.id);
+            if !config.dry_run && let Err(e) = storage.mark_notified(&offer.id) {
+                warn!("Mark notified failed: {:?}", e);
+            }
+            continue;
+        }
+
+        // Reposts whose seller tweaked the title or price slightly still share the content hash
+        let hash = content_hash(offer, config.repost_price_window);
+        match storage.find_notified_by_hash(&hash, &offer.id, since) {
+            Ok(Some(original_id)) => {
+                info!("♻️ Same item as recently notified offer {}: {}", original_id, offer.id);
+                if !config.dry_run && let Err(e) = storage.mark_notified(&offer.id) {
+                    warn!("Mark notified failed: {:?}", e);
+                }
+                continue;
+            }
+            Ok(None) => {}
+            Err(e) => warn!("Content hash check failed: {:?}", e),
+        }
+
+        if config.image_dedup
+            && let Some(original_id) =
+                find_image_repost(offer, &scraper, &storage, config.image_hash_max_distance, since).await
+        {
+            info!("🖼 Same photo as recently notified offer {}: {}", original_id, offer.id);
+            if !config.dry_run && let Err(e) = storage.mark_notified(&offer.id) {
+                warn!("Mark notified failed: {:?}", e);
+            }
+            continue;
+        }
+
+        if let Some(path) = config.deal_log_path.as_deref() {
+            deal_log::append(path, &deal_log::DealEvent::new(&deal, &stats));
+        }
+
+        if config.dry_run {
+            info!(
+                "🧪 [dry run] Would notify: {} — {:.2} € (score {:.0}) {}",
+                offer.title, offer.price, deal.score, offer.link
+            );
+            continue;
+        }
+
+        if config.digest_only {
+            // Still recorded as notified so the digest can count it
+            info!("📰 Deal kept for the digest: {}", offer.id);
+            if let Err(e) = storage.mark_deal_notified(&offer.id, &hash) {
+                warn!("Mark notified failed: {:?}", e);
+            }
+            continue;
+        }
+
+        info!("Sending notification...");
+        match notifier.notify_deal(&deal).await {
+            Ok(()) => {
+                if let Err(e) = storage.mark_deal_notified(&offer.id, &hash) {
+                    warn!("Mark notified failed: {:?}", e);
+                } else {
+                    info!("Offer notified and marked.");
+                }
+            }
+            Err(model::NotifyError::Muted) => {
+                info!("🔇 Notifications muted, offer {} will be sent after /unmute", offer.id);
+            }
+            Err(e) => warn!("Notification send error: {:?}", e),
         }
     }
 
+    METRICS.set_model_last_run(&model_cfg.query);
     info!("Finished processing model: {}", model_cfg.query);
+    true
+}
+
+/// Returns the id of an offer notified after `since` whose thumbnail is nearly identical to this
+/// offer's. The thumbnail is fetched and hashed once and the hash stored; a failed download or
+/// an unsupported image format just means no match.
+async fn find_image_repost(
+    offer: &Offer,
+    scraper: &ScraperImpl,
+    storage: &SqliteStorage,
+    max_distance: u32,
+    since: chrono::DateTime<chrono::Utc>,
+) -> Option<String> {
+    let url = offer.image_url.as_deref()?;
+    let hash = match storage.get_image_hash(&offer.id) {
+        Ok(Some(hash)) => hash,
+        Ok(None) => {
+            let bytes = match scraper.fetch_bytes(url).await {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    debug!("Thumbnail download failed for {}: {:?}", offer.id, e);
+                    return None;
+                }
+            };
+            let Some(hash) = image_hash::dhash(&bytes) else {
+                debug!("Thumbnail of {} is not a baseline JPEG, skipping the photo check", offer.id);
+                return None;
+            };
+            if let Err(e) = storage.set_image_hash(&offer.id, hash) {
+                warn!("Saving image hash failed: {:?}", e);
+            }
+            hash
+        }
+        Err(e) => {
+            warn!("Loading image hash failed: {:?}", e);
+            return None;
+        }
+    };
+    match storage.find_notified_by_image_hash(&offer.model, hash, max_distance, &offer.id, since) {
+        Ok(original_id) => original_id,
+        Err(e) => {
+            warn!("Image hash check failed: {:?}", e);
+            None
+        }
+    }
+}
+
+/// Number of consecutive runs without offers after a run that found `offer_count` offers.
+fn next_empty_run_count(previous: u32, offer_count: usize) -> u32 {
+    if offer_count == 0 { previous.saturating_add(1) } else { 0 }
+}
+
+/// Counts consecutive runs without offers per model in the meta table and warns once in Telegram
+/// when the count reaches `empty_run_alert_threshold`; a run with offers resets it (and says so
+/// if the warning had been sent).
+async fn track_empty_runs(
+    model: &str,
+    offer_count: usize,
+    config: &AppConfig,
+    storage: &SqliteStorage,
+    notifier: &dyn Notifier,
+) {
+    let key = format!("empty_runs:{}", model);
+    let previous = match storage.get_meta(&key) {
+        Ok(value) => value.and_then(|v| v.parse().ok()).unwrap_or(0),
+        Err(e) => {
+            warn!("Loading empty run count for {} failed: {:?}", model, e);
+            return;
+        }
+    };
+    let runs = next_empty_run_count(previous, offer_count);
+    if runs == previous {
+        return;
+    }
+    if let Err(e) = storage.set_meta(&key, &runs.to_string()) {
+        warn!("Saving empty run count for {} failed: {:?}", model, e);
+    }
+
+    let threshold = config.empty_run_alert_threshold;
+    let msg = if threshold > 0 && runs == threshold {
+        warn!("⚠️ {} found no offers for {} runs in a row", model, runs);
+        format!(
+            "⚠️ {} found no offers for {} runs in a row. The selectors or category_id may be broken; \
+             check a search URL with /debug.",
+            model, runs
+        )
+    } else if threshold > 0 && runs == 0 && previous >= threshold {
+        format!("✅ {} is finding offers again after {} empty runs.", model, previous)
+    } else {
+        return;
+    };
+    if let Err(e) = notifier.notify_text(&msg).await {
+        warn!("Empty run alert failed: {:?}", e);
+    }
+}
+
+/// Sends a "price dropped" alert for each offer that got cheaper, skipping ignored offers.
+async fn notify_price_drops(
+    drops: &[(&Offer, f64)],
+    config: &AppConfig,
+    storage: &SqliteStorage,
+    notifier: &dyn Notifier,
+) {
+    if drops.is_empty() {
+        return;
+    }
+    let ignored = storage.get_ignored_ids().unwrap_or_else(|e| {
+        warn!("Loading ignored offers failed: {:?}", e);
+        HashSet::new()
+    });
+    for (offer, previous) in drops {
+        if ignored.contains(&offer.id) {
+            continue;
+        }
+        match storage.get_price_history(&offer.id) {
+            Ok(history) => {
+                let peak = history.iter().map(|h| h.price).fold(*previous, f64::max);
+                info!(
+                    "📉 Price drop: {} — {:.2} € → {:.2} € (peak {:.2} € over {} observations)",
+                    offer.id,
+                    previous,
+                    offer.price,
+                    peak,
+                    history.len()
+                );
+            }
+            Err(e) => {
+                warn!("Loading price history for {} failed: {:?}", offer.id, e);
+                info!("📉 Price drop: {} — {:.2} € → {:.2} €", offer.id, previous, offer.price);
+            }
+        }
+        if config.dry_run || config.digest_only {
+            continue;
+        }
+        match notifier.notify_price_drop(offer, *previous).await {
+            Ok(()) => {}
+            Err(model::NotifyError::Muted) => info!("🔇 Notifications muted, skipping price drop for {}", offer.id),
+            Err(e) => warn!("Notification send error: {:?}", e),
+        }
+    }
+}
+
+/// Listings on the page from a seller in `watched_sellers`. The page is parsed again without the
+/// model's price, keyword, location and age filters, so a watched seller's listing is found even
+/// when the model's filters drop it.
+fn watched_seller_offers(
+    parser: &KleinanzeigenParser,
+    html: &str,
+    model_cfg: &ModelConfig,
+    config: &AppConfig,
+) -> Vec<Offer> {
+    if config.watched_sellers.is_empty() {
+        return Vec::new();
+    }
+    let offers = match parser.parse_filtered(html, &ModelConfig::permissive(&model_cfg.query)) {
+        Ok((offers, _)) => offers,
+        Err(e) => {
+            warn!("Parsing listings of watched sellers failed: {:?}", e);
+            return Vec::new();
+        }
+    };
+    offers
+        .into_iter()
+        .filter(|offer| {
+            offer
+                .user_id
+                .as_deref()
+                .is_some_and(|id| config.watched_sellers.iter().any(|w| w.trim() == id))
+        })
+        .collect()
+}
+
+/// Sends every offer from `watched_seller_offers` that wasn't notified yet, skipping the
+/// deal filters. Offers that also qualify as deals are then skipped as already notified.
+async fn notify_watched_sellers(
+    watched: &[Offer],
+    config: &AppConfig,
+    storage: &SqliteStorage,
+    notifier: &dyn Notifier,
+) {
+    if watched.is_empty() {
+        return;
+    }
+    let ignored = storage.get_ignored_ids().unwrap_or_else(|e| {
+        warn!("Loading ignored offers failed: {:?}", e);
+        HashSet::new()
+    });
+    for offer in watched {
+        if ignored.contains(&offer.id) {
+            continue;
+        }
+        match storage.is_notified(&offer.id) {
+            Ok(false) => {}
+            Ok(true) => continue,
+            Err(e) => {
+                warn!("Notify check failed: {:?}", e);
+                continue;
+            }
+        }
+        info!("👀 Watched seller {:?}: {} — {:.2} €", offer.user_id, offer.id, offer.price);
+        if config.dry_run {
+            info!("🧪 [dry run] Would notify: {} — {:.2} € {}", offer.title, offer.price, offer.link);
+            continue;
+        }
+        let sent = if config.digest_only {
+            Ok(())
+        } else {
+            notifier.notify_watched_seller(offer).await
+        };
+        match sent {
+            Ok(()) => {
+                if let Err(e) = storage.mark_notified(&offer.id) {
+                    warn!("Mark notified failed: {:?}", e);
+                }
+            }
+            Err(model::NotifyError::Muted) => info!("🔇 Notifications muted, skipping watched seller offer {}", offer.id),
+            Err(e) => warn!("Notification send error: {:?}", e),
+        }
+    }
 }
 
 /// Logs and saves the provided HTML for debugging purposes.
@@ -278,4 +957,370 @@ fn log_and_save_html(html: &str, query: &str) {
     } else {
         info!("Saved debug HTML: {}", filename.display());
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{app_config, search_page, Listing, LocalDriver, RecordingNotifier, TempStorage};
+
+    fn watched_listing(id: &str, title: &str, price: &str, seller: &str) -> Listing {
+        Listing { seller_id: Some(seller.to_string()), ..Listing::new(id, title, price) }
+    }
+
+    #[tokio::test]
+    async fn watched_sellers_bypass_the_model_filters() {
+        let mut model_cfg = ModelConfig::permissive("iphone 13");
+        model_cfg.match_keywords = vec!["iphone 13".to_string()];
+        model_cfg.min_price = 300.0;
+        model_cfg.max_price = 600.0;
+        let mut config = app_config(vec![model_cfg.clone()]);
+        config.watched_sellers = vec![" 777 ".to_string()];
+        let html = search_page(&[
+            watched_listing("in-filters", "iPhone 13 128GB", "450 €", "777"),
+            watched_listing("too-expensive", "iPhone 13 Pro Max", "1.100 €", "777"),
+            watched_listing("no-keyword", "Samsung Galaxy S21", "250 €", "777"),
+            watched_listing("other-seller", "Pixel 7", "200 €", "888"),
+            Listing::new("no-seller", "iPhone 13 mini", "400 €"),
+        ]);
+        let parser = KleinanzeigenParser::new().unwrap();
+
+        // The model's own parse drops two of the watched seller's listings
+        let (offers, report) = parser.parse_filtered(&html, &model_cfg).unwrap();
+        let ids: Vec<&str> = offers.iter().map(|o| o.id.as_str()).collect();
+        assert_eq!(ids, vec!["in-filters", "no-seller"]);
+        assert_eq!(report.skipped_price_filter + report.skipped_keyword, 3);
+
+        let watched = watched_seller_offers(&parser, &html, &model_cfg, &config);
+        let ids: Vec<&str> = watched.iter().map(|o| o.id.as_str()).collect();
+        assert_eq!(ids, vec!["in-filters", "too-expensive", "no-keyword"]);
+        assert!(watched.iter().all(|o| o.model == "iphone 13"));
+
+        let storage = TempStorage::new();
+        storage.ignore_offer("no-keyword").unwrap();
+        let notifier = RecordingNotifier::default();
+        notify_watched_sellers(&watched, &config, &storage, &notifier).await;
+        assert_eq!(notifier.sent(), vec!["watched_seller:in-filters", "watched_seller:too-expensive"]);
+
+        // Already notified offers are not sent again
+        notify_watched_sellers(&watched, &config, &storage, &notifier).await;
+        assert_eq!(notifier.sent().len(), 2);
+    }
+
+    #[test]
+    fn no_watched_sellers_means_no_second_parse() {
+        let config = app_config(vec![ModelConfig::permissive("iphone 13")]);
+        let html = search_page(&[watched_listing("a", "iPhone 13", "450 €", "777")]);
+        let parser = KleinanzeigenParser::new().unwrap();
+        assert!(watched_seller_offers(&parser, &html, &config.models[0], &config).is_empty());
+    }
+
+    #[tokio::test]
+    async fn shutdown_is_requested_when_the_signal_fires() {
+        let (signal, received) = tokio::sync::oneshot::channel::<()>();
+        let shutdown = Shutdown::watch(async {
+            let _ = received.await;
+        });
+        tokio::task::yield_now().await;
+        assert!(!shutdown.is_requested());
+
+        let waiting = tokio::spawn({
+            let shutdown = shutdown.clone();
+            async move { shutdown.wait().await }
+        });
+        signal.send(()).unwrap();
+        tokio::time::timeout(Duration::from_secs(1), waiting).await.unwrap().unwrap();
+        assert!(shutdown.is_requested());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_signal_during_a_pass_ends_the_next_wait_immediately() {
+        let shutdown = Shutdown::watch(async {});
+        // The "pass" still runs to the end; the signal only takes effect afterwards
+        sleep(Duration::from_secs(30)).await;
+        assert!(shutdown.is_requested());
+
+        let idle = sleep(Duration::from_secs(3600));
+        tokio::select! {
+            _ = idle => panic!("the interval wait should have been cut short"),
+            _ = shutdown.wait() => {}
+        }
+        // Waiting again doesn't hang either
+        tokio::time::timeout(Duration::from_secs(1), shutdown.wait()).await.unwrap();
+    }
+
+    /// Runs one `process_model` pass for the first model of `config` against a local server
+    /// that shows `listings` on the first search page.
+    async fn run_model(listings: &[Listing], mut config: AppConfig, storage: &TempStorage, notifier: Arc<RecordingNotifier>) {
+        let page = search_page(listings);
+        let base = crate::test_support::spawn_http_server(move |request: http_server::HttpRequest| {
+            let body = if request.route() == "/seite:1" { page.clone() } else { search_page(&[]) };
+            async move { http_server::HttpResponse::text(200, body) }
+        })
+        .await;
+        (config.delay_ms_min, config.delay_ms_max) = (0, 0);
+        let driver = Arc::new(LocalDriver::new(base));
+        let scraper = ScraperImpl::with_driver(&config, driver).unwrap();
+        let ctx = PassContext {
+            base_scraper: &scraper,
+            parser: &KleinanzeigenParser::new().unwrap(),
+            analyzer: &AnalyzerImpl::new(),
+            breaker: &CircuitBreaker::new(),
+        };
+        process_model(&config.models[0].clone(), &ctx, storage.storage.clone(), Arc::new(config), notifier).await;
+    }
+
+    #[tokio::test]
+    async fn ignored_offers_are_never_notified() {
+        let mut model_cfg = ModelConfig::permissive("iphone 13");
+        model_cfg.match_keywords = vec!["iphone 13".to_string()];
+        let config = || app_config(vec![model_cfg.clone()]);
+        let listings = [
+            Listing::new("cheapest", "iPhone 13", "300 €"),
+            Listing::new("cheap", "iPhone 13", "350 €"),
+            Listing::new("a", "iPhone 13", "500 €"),
+            Listing::new("b", "iPhone 13", "500 €"),
+            Listing::new("c", "iPhone 13", "500 €"),
+        ];
+        let storage = TempStorage::new();
+        storage.ignore_offer("cheapest").unwrap();
+        storage.ignore_offer("cheap").unwrap();
+        let notifier = Arc::new(RecordingNotifier::default());
+
+        run_model(&listings, config(), &storage, notifier.clone()).await;
+        let sent = notifier.sent();
+        assert!(!sent.is_empty());
+        assert!(sent.iter().all(|entry| !entry.ends_with(":cheapest") && !entry.ends_with(":cheap")), "{:?}", sent);
+
+        // After /unignore the offer is a deal again
+        storage.unignore_offer("cheapest").unwrap();
+        run_model(&listings, config(), &storage, notifier.clone()).await;
+        assert!(notifier.sent().iter().any(|entry| entry.ends_with(":cheapest")), "{:?}", notifier.sent());
+    }
+
+    #[tokio::test]
+    async fn a_suppressed_repost_is_not_counted_as_a_deal() {
+        let mut model_cfg = ModelConfig::permissive("iphone 13");
+        model_cfg.match_keywords = vec!["iphone 13".to_string()];
+        model_cfg.alert_below_price = Some(350.0);
+        let listings = [
+            watched_listing("first", "iPhone 13 128GB", "300 €", "777"),
+            watched_listing("repost", "iPhone 13 128GB", "300 €", "777"),
+            // Announced as the cheapest offer, which doesn't count as a deal either
+            Listing::new("cheapest", "iPhone 13 defekt", "250 €"),
+            Listing::new("a", "iPhone 13", "500 €"),
+            Listing::new("b", "iPhone 13", "500 €"),
+            Listing::new("c", "iPhone 13", "500 €"),
+        ];
+        let storage = TempStorage::new();
+        let notifier = Arc::new(RecordingNotifier::default());
+
+        run_model(&listings, app_config(vec![model_cfg]), &storage, notifier.clone()).await;
+
+        let deals: Vec<String> = notifier.sent().into_iter().filter(|entry| entry.starts_with("deal:")).collect();
+        assert_eq!(deals, vec!["deal:first"], "{:?}", notifier.sent());
+        assert!(storage.is_notified("first").unwrap() && storage.is_notified("repost").unwrap());
+        let (from, to) = (chrono::Utc::now() - chrono::Duration::hours(1), chrono::Utc::now());
+        assert_eq!(storage.count_deals_between("iphone 13", from, to).unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn offers_matching_no_model_are_dropped_unless_keep_unknown_is_set() {
+        // The first model accepts every listing; only the second one can place them
+        let mut iphone = ModelConfig::permissive("iphone 13");
+        iphone.match_keywords = vec!["iphone 13".to_string()];
+        let models = || vec![ModelConfig::permissive("handy"), iphone.clone()];
+        let listings = [Listing::new("iphone", "iPhone 13 128GB", "450 €"), Listing::new("pixel", "Pixel 7", "300 €")];
+        let ids = |storage: &TempStorage, model: &str| -> Vec<String> {
+            storage.get_offers_for_model(model).unwrap().into_iter().map(|o| o.id).collect()
+        };
+
+        let storage = TempStorage::new();
+        run_model(&listings, app_config(models()), &storage, Arc::new(RecordingNotifier::default())).await;
+        assert_eq!(ids(&storage, "iphone 13"), vec!["iphone"]);
+        assert!(ids(&storage, normalizer::UNKNOWN_MODEL).is_empty());
+
+        let mut config = app_config(models());
+        config.keep_unknown = true;
+        let storage = TempStorage::new();
+        run_model(&listings, config, &storage, Arc::new(RecordingNotifier::default())).await;
+        assert_eq!(ids(&storage, "iphone 13"), vec!["iphone"]);
+        assert_eq!(ids(&storage, normalizer::UNKNOWN_MODEL), vec!["pixel"]);
+    }
+
+    #[tokio::test]
+    async fn a_paused_pass_neither_scrapes_nor_notifies() {
+        let requests = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counted = requests.clone();
+        let page = search_page(&[Listing::new("a", "iPhone 13", "300 €"), Listing::new("b", "iPhone 13", "500 €")]);
+        let base = crate::test_support::spawn_http_server(move |request: http_server::HttpRequest| {
+            counted.fetch_add(1, Ordering::SeqCst);
+            let body = if request.route() == "/seite:1" { page.clone() } else { search_page(&[]) };
+            async move { http_server::HttpResponse::text(200, body) }
+        })
+        .await;
+        let mut model_cfg = ModelConfig::permissive("iphone 13");
+        model_cfg.match_keywords = vec!["iphone 13".to_string()];
+        let mut config = app_config(vec![model_cfg]);
+        (config.delay_ms_min, config.delay_ms_max) = (0, 0);
+        let scraper = ScraperImpl::with_driver(&config, Arc::new(LocalDriver::new(base))).unwrap();
+        let config = Arc::new(config);
+        let storage = TempStorage::new();
+        let recording = Arc::new(RecordingNotifier::default());
+        let notifier: Arc<dyn Notifier> = recording.clone();
+        let (parser, analyzer, breaker) = (KleinanzeigenParser::new().unwrap(), AnalyzerImpl::new(), CircuitBreaker::new());
+        let pass = || run_pass(&scraper, &parser, &analyzer, &storage.storage, &config, &notifier, &breaker);
+
+        recording.paused.store(true, Ordering::SeqCst);
+        assert_eq!(pass().await, 0);
+        assert_eq!(requests.load(Ordering::SeqCst), 0);
+        assert!(storage.get_offers_for_model("iphone 13").unwrap().is_empty());
+        assert!(recording.sent().is_empty());
+
+        // /resume_all: the next pass scrapes again
+        recording.paused.store(false, Ordering::SeqCst);
+        assert_eq!(pass().await, 0);
+        assert!(requests.load(Ordering::SeqCst) > 0);
+        assert_eq!(storage.get_offers_for_model("iphone 13").unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn no_more_than_max_concurrent_models_are_scraped_at_once() {
+        use std::sync::atomic::AtomicUsize;
+        let (in_flight, peak) = (Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0)));
+        let (counted, seen_peak) = (in_flight.clone(), peak.clone());
+        let page = search_page(&[Listing::new("a", "iPhone 13", "300 €")]);
+        let base = crate::test_support::spawn_http_server(move |request: http_server::HttpRequest| {
+            let (in_flight, peak) = (counted.clone(), seen_peak.clone());
+            let body = if request.route() == "/seite:1" { page.clone() } else { search_page(&[]) };
+            async move {
+                let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                sleep(Duration::from_millis(50)).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                http_server::HttpResponse::text(200, body)
+            }
+        })
+        .await;
+        let models: Vec<ModelConfig> = (0..5)
+            .map(|i| {
+                let mut model_cfg = ModelConfig::permissive(&format!("iphone 13 m{}", i));
+                model_cfg.match_keywords = vec!["iphone 13".to_string()];
+                model_cfg
+            })
+            .collect();
+        let mut config = app_config(models);
+        (config.delay_ms_min, config.delay_ms_max) = (0, 0);
+        config.max_concurrent_models = 2;
+        let scraper = ScraperImpl::with_driver(&config, Arc::new(LocalDriver::new(base))).unwrap();
+        let storage = TempStorage::new();
+        let notifier: Arc<dyn Notifier> = Arc::new(RecordingNotifier::default());
+
+        let failed = run_pass(
+            &scraper,
+            &KleinanzeigenParser::new().unwrap(),
+            &AnalyzerImpl::new(),
+            &storage.storage,
+            &Arc::new(config),
+            &notifier,
+            &CircuitBreaker::new(),
+        )
+        .await;
+
+        assert_eq!(failed, 0);
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+        assert_eq!(in_flight.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn import_html_saves_the_parsed_offers_of_a_saved_page() {
+        let mut model_cfg = ModelConfig::permissive("iphone 13");
+        model_cfg.match_keywords = vec!["iphone 13".to_string()];
+        let config = app_config(vec![model_cfg]);
+        let page = crate::test_support::TempFile::new("html");
+        fs::write(
+            page.path(),
+            search_page(&[
+                Listing::new("1", "iPhone 13 128GB", "450 €"),
+                Listing::new("2", "iPhone 13 mini", "400 € VB"),
+                Listing::new("3", "Pixel 7", "300 €"),
+            ]),
+        )
+        .unwrap();
+        let storage = TempStorage::new();
+        let parser = KleinanzeigenParser::new().unwrap();
+
+        assert_eq!(import_html(Path::new(page.path()), "iPhone 13", &config, &parser, &storage), Ok(2));
+        let mut ids: Vec<String> = storage.get_offers_for_model("iphone 13").unwrap().into_iter().map(|o| o.id).collect();
+        ids.sort();
+        assert_eq!(ids, vec!["1", "2"]);
+
+        let unknown = import_html(Path::new(page.path()), "pixel", &config, &parser, &storage);
+        assert_eq!(unknown, Err("unknown model 'pixel'".to_string()));
+        assert!(import_html(Path::new("/nonexistent/page.html"), "iphone 13", &config, &parser, &storage).is_err());
+    }
+
+    #[tokio::test]
+    async fn offers_below_the_sanity_floor_are_left_out_of_stats_and_deals() {
+        let mut model_cfg = ModelConfig::permissive("iphone 13");
+        model_cfg.match_keywords = vec!["iphone 13".to_string()];
+        model_cfg.deviation_threshold = 0.1;
+        model_cfg.sanity_min_price = Some(50.0);
+        let listings = [
+            Listing::new("scam", "iPhone 13", "1 €"),
+            Listing::new("deal", "iPhone 13", "300 €"),
+            Listing::new("a", "iPhone 13", "500 €"),
+            Listing::new("b", "iPhone 13", "500 €"),
+            Listing::new("c", "iPhone 13", "500 €"),
+        ];
+        let storage = TempStorage::new();
+        let notifier = Arc::new(RecordingNotifier::default());
+
+        run_model(&listings, app_config(vec![model_cfg.clone()]), &storage, notifier.clone()).await;
+        let stats = storage.get_stats("iphone 13").unwrap().unwrap();
+        assert_eq!(stats.avg_price, 450.0);
+        let sent = notifier.sent();
+        assert!(sent.iter().any(|entry| entry.ends_with(":deal")), "{:?}", sent);
+        assert!(sent.iter().all(|entry| !entry.ends_with(":scam")), "{:?}", sent);
+
+        let (deals, _) = AnalyzerImpl::new().current_deals(&storage, &app_config(vec![model_cfg]), None);
+        let ids: Vec<&str> = deals.iter().map(|d| d.offer.id.as_str()).collect();
+        assert_eq!(ids, vec!["deal"]);
+    }
+
+    #[test]
+    fn empty_runs_count_up_and_reset_on_offers() {
+        assert_eq!(next_empty_run_count(0, 0), 1);
+        assert_eq!(next_empty_run_count(4, 0), 5);
+        assert_eq!(next_empty_run_count(4, 3), 0);
+        assert_eq!(next_empty_run_count(u32::MAX, 0), u32::MAX);
+    }
+
+    #[tokio::test]
+    async fn the_empty_run_alert_is_sent_once_and_cleared_when_offers_return() {
+        let mut config = app_config(Vec::new());
+        config.empty_run_alert_threshold = 3;
+        let storage = TempStorage::new();
+        let notifier = RecordingNotifier::default();
+        let run = |offers: usize| track_empty_runs("iphone 13", offers, &config, &storage, &notifier);
+
+        run(0).await;
+        run(0).await;
+        assert!(notifier.sent().is_empty());
+        run(0).await;
+        assert_eq!(notifier.sent().len(), 1);
+        assert!(notifier.sent()[0].starts_with("text:⚠️ iphone 13 found no offers for 3 runs in a row"));
+        run(0).await;
+        run(0).await;
+        assert_eq!(notifier.sent().len(), 1);
+        assert_eq!(storage.get_meta("empty_runs:iphone 13").unwrap().as_deref(), Some("5"));
+
+        run(7).await;
+        assert_eq!(notifier.sent()[1], "text:✅ iphone 13 is finding offers again after 5 empty runs.");
+        assert_eq!(storage.get_meta("empty_runs:iphone 13").unwrap().as_deref(), Some("0"));
+        // Quiet runs below the threshold never notify
+        run(0).await;
+        run(2).await;
+        assert_eq!(notifier.sent().len(), 2);
+    }
+}
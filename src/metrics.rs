@@ -0,0 +1,187 @@
+// Process-wide counters exposed in the Prometheus text format on `/metrics`,
+// plus a `/healthz` endpoint built on the per-model last-run timestamps.
+use crate::config::SharedConfig;
+use crate::http_server::{self, HttpRequest, HttpResponse};
+use crate::storage::SqliteStorage;
+use chrono::Utc;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+use tracing::warn;
+
+pub static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::default);
+
+#[derive(Default)]
+pub struct Metrics {
+    offers_scraped: AtomicU64,
+    deals_found: AtomicU64,
+    notifications_sent: AtomicU64,
+    scraper_errors: Mutex<BTreeMap<&'static str, u64>>,
+    model_last_run: Mutex<BTreeMap<String, i64>>,
+}
+
+impl Metrics {
+    pub fn add_offers_scraped(&self, n: usize) {
+        self.offers_scraped.fetch_add(n as u64, Ordering::Relaxed);
+    }
+
+    pub fn add_deals_found(&self, n: usize) {
+        self.deals_found.fetch_add(n as u64, Ordering::Relaxed);
+    }
+
+    pub fn inc_notifications_sent(&self) {
+        self.notifications_sent.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn inc_scraper_error(&self, kind: &'static str) {
+        *self.scraper_errors.lock().unwrap_or_else(|e| e.into_inner()).entry(kind).or_insert(0) += 1;
+    }
+
+    /// Records that `model` finished a processing run just now.
+    pub fn set_model_last_run(&self, model: &str) {
+        self.model_last_run
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(model.to_string(), Utc::now().timestamp());
+    }
+
+    /// Unix time each model last finished processing.
+    pub fn model_last_runs(&self) -> BTreeMap<String, i64> {
+        self.model_last_run.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Renders all metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let counters = [
+            ("offers_scraped_total", "Offers parsed from search results.", &self.offers_scraped),
+            ("deals_found_total", "Offers flagged as deals by the analyzer.", &self.deals_found),
+            ("notifications_sent_total", "Offer notifications delivered by the configured notifier.", &self.notifications_sent),
+        ];
+        for (name, help, value) in counters {
+            let _ = writeln!(out, "# HELP {} {}\n# TYPE {} counter", name, help, name);
+            let _ = writeln!(out, "{} {}", name, value.load(Ordering::Relaxed));
+        }
+
+        let _ = writeln!(
+            out,
+            "# HELP scraper_errors_total Failed scrapes by error kind.\n# TYPE scraper_errors_total counter"
+        );
+        for (kind, count) in self.scraper_errors.lock().unwrap_or_else(|e| e.into_inner()).iter() {
+            let _ = writeln!(out, "scraper_errors_total{{kind=\"{}\"}} {}", kind, count);
+        }
+
+        let _ = writeln!(
+            out,
+            "# HELP model_last_run_timestamp_seconds Unix time a model was last processed.\n\
+             # TYPE model_last_run_timestamp_seconds gauge"
+        );
+        for (model, ts) in self.model_last_run.lock().unwrap_or_else(|e| e.into_inner()).iter() {
+            let _ = writeln!(
+                out,
+                "model_last_run_timestamp_seconds{{model=\"{}\"}} {}",
+                escape_label(model),
+                ts
+            );
+        }
+        out
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// Builds the `/healthz` response: 200 with the last run per enabled model, or 503 when a model
+/// hasn't finished within `health_max_missed_intervals` check intervals (counted from `started_at`
+/// for models that never finished).
+fn health(config: &SharedConfig, storage: &SqliteStorage, started_at: i64) -> HttpResponse {
+    let config = config.load();
+    let disabled = storage.get_disabled_models().unwrap_or_else(|e| {
+        warn!("❌ /healthz could not load disabled models: {:?}", e);
+        Default::default()
+    });
+    let max_age = config.check_interval_seconds as i64 * config.health_max_missed_intervals as i64;
+    let now = Utc::now().timestamp();
+    let last_runs = METRICS.model_last_runs();
+
+    let mut healthy = true;
+    let mut models = serde_json::Map::new();
+    for model in config.models.iter().filter(|m| !disabled.contains(&m.query)) {
+        let last_run = last_runs.get(&model.query).copied();
+        let stale = now - last_run.unwrap_or(started_at) > max_age;
+        healthy &= !stale;
+        models.insert(
+            model.query.clone(),
+            serde_json::json!({ "last_success": last_run, "stale": stale }),
+        );
+    }
+
+    HttpResponse {
+        status: if healthy { 200 } else { 503 },
+        content_type: "application/json",
+        body: serde_json::json!({ "healthy": healthy, "now": now, "models": models }).to_string(),
+    }
+}
+
+/// Serves `/metrics` and `/healthz` on `addr` until the task is aborted.
+pub async fn serve_metrics(addr: String, config: Arc<SharedConfig>, storage: Arc<SqliteStorage>) {
+    let started_at = Utc::now().timestamp();
+    let result = http_server::serve(&addr, move |request| {
+        let config = config.clone();
+        let storage = storage.clone();
+        async move { handle_request(&request, &config, &storage, started_at) }
+    })
+    .await;
+    if let Err(e) = result {
+        warn!("❌ Metrics server on {} stopped: {}", addr, e);
+    }
+}
+
+/// Routes `/metrics` and `/healthz`; a query string (e.g. a scraper's cache buster) is ignored.
+fn handle_request(
+    request: &HttpRequest,
+    config: &SharedConfig,
+    storage: &SqliteStorage,
+    started_at: i64,
+) -> HttpResponse {
+    match request.route() {
+        "/metrics" => HttpResponse {
+            status: 200,
+            content_type: "text/plain; version=0.0.4; charset=utf-8",
+            body: METRICS.render(),
+        },
+        "/healthz" => health(config, storage, started_at),
+        _ => HttpResponse::text(404, "Not Found"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{app_config, TempStorage};
+
+    fn get(path: &str) -> HttpRequest {
+        HttpRequest {
+            method: "GET".to_string(),
+            path: path.to_string(),
+            headers: Default::default(),
+            body: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn query_strings_are_ignored_when_routing() {
+        let config = SharedConfig::new("config.json", app_config(Vec::new()));
+        let storage = TempStorage::new();
+        let started_at = Utc::now().timestamp();
+        let respond = |path: &str| handle_request(&get(path), &config, &storage, started_at);
+
+        let metrics = respond("/metrics?x=1");
+        assert_eq!(metrics.status, 200);
+        assert!(metrics.body.contains("notifications_sent_total"), "{}", metrics.body);
+        assert_eq!(respond("/healthz?probe=k8s").status, 200);
+        assert_eq!(respond("/metricsx").status, 404);
+    }
+}
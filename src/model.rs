@@ -1,9 +1,10 @@
 use chrono::{DateTime, Utc,ParseError};
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use rusqlite;
 
-/// Основная информация об объявлении
-#[derive(Debug, Clone)]
+/// Основная информация об объявлении (даты сериализуются в RFC3339)
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Offer {
     pub id: String,
     pub title: String,
@@ -17,17 +18,102 @@ pub struct Offer {
     pub user_id: Option<String>,     
     pub user_name: Option<String>,   
     pub user_url: Option<String>,    
+    pub negotiable: bool,
+    pub price_kind: PriceKind,
+    pub image_url: Option<String>,
+    /// "Versand möglich" — продавец готов отправить товар
+    pub shipping_available: bool,
+}
+
+/// Вид цены объявления
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PriceKind {
+    /// Обычная цена (в т.ч. "VB")
+    Fixed,
+    /// "Zu verschenken" — отдаётся бесплатно
+    Free,
+    /// "Preis auf Anfrage" или цена не указана
+    OnRequest,
+}
+
+impl PriceKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PriceKind::Fixed => "fixed",
+            PriceKind::Free => "free",
+            PriceKind::OnRequest => "on_request",
+        }
+    }
+
+    pub fn from_str(value: &str) -> Self {
+        match value {
+            "free" => PriceKind::Free,
+            "on_request" => PriceKind::OnRequest,
+            _ => PriceKind::Fixed,
+        }
+    }
+}
+/// Выгодное предложение с оценкой 0–100 (чем выше, тем лучше)
+#[derive(Debug, Clone, Serialize)]
+pub struct Deal {
+    pub offer: Offer,
+    pub score: f64,
 }
+
 /// Статистика по модели (для анализа отклонений)
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ModelStats {
     pub model: String,
     pub avg_price: f64,
     pub std_dev: f64,
+    pub median_price: f64,
+    pub p25: f64,
+    pub p75: f64,
     pub last_updated: DateTime<Utc>,
 }
 
 
+/// Один прогон сбора по модели (для /runs)
+#[derive(Debug, Clone, Serialize)]
+pub struct ScrapeRun {
+    pub model: String,
+    pub started_at: DateTime<Utc>,
+    pub duration_ms: u64,
+    pub offer_count: usize,
+}
+
+/// Текущий рынок модели в сравнении с сохранённой статистикой (для /market)
+#[derive(Debug, Clone)]
+pub struct MarketSnapshot {
+    pub model: String,
+    /// Средняя цена из model_stats
+    pub stats_avg_price: f64,
+    /// Средняя цена активных офферов с ценой
+    pub current_avg_price: f64,
+    pub cheapest_price: f64,
+    pub offer_count: usize,
+}
+
+impl MarketSnapshot {
+    /// Отклонение текущей средней цены от сохранённой (-0.1 = на 10% дешевле)
+    pub fn deviation(&self) -> f64 {
+        if self.stats_avg_price > 0.0 {
+            (self.current_avg_price - self.stats_avg_price) / self.stats_avg_price
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Наблюдение цены оффера в конкретный момент (одна запись на каждый цикл сбора)
+#[derive(Debug, Clone)]
+pub struct PriceObservation {
+    pub offer_id: String,
+    pub price: f64,
+    pub observed_at: DateTime<Utc>,
+}
+
 #[derive(Debug)]
 pub struct OfferLifecycle {
     pub price: f64,
@@ -35,6 +121,52 @@ pub struct OfferLifecycle {
     pub last_seen: DateTime<Utc>,
     pub price_changes: u32,
 }
+/// Порядок сортировки офферов для постраничных выборок
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    PriceAsc,
+    PriceDesc,
+    /// Сначала недавно собранные
+    FetchedAtDesc,
+}
+
+impl SortKey {
+    /// Разбирает значение параметра `sort` ("price_asc", "price_desc", "newest")
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "price_asc" => Some(SortKey::PriceAsc),
+            "price_desc" => Some(SortKey::PriceDesc),
+            "newest" => Some(SortKey::FetchedAtDesc),
+            _ => None,
+        }
+    }
+
+    /// ORDER BY для SQL-выборки (id — для стабильного порядка страниц)
+    pub fn order_by(&self) -> &'static str {
+        match self {
+            SortKey::PriceAsc => "price ASC, id ASC",
+            SortKey::PriceDesc => "price DESC, id ASC",
+            SortKey::FetchedAtDesc => "fetched_at DESC, id ASC",
+        }
+    }
+}
+
+/// Формат выгрузки офферов
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+        }
+    }
+}
+
 /// Запрос для парсера
 #[derive(Debug, Clone)]
 pub struct ScrapeRequest {
@@ -43,11 +175,35 @@ pub struct ScrapeRequest {
 }
 
 /// Ошибки, возникающие при загрузке страниц
-#[derive(Debug)]
+#[derive(Debug, Error)]
 pub enum ScraperError {
+    #[error("🌐 Ошибка HTTP: {0}")]
     HttpError(String),
+
+    #[error("📄 Неожиданный ответ ({} байт)", .0.len())]
     InvalidResponse(String),
+
+    #[error("🧩 Ошибка разбора HTML: {0}")]
     HtmlParseError(String),
+
+    #[error("🔌 Некорректный proxy_url: {0}")]
+    InvalidProxy(String),
+
+    #[error("⛔ Доступ заблокирован (captcha)")]
+    Blocked,
+}
+
+impl ScraperError {
+    /// Короткое имя варианта ошибки (для метрик)
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ScraperError::HttpError(_) => "http",
+            ScraperError::InvalidResponse(_) => "invalid_response",
+            ScraperError::HtmlParseError(_) => "html_parse",
+            ScraperError::InvalidProxy(_) => "invalid_proxy",
+            ScraperError::Blocked => "blocked",
+        }
+    }
 }
 
 /// Ошибки, возникающие при разборе HTML
@@ -82,4 +238,49 @@ impl From<rusqlite::Error> for StorageError {
 pub enum NotifyError {
     ApiError(String),
     Unreachable,
+    /// Telegram ответил 429; значение — рекомендуемая пауза в секундах
+    RateLimited(u64),
+    /// Уведомления временно отключены командой /mute
+    Muted,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{model_stats, offer};
+    use chrono::TimeZone;
+
+    #[test]
+    fn offer_round_trips_through_json() {
+        let mut original = offer("2712345678", "iPhone 13 \"neu\"", 1049.5);
+        original.posted_at = Utc.with_ymd_and_hms(2025, 3, 4, 23, 0, 0).unwrap();
+        original.user_id = Some("777".to_string());
+        original.negotiable = true;
+        original.price_kind = PriceKind::OnRequest;
+        original.shipping_available = true;
+
+        let json = serde_json::to_value(&original).unwrap();
+        // Стабильные имена полей, даты в RFC3339, вид цены в snake_case
+        assert_eq!(json["posted_at"], "2025-03-04T23:00:00Z");
+        assert_eq!(json["price_kind"], "on_request");
+        assert_eq!(json["user_id"], "777");
+        assert_eq!(json["image_url"], serde_json::Value::Null);
+
+        let restored: Offer = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&restored).unwrap(), json);
+        assert_eq!(restored.posted_at, original.posted_at);
+        assert_eq!(restored.fetched_at, original.fetched_at);
+    }
+
+    #[test]
+    fn model_stats_round_trip_through_json() {
+        let stats = ModelStats {
+            last_updated: Utc.with_ymd_and_hms(2025, 7, 1, 12, 0, 0).unwrap(),
+            ..model_stats("iphone 13", 500.0, 40.0)
+        };
+        let json = serde_json::to_string(&stats).unwrap();
+        assert!(json.contains(r#""last_updated":"2025-07-01T12:00:00Z""#), "{}", json);
+        let restored: ModelStats = serde_json::from_str(&json).unwrap();
+        assert_eq!(serde_json::to_string(&restored).unwrap(), json);
+    }
 }
@@ -1,23 +1,208 @@
 use crate::model::Offer;
 use crate::config::ModelConfig;
+use crate::parser::klein_parser::is_excluded;
+use regex::Regex;
 
-pub fn normalize_all(offers: &mut Vec<Offer>, models: &[ModelConfig]) {
+/// Label given to offers that match no configured model.
+pub const UNKNOWN_MODEL: &str = "unknown";
+
+/// Assigns every offer to a model. Offers that match no model are removed from `offers`
+/// and returned (labelled `UNKNOWN_MODEL`) so the caller can decide what to do with them.
+pub fn normalize_all(offers: &mut Vec<Offer>, models: &[ModelConfig]) -> Vec<Offer> {
+    let regexes: Vec<Option<Regex>> = models.iter().map(ModelConfig::title_regex).collect();
     for offer in offers.iter_mut() {
-        normalize_offer(offer, models);
+        normalize_offer(offer, models, &regexes);
     }
+    let (matched, unknown): (Vec<Offer>, Vec<Offer>) =
+        offers.drain(..).partition(|offer| offer.model != UNKNOWN_MODEL);
+    *offers = matched;
+    unknown
 }
 
-fn normalize_offer(offer: &mut Offer, models: &[ModelConfig]) {
+/// Assigns the offer to the model that matches the title most specifically.
+/// A model with a `match_regex` (compiled in `regexes`, in model order) matches by that regex,
+/// with the length of the match as its specificity; otherwise its keywords match as
+/// case-insensitive substrings, the longest one counting. When several models match, the most
+/// specific wins, so "iPhone 13 Pro" goes to an "iphone 13 pro" model rather than "iphone 13".
+/// Ties keep the first model in config order.
+/// A model never claims an offer that its `exclude_keywords` reject, so an "iphone 13" model with
+/// `"exclude_keywords": ["pro", "mini"]` leaves "iPhone 13 Pro" to another model, or unknown.
+fn normalize_offer(offer: &mut Offer, models: &[ModelConfig], regexes: &[Option<Regex>]) {
     let title = offer.title.to_lowercase();
 
-    for model in models {
-        for keyword in &model.match_keywords {
-            if title.contains(&keyword.to_lowercase()) {
-                offer.model = model.query.clone(); // ✅ фикс: теперь присваивается основное имя из конфига
-                return;
-            }
-        }
+    let best = models
+        .iter()
+        .zip(regexes)
+        .filter(|(model, _)| !is_excluded(&offer.title, &offer.description, &model.exclude_keywords))
+        .filter_map(|(model, regex)| {
+            let len = match regex {
+                Some(regex) => regex.find(&offer.title).map(|m| m.as_str().chars().count()).filter(|&len| len > 0),
+                None => model
+                    .match_keywords
+                    .iter()
+                    .map(|keyword| keyword.to_lowercase())
+                    .filter(|keyword| !keyword.is_empty() && title.contains(keyword.as_str()))
+                    .map(|keyword| keyword.chars().count())
+                    .max(),
+            };
+            len.map(|len| (model, len))
+        })
+        .fold(None, |best: Option<(&ModelConfig, usize)>, (model, len)| match best {
+            Some((_, best_len)) if best_len >= len => best,
+            _ => Some((model, len)),
+        });
+
+    offer.model = match best {
+        Some((model, _)) => model.query.clone(),
+        None => UNKNOWN_MODEL.to_string(),
+    };
+}
+
+/// Stable fingerprint of the physical item behind an offer, used to recognize reposts under a new id.
+/// Combines the normalized title (lowercase alphanumeric words), the price bucket of width
+/// `price_bucket` (at least 1 €) and the seller (user id, else name). Uses 64-bit FNV-1a,
+/// so hashes stay comparable across restarts and Rust versions.
+pub fn content_hash(offer: &Offer, price_bucket: f64) -> String {
+    let title = offer
+        .title
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    let bucket = (offer.price / price_bucket.max(1.0)).floor() as i64;
+    let seller = offer
+        .user_id
+        .as_deref()
+        .or(offer.user_name.as_deref())
+        .unwrap_or_default()
+        .trim()
+        .to_lowercase();
+
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in format!("{}\u{1f}{}\u{1f}{}", title, bucket, seller).bytes() {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    format!("{:016x}", hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::offer;
+
+    fn model(query: &str, keywords: &[&str], exclude: &[&str]) -> ModelConfig {
+        let mut model = ModelConfig::permissive(query);
+        model.match_keywords = keywords.iter().map(|k| k.to_string()).collect();
+        model.exclude_keywords = exclude.iter().map(|k| k.to_string()).collect();
+        model
+    }
+
+    fn assigned(title: &str, models: &[ModelConfig]) -> String {
+        let mut offer = offer("1", title, 500.0);
+        let regexes: Vec<Option<Regex>> = models.iter().map(ModelConfig::title_regex).collect();
+        normalize_offer(&mut offer, models, &regexes);
+        offer.model
+    }
+
+    #[test]
+    fn longest_keyword_wins_between_overlapping_models() {
+        let models = [
+            model("iphone 13", &["iphone 13"], &[]),
+            model("iphone 13 pro", &["iphone 13 pro"], &[]),
+        ];
+        assert_eq!(assigned("Apple iPhone 13 Pro 256GB", &models), "iphone 13 pro");
+        assert_eq!(assigned("iPhone 13 128GB", &models), "iphone 13");
+
+        // Config order doesn't matter
+        let reversed = [models[1].clone(), models[0].clone()];
+        assert_eq!(assigned("Apple iPhone 13 Pro 256GB", &reversed), "iphone 13 pro");
+    }
+
+    #[test]
+    fn excluded_variants_are_not_claimed() {
+        let only_base = [model("iphone 13", &["iphone 13"], &["pro", "mini"])];
+        assert_eq!(assigned("iPhone 13 Pro 256GB", &only_base), UNKNOWN_MODEL);
+        assert_eq!(assigned("iPhone 13 mini", &only_base), UNKNOWN_MODEL);
+        assert_eq!(assigned("iPhone 13 128GB blau", &only_base), "iphone 13");
+    }
+
+    #[test]
+    fn without_excludes_a_shorter_keyword_still_claims_the_variant() {
+        let only_base = [model("iphone 13", &["iphone 13"], &[])];
+        assert_eq!(assigned("iPhone 13 Pro 256GB", &only_base), "iphone 13");
+    }
+
+    #[test]
+    fn excluded_model_falls_through_to_the_next_match() {
+        let models = [
+            model("z1 extreme", &["z1 extreme"], &["defekt"]),
+            model("z1", &["z1"], &[]),
+        ];
+        assert_eq!(assigned("Lenovo Z1 Extreme defekt", &models), "z1");
+        assert_eq!(assigned("Lenovo Z1 Extreme", &models), "z1 extreme");
+    }
+
+    #[test]
+    fn match_regex_is_preferred_over_keywords() {
+        let mut strict = model("iphone 13", &["iphone"], &[]);
+        strict.match_regex = Some(r"\biphone\s?13\b".to_string());
+        let models = [strict, model("iphone", &["iphone"], &[])];
+        // The regex rejects titles the "iphone" keyword would have let through
+        assert_eq!(assigned("iPhone 135 Hülle", &models[..1]), UNKNOWN_MODEL);
+        // The matched text counts as the specificity, beating the shorter keyword
+        assert_eq!(assigned("Apple IPHONE13 256GB", &models), "iphone 13");
+        assert_eq!(assigned("iPhone 12", &models), "iphone");
+    }
+
+    #[test]
+    fn normalize_all_splits_off_unknown_offers() {
+        let models = [model("iphone 13", &["iphone 13"], &["pro"])];
+        let mut offers = vec![offer("a", "iPhone 13", 500.0), offer("b", "iPhone 13 Pro", 700.0)];
+        let unknown = normalize_all(&mut offers, &models);
+        assert_eq!(offers.len(), 1);
+        assert_eq!(offers[0].id, "a");
+        assert_eq!(unknown.len(), 1);
+        assert_eq!(unknown[0].model, UNKNOWN_MODEL);
+    }
+
+    fn listed(id: &str, title: &str, price: f64, seller: Option<&str>) -> Offer {
+        let mut offer = offer(id, title, price);
+        offer.user_id = seller.map(str::to_string);
+        offer
     }
 
-    offer.model = "unknown".to_string();
-}
\ No newline at end of file
+    #[test]
+    fn content_hash_is_a_stable_fnv1a() {
+        // FNV-1a of "iphone 13 128gb\x1f50\x1f777"
+        let hash = content_hash(&listed("1", "iPhone 13 128GB", 505.0, Some("777")), 10.0);
+        assert_eq!(hash, "db4379138815825c");
+    }
+
+    #[test]
+    fn reposts_of_the_same_item_share_the_hash() {
+        let original = content_hash(&listed("1", "iPhone 13 128GB", 500.0, Some("777")), 10.0);
+        // New id, punctuation and case changes, a price within the same bucket
+        assert_eq!(content_hash(&listed("2", "IPHONE 13 - 128GB!", 509.0, Some(" 777 ")), 10.0), original);
+
+        // Another bucket, another seller or another title is another item
+        assert_ne!(content_hash(&listed("3", "iPhone 13 128GB", 510.0, Some("777")), 10.0), original);
+        assert_ne!(content_hash(&listed("4", "iPhone 13 128GB", 500.0, Some("888")), 10.0), original);
+        assert_ne!(content_hash(&listed("5", "iPhone 13 256GB", 500.0, Some("777")), 10.0), original);
+    }
+
+    #[test]
+    fn seller_name_stands_in_for_a_missing_id_and_buckets_are_at_least_one_euro() {
+        let mut by_name = listed("1", "iPhone 13", 500.0, None);
+        by_name.user_name = Some("Max".to_string());
+        let mut by_id_and_name = listed("2", "iPhone 13", 500.0, Some("max"));
+        by_id_and_name.user_name = Some("Someone else".to_string());
+        assert_eq!(content_hash(&by_name, 10.0), content_hash(&by_id_and_name, 10.0));
+
+        let a = listed("1", "iPhone 13", 500.2, None);
+        let b = listed("2", "iPhone 13", 500.9, None);
+        assert_eq!(content_hash(&a, 0.0), content_hash(&b, 0.0));
+        assert_eq!(content_hash(&a, -5.0), content_hash(&b, 1.0));
+    }
+}
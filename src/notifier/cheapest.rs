@@ -0,0 +1,174 @@
+use crate::analyzer::price_analysis::cheapest_offer;
+use crate::config::AppConfig;
+use crate::normalizer::content_hash;
+use crate::notifier::Notifier;
+use crate::storage::SqliteStorage;
+use tracing::{info, warn};
+
+/// Meta key prefix (followed by the offer id) marking that a priority offer already used its cooldown bypass.
+const PRIORITY_BYPASS_KEY_PREFIX: &str = "priority_bypass:";
+
+/// Notifies the cheapest stored offer of a model when it changed or its cooldown expired.
+pub async fn check_and_notify_cheapest_for_model(
+    model_name: &str,
+    storage: &SqliteStorage,
+    config: &AppConfig,
+    notifier: &dyn Notifier,
+) {
+    info!("🔍 [cheapest] Starting check for model '{}'", model_name);
+    if notifier.is_muted() {
+        info!("🔇 [cheapest] Notifications muted, skipping '{}'", model_name);
+        return;
+    }
+    let mut offers = match storage.get_offers_for_model(model_name) {
+        Ok(o) => o,
+        Err(e) => {
+            warn!("❌ [cheapest] Failed to get offers for '{}': {:?}", model_name, e);
+            return;
+        }
+    };
+    match storage.get_ignored_ids() {
+        Ok(ignored) => offers.retain(|o| !ignored.contains(&o.id)),
+        Err(e) => warn!("❌ [cheapest] Failed to load ignored offers: {:?}", e),
+    }
+    if let Some(model_cfg) = config.models.iter().find(|m| m.query == model_name) {
+        offers.retain(|o| !model_cfg.is_suspicious_price(o.price));
+    }
+
+    info!("📦 [cheapest] Found {} offers for model '{}'", offers.len(), model_name);
+
+    if offers.is_empty() {
+        info!("ℹ️ [cheapest] No offers for '{}'", model_name);
+        return;
+    }
+
+    if let Some(cheapest) = cheapest_offer(&offers) {
+        info!(
+            "💰 [cheapest] Cheapest offer: {:.2} € | {} | id={}",
+            cheapest.price, cheapest.link, cheapest.id
+        );
+
+        // A different cheapest offer is announced right away; the same one waits for the cooldown
+        let cheapest_changed = match storage.get_best_deal(model_name) {
+            Ok(best_id) => best_id.as_deref() != Some(cheapest.id.as_str()),
+            Err(e) => {
+                warn!("❌ [cheapest] Failed to load best deal for '{}': {:?}", model_name, e);
+                false
+            }
+        };
+
+        // A priority offer skips the cooldown once; after that it waits like any other offer
+        let bypass_key = format!("{}{}", PRIORITY_BYPASS_KEY_PREFIX, cheapest.id);
+        let priority_bypass = config
+            .models
+            .iter()
+            .any(|m| m.query == model_name && m.priority_bypass_cooldown && m.is_priority(&cheapest.title))
+            && match storage.get_meta(&bypass_key) {
+                Ok(used) => used.is_none(),
+                Err(e) => {
+                    warn!("❌ [cheapest] Failed to load the priority bypass of {}: {:?}", cheapest.id, e);
+                    false
+                }
+            };
+        if priority_bypass {
+            info!("🔥 [cheapest] Priority offer {}, skipping the cooldown once", cheapest.id);
+        }
+
+        let should_notify = cheapest_changed
+            || priority_bypass
+            || match storage.should_notify(&cheapest.id) {
+                Ok(flag) => flag,
+                Err(e) => {
+                    warn!("❌ [cheapest] Error checking notification status: {:?}", e);
+                    false
+                }
+            };
+
+        if !should_notify {
+            info!(
+                "✅ [cheapest] Offer already notified recently: {} € (id={})",
+                cheapest.price, cheapest.id
+            );
+            return;
+        }
+
+        info!(
+            "📤 [cheapest] Calling notify() for id={}, price={:.2} €",
+            cheapest.id, cheapest.price
+        );
+
+        match notifier.notify(cheapest).await {
+            Ok(_) => {
+                info!("✅ [cheapest] Notification sent, saving id.");
+                let hash = content_hash(cheapest, config.repost_price_window);
+                if let Err(e) = storage.mark_notified_with_hash(&cheapest.id, Some(&hash)) {
+                    warn!("❌ [cheapest] Mark notified failed: {:?}", e);
+                }
+                if let Err(e) = storage.set_best_deal(model_name, &cheapest.id) {
+                    warn!("❌ [cheapest] Saving best deal failed: {:?}", e);
+                }
+                if priority_bypass && let Err(e) = storage.set_meta(&bypass_key, "1") {
+                    warn!("❌ [cheapest] Saving the priority bypass failed: {:?}", e);
+                }
+            }
+            Err(e) => {
+                warn!("❌ [cheapest] Error sending notification: {:?}", e);
+            }
+        }
+    } else {
+        warn!("⚠️ [cheapest] No offer with a valid price for '{}'", model_name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{app_config, offer, RecordingNotifier, TempStorage};
+
+    async fn run(storage: &SqliteStorage, config: &AppConfig, notifier: &RecordingNotifier) -> Vec<String> {
+        let before = notifier.sent().len();
+        check_and_notify_cheapest_for_model("test", storage, config, notifier).await;
+        notifier.sent()[before..].to_vec()
+    }
+
+    #[tokio::test]
+    async fn a_new_cheaper_offer_is_notified_before_the_cooldown_ends() {
+        let storage = TempStorage::new();
+        let config = app_config(vec![]);
+        let notifier = RecordingNotifier::default();
+        storage.save_offer(&offer("o1", "iPhone 13", 500.0)).unwrap();
+
+        assert_eq!(run(&storage, &config, &notifier).await, vec!["offer:o1"]);
+        // Same cheapest offer: waits for the cooldown
+        assert!(run(&storage, &config, &notifier).await.is_empty());
+
+        storage.save_offer(&offer("o2", "iPhone 13", 450.0)).unwrap();
+        assert_eq!(run(&storage, &config, &notifier).await, vec!["offer:o2"]);
+        assert_eq!(storage.get_best_deal("test").unwrap().as_deref(), Some("o2"));
+
+        // The best deal is persisted, so a restart doesn't announce it again
+        let reopened = SqliteStorage::new(&storage.path).unwrap();
+        assert!(run(&reopened, &config, &notifier).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn a_priority_offer_skips_the_cooldown_once_when_bypass_is_on() {
+        let mut model_cfg = crate::config::ModelConfig::permissive("test");
+        model_cfg.priority_keywords = vec!["OVP".to_string()];
+        model_cfg.priority_bypass_cooldown = true;
+        let storage = TempStorage::new();
+        let notifier = RecordingNotifier::default();
+        storage.save_offer(&offer("o1", "iPhone 13", 500.0)).unwrap();
+        assert_eq!(run(&storage, &app_config(vec![model_cfg.clone()]), &notifier).await, vec!["offer:o1"]);
+
+        // The seller edits the cheapest listing into a priority one within the cooldown
+        storage.save_offer(&offer("o1", "iPhone 13 neu ovp", 500.0)).unwrap();
+        let mut without_bypass = model_cfg.clone();
+        without_bypass.priority_bypass_cooldown = false;
+        assert!(run(&storage, &app_config(vec![without_bypass]), &notifier).await.is_empty());
+
+        let config = app_config(vec![model_cfg]);
+        assert_eq!(run(&storage, &config, &notifier).await, vec!["offer:o1"]);
+        assert!(run(&storage, &config, &notifier).await.is_empty());
+    }
+}
@@ -1,3 +1,8 @@
+pub mod cheapest;
 pub mod telegram;
+pub mod traits;
+pub mod webhook;
 
-pub use telegram::TelegramNotifier;
\ No newline at end of file
+pub use telegram::TelegramNotifier;
+pub use traits::Notifier;
+pub use webhook::WebhookNotifier;
@@ -1,41 +1,141 @@
 // notifier/telegram/command_handler.rs
 
-use crate::notifier::telegram::TelegramNotifier;
+use crate::analyzer::market_indicators::MarketAnalyzer;
+use crate::analyzer::AnalyzerImpl;
+use crate::config::{AppConfig, ModelConfig};
+use crate::model::{ExportFormat, SortKey};
+use crate::parser::KleinanzeigenParser;
+use crate::scraper::ScraperImpl;
+use crate::notifier::telegram::{sender, TelegramNotifier};
 use tracing::{info, warn};
 
+/// Maximum number of matches returned by /search.
+const SEARCH_LIMIT: usize = 10;
+
+/// Maximum number of deals listed by /deals.
+const DEALS_LIMIT: usize = 10;
+
+/// Number of parsed offers shown by /debug.
+const DEBUG_OFFERS_LIMIT: usize = 5;
+
+/// Number of runs listed by /runs.
+const RUNS_LIMIT: usize = 10;
+
+/// Number of days shown by /history.
+const HISTORY_DAYS: u32 = 14;
+
+/// Days of daily averages loaded for /bands.
+const BANDS_HISTORY_DAYS: u32 = 60;
+/// Bollinger window (days) and width (standard deviations) used by /bands.
+const BANDS_PERIOD: usize = 7;
+const BANDS_NUM_STD: f64 = 2.0;
+
+/// Splits a message into the command (without any `@botname` suffix) and its trimmed arguments.
+/// For example, `/top5@mybot iphone 13` becomes `("/top5", "iphone 13")`.
+pub fn parse_command(text: &str) -> (&str, &str) {
+    let text = text.trim();
+    let (head, args) = match text.split_once(char::is_whitespace) {
+        Some((head, args)) => (head, args.trim()),
+        None => (text, ""),
+    };
+    let command = head.split('@').next().unwrap_or(head);
+    (command, args)
+}
+
 /// Handles an incoming command and triggers the corresponding action.
-pub async fn handle_command(command_text: &str, notifier: &TelegramNotifier) {
+/// `chat_id` is the chat the command came from; every reply goes there.
+/// Commands from chats that `is_chat_allowed` rejects are ignored without a reply.
+pub async fn handle_command(command_text: &str, chat_id: i64, notifier: &TelegramNotifier) {
+    let (command, args) = parse_command(command_text);
+    if !notifier.config.load().is_chat_allowed(chat_id) {
+        warn!("Ignoring {} from unauthorized chat {}", command, chat_id);
+        return;
+    }
     info!("Handling command: {}", command_text);
-    match command_text {
+    let model_filter = if args.is_empty() { None } else { Some(args) };
+    match command {
         "/ping" => {
-            if let Err(e) = notifier.notify_text("✅ I am online!").await {
+            if let Err(e) = notifier.notify_text_to(chat_id, "✅ I am online!").await {
                 warn!("/ping error: {:?}", e);
             }
         },
         "/status" => {
-            if let Err(e) = notifier.notify_text("📊 Analyzer is running. Waiting for the next check.").await {
+            let mut msg = if notifier.is_paused() {
+                String::from("⏸ All scraping and notifications are paused. Use /resume_all to continue.")
+            } else {
+                String::from("📊 Analyzer is running. Waiting for the next check.")
+            };
+            if let Some(remaining) = notifier.mute_remaining() {
+                msg.push_str(&format!(
+                    "\n🔇 Muted for another {}h {:02}m.",
+                    remaining.num_hours(),
+                    remaining.num_minutes() % 60
+                ));
+            }
+            if let Err(e) = notifier.notify_text_to(chat_id, &msg).await {
                 warn!("/status error: {:?}", e);
             }
         },
+        "/mute" => {
+            let msg = match args.parse::<u64>() {
+                Ok(hours) if hours > 0 => match notifier.mute_for_hours(hours) {
+                    Ok(()) => format!("🔇 Notifications muted for {}h. Offers are still collected; use /unmute to resume.", hours),
+                    Err(e) => format!("❌ Error: {:?}", e),
+                },
+                _ => "ℹ️ Usage: /mute <hours>".to_string(),
+            };
+            if let Err(e) = notifier.notify_text_to(chat_id, &msg).await {
+                warn!("/mute error: {:?}", e);
+            }
+        },
+        "/unmute" => {
+            let msg = match notifier.unmute() {
+                Ok(()) => "🔔 Notifications resumed.".to_string(),
+                Err(e) => format!("❌ Error: {:?}", e),
+            };
+            if let Err(e) = notifier.notify_text_to(chat_id, &msg).await {
+                warn!("/unmute error: {:?}", e);
+            }
+        },
         "/help" => {
             let help_msg = "📋 Available commands:\n\
                 /ping — check connection\n\
                 /status — analyzer status\n\
                 /help — command list\n\
-                /last — last great deal\n\
-                /top5 — top 5 offers\n\
-                /avg — average price\n\
+                /last [model] — last great deal\n\
+                /top5 [model] — top 5 offers\n\
+                /deals [model] — current deals ranked by score\n\
+                /avg [model] — average price\n\
+                /runs [model] — recent scrape runs with duration and offer count\n\
+                /market — models ranked by price vs. their average\n\
+                /stats [model] — avg, median, σ, min/max and offer count\n\
                 /config — current configuration\n\
+                /reload — re-read config.json\n\
                 /refresh — manual restart\n\
-                /uptime — service uptime";
-            if let Err(e) = notifier.notify_text(help_msg).await {
+                /mute <hours> — pause offer notifications\n\
+                /unmute — resume offer notifications\n\
+                /uptime — service uptime\n\
+                /history <model> — daily average price trend\n\
+                /bands <model> — Bollinger bands vs. cheapest offer\n\
+                /export [csv|json] — download all offers\n\
+                /search <terms> — find stored offers by keyword\n\
+                /subscribe <model> — notify this chat about a model\n\
+                /unsubscribe <model> — stop notifying this chat\n\
+                /enable <model> — resume processing a model\n\
+                /disable <model> — pause processing a model\n\
+                /ignore <offer_id> — never flag an offer as a deal\n\
+                /unignore <offer_id> — remove an offer from the ignore list\n\
+                /debug <url> — fetch a Kleinanzeigen page and show what the parser extracts\n\
+                /pause_all — pause all scraping and notifications\n\
+                /resume_all — resume scraping and notifications";
+            if let Err(e) = notifier.notify_text_to(chat_id, help_msg).await {
                 warn!("/help error: {:?}", e);
             }
         },
         "/refresh" => {
             info!("/refresh command received, triggering refresh...");
             notifier.refresh_notify.notify_one();
-            if let Err(e) = notifier.notify_text("🔄 Forced restart initiated.").await {
+            if let Err(e) = notifier.notify_text_to(chat_id, "🔄 Forced restart initiated.").await {
                 warn!("/refresh error: {:?}", e);
             }
         },
@@ -47,126 +147,759 @@ pub async fn handle_command(command_text: &str, notifier: &TelegramNotifier) {
                 (uptime.as_secs() % 3600) / 60,
                 uptime.as_secs() % 60
             );
-            if let Err(e) = notifier.notify_text(&msg).await {
+            if let Err(e) = notifier.notify_text_to(chat_id, &msg).await {
                 warn!("/uptime error: {:?}", e);
             }
         },
         "/last" => {
-            match notifier.storage.lock().await.get_last_offer() {
+            match notifier.storage.get_last_offer(model_filter) {
                 Ok(Some(offer)) => {
                     let msg = format!(
-                        "🕵️ Last offer:\n📦 {}\n💰 {:.2} €\n📍 {}\n🔗 {}",
-                        offer.title, offer.price, offer.location, offer.link
+                        "🕵️ Last offer:\n📦 {}\n💰 {}\n📍 {}\n🔗 {}",
+                        offer.title,
+                        notifier.config.load().format_price(offer.price),
+                        offer.location,
+                        offer.link
                     );
-                    if let Err(e) = notifier.notify_text(&msg).await {
+                    if let Err(e) = notifier.notify_text_to(chat_id, &msg).await {
                         warn!("/last notify error: {:?}", e);
                     }
                 },
                 Ok(None) => {
-                    if let Err(e) = notifier.notify_text("📭 No offers in the database.").await {
+                    if let Err(e) = notifier.notify_text_to(chat_id, "📭 No offers in the database.").await {
                         warn!("/last empty notify error: {:?}", e);
                     }
                 },
                 Err(e) => {
-                    if let Err(send_err) = notifier.notify_text(&format!("❌ Error: {:?}", e)).await {
+                    if let Err(send_err) = notifier.notify_text_to(chat_id, &format!("❌ Error: {:?}", e)).await {
                         warn!("/last send error: {:?}", send_err);
                     }
                 }
             }
         },
         "/top5" => {
-            match notifier.storage.lock().await.get_top5_offers() {
+            match notifier.storage.get_offers(model_filter, SortKey::PriceAsc, 5, 0) {
                 Ok(offers) if !offers.is_empty() => {
+                    let config = notifier.config.load();
                     let mut msg = String::from("🏆 Top-5 best offers:\n");
                     for (i, offer) in offers.iter().enumerate() {
                         msg.push_str(&format!(
-                            "{}. {} — {:.2} €\n📍 {}\n🔗 {}\n\n",
+                            "{}. {} — {}\n📍 {}\n🔗 {}\n\n",
                             i + 1,
                             offer.title,
-                            offer.price,
+                            config.format_price(offer.price),
                             offer.location,
                             offer.link
                         ));
                     }
-                    if let Err(e) = notifier.notify_text(&msg).await {
+                    if let Err(e) = notifier.notify_text_to(chat_id, &msg).await {
                         warn!("/top5 notify error: {:?}", e);
                     }
                 },
                 Ok(_) => {
-                    if let Err(e) = notifier.notify_text("📭 No offers in the database.").await {
+                    if let Err(e) = notifier.notify_text_to(chat_id, "📭 No offers in the database.").await {
                         warn!("/top5 empty notify error: {:?}", e);
                     }
                 },
                 Err(e) => {
-                    if let Err(send_err) = notifier.notify_text(&format!("❌ Error: {:?}", e)).await {
+                    if let Err(send_err) = notifier.notify_text_to(chat_id, &format!("❌ Error: {:?}", e)).await {
                         warn!("/top5 send error: {:?}", send_err);
                     }
                 }
             }
         },
         "/avg" => {
-            match notifier.storage.lock().await.get_average_prices() {
+            match notifier.storage.get_average_prices(model_filter) {
                 Ok(prices) if !prices.is_empty() => {
+                    let config = notifier.config.load();
                     let mut msg = String::from("📊 Average prices by model:\n");
                     for (model, price) in prices {
-                        msg.push_str(&format!("🔹 {} — {:.2} €\n", model, price));
+                        msg.push_str(&format!("🔹 {} — {}\n", model, config.format_price(price)));
                     }
-                    if let Err(e) = notifier.notify_text(&msg).await {
+                    if let Err(e) = notifier.notify_text_to(chat_id, &msg).await {
                         warn!("/avg notify error: {:?}", e);
                     }
                 },
                 Ok(_) => {
-                    if let Err(e) = notifier.notify_text("📭 No model statistics available.").await {
+                    if let Err(e) = notifier.notify_text_to(chat_id, "📭 No model statistics available.").await {
                         warn!("/avg empty notify error: {:?}", e);
                     }
                 },
                 Err(e) => {
-                    if let Err(send_err) = notifier.notify_text(&format!("❌ Error: {:?}", e)).await {
+                    if let Err(send_err) = notifier.notify_text_to(chat_id, &format!("❌ Error: {:?}", e)).await {
                         warn!("/avg send error: {:?}", send_err);
                     }
                 }
             }
         },
+        "/stats" => {
+            let config = notifier.config.load();
+            let mut msg = String::from("📈 Model statistics:\n");
+            for model_cfg in config
+                .models
+                .iter()
+                .filter(|m| model_filter.is_none_or(|f| m.query.eq_ignore_ascii_case(f)))
+            {
+                let stats = notifier.storage.get_stats(&model_cfg.query);
+                let offers = notifier.storage.get_offers_for_model(&model_cfg.query);
+                match (stats, offers) {
+                    (Ok(stats), Ok(offers)) => {
+                        let prices: Vec<f64> = offers.iter().map(|o| o.price).filter(|p| *p > 0.0).collect();
+                        msg.push_str(&format!("\n🔹 {} — {} offers", model_cfg.query, offers.len()));
+                        if prices.len() < config.min_stats_samples {
+                            msg.push_str(" ⚠️ low sample");
+                        }
+                        match stats {
+                            Some(s) => msg.push_str(&format!(
+                                "\n   avg {} · median {} · σ {}",
+                                config.format_price(s.avg_price),
+                                config.format_price(s.median_price),
+                                config.format_price(s.std_dev)
+                            )),
+                            None => msg.push_str("\n   no stored stats yet"),
+                        }
+                        if !prices.is_empty() {
+                            let min = prices.iter().copied().fold(f64::INFINITY, f64::min);
+                            let max = prices.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+                            msg.push_str(&format!(
+                                "\n   min {} · max {}",
+                                config.format_price(min),
+                                config.format_price(max)
+                            ));
+                        }
+                    }
+                    (Err(e), _) | (_, Err(e)) => {
+                        warn!("/stats query error for '{}': {:?}", model_cfg.query, e);
+                        msg.push_str(&format!("\n🔹 {} — ❌ {:?}", model_cfg.query, e));
+                    }
+                }
+            }
+            if msg.lines().count() == 1 {
+                msg = "📭 No matching models configured.".to_string();
+            }
+            if let Err(e) = notifier.notify_text_to(chat_id, &msg).await {
+                warn!("/stats error: {:?}", e);
+            }
+        },
+        "/runs" => {
+            let msg = match notifier.storage.get_recent_scrape_runs(model_filter, RUNS_LIMIT) {
+                Ok(runs) if !runs.is_empty() => {
+                    let mut msg = String::from("⏱ Recent runs:\n");
+                    for run in &runs {
+                        msg.push_str(&format!(
+                            "\n{} {} — {} offers in {:.1}s",
+                            run.started_at.with_timezone(&chrono::Local).format("%d.%m %H:%M"),
+                            run.model,
+                            run.offer_count,
+                            run.duration_ms as f64 / 1000.0
+                        ));
+                    }
+                    msg
+                }
+                Ok(_) => "📭 No runs recorded yet.".to_string(),
+                Err(e) => format!("❌ Error: {:?}", e),
+            };
+            if let Err(e) = notifier.notify_text_to(chat_id, &msg).await {
+                warn!("/runs notify error: {:?}", e);
+            }
+        },
+        "/market" => {
+            let msg = match notifier.storage.get_market_snapshots() {
+                Ok(mut snapshots) if !snapshots.is_empty() => {
+                    // Biggest drop below the stored average first
+                    snapshots.sort_by(|a, b| a.deviation().total_cmp(&b.deviation()));
+                    let config = notifier.config.load();
+                    let mut msg = String::from("🌍 Market overview (current vs. stored average):\n");
+                    for s in &snapshots {
+                        let icon = if s.deviation() < 0.0 { "🟢" } else { "🔴" };
+                        msg.push_str(&format!(
+                            "\n{} {} — {:+.1}% · now {} vs {} · cheapest {} ({} offers)",
+                            icon,
+                            s.model,
+                            s.deviation() * 100.0,
+                            config.format_price(s.current_avg_price),
+                            config.format_price(s.stats_avg_price),
+                            config.format_price(s.cheapest_price),
+                            s.offer_count
+                        ));
+                    }
+                    msg
+                }
+                Ok(_) => "📭 No model statistics available.".to_string(),
+                Err(e) => format!("❌ Error: {:?}", e),
+            };
+            if let Err(e) = notifier.notify_text_to(chat_id, &msg).await {
+                warn!("/market notify error: {:?}", e);
+            }
+        },
+        "/deals" => {
+            let config = notifier.config.load();
+            let (deals, errors) = AnalyzerImpl::new().current_deals(&notifier.storage, &config, model_filter);
+
+            let mut msg = if deals.is_empty() {
+                "📭 No deals right now.".to_string()
+            } else {
+                let mut msg = String::from("💸 Current deals:\n");
+                for (i, deal) in deals.iter().take(DEALS_LIMIT).enumerate() {
+                    msg.push_str(&format!(
+                        "{}. {} — {} (⭐ {:.0})\n🔗 {}\n\n",
+                        i + 1,
+                        deal.offer.title,
+                        config.format_price(deal.offer.price),
+                        deal.score,
+                        deal.offer.link
+                    ));
+                }
+                msg
+            };
+            for error in errors {
+                msg.push_str(&format!("\n❌ Error: {}", error));
+            }
+            if let Err(e) = notifier.notify_text_to(chat_id, &msg).await {
+                warn!("/deals notify error: {:?}", e);
+            }
+        },
         "/config" => {
-            if notifier.config.models.is_empty() {
-                if let Err(e) = notifier.notify_text("⚠️ No models loaded in the configuration.").await {
+            let config = notifier.config.load();
+            if config.models.is_empty() {
+                if let Err(e) = notifier.notify_text_to(chat_id, "⚠️ No models loaded in the configuration.").await {
                     warn!("/config empty error: {:?}", e);
                 }
             } else {
+                let disabled = notifier.storage.get_disabled_models().unwrap_or_else(|e| {
+                    warn!("/config disabled models error: {:?}", e);
+                    Default::default()
+                });
                 let mut msg = String::from("⚙️ Loaded models:\n");
-                for model in &notifier.config.models {
-                    msg.push_str(&format!("🔸 {} [{}]\n", model.query, model.category_id));
+                for model in &config.models {
+                    msg.push_str(&format!("🔸 {} [{}]", model.query, model.category_id));
+                    if disabled.contains(&model.query) {
+                        msg.push_str(" ⏸ disabled");
+                    }
+                    msg.push('\n');
                 }
-                if let Err(e) = notifier.notify_text(&msg).await {
+                if let Err(e) = notifier.notify_text_to(chat_id, &msg).await {
                     warn!("/config notify error: {:?}", e);
                 }
             }
         },
+        "/reload" => {
+            // Build a scraper from the new file up front, so the main loop never meets a config it can't use
+            let check = |config: &AppConfig| {
+                ScraperImpl::new(config)
+                    .map(drop)
+                    .map_err(|e| format!("the scraper rejects it: {:?}", e))
+            };
+            let msg = match notifier.config.reload(check) {
+                Ok(config) => format!("✅ Configuration reloaded: {} models. Applied on the next cycle.", config.models.len()),
+                Err(e) => format!("❌ Config reload failed, keeping the old configuration: {}", e),
+            };
+            info!("/reload: {}", msg);
+            if let Err(e) = notifier.notify_text_to(chat_id, &msg).await {
+                warn!("/reload notify error: {:?}", e);
+            }
+        },
         "/force_notify" => {
-            match notifier.storage.lock().await.get_last_offer() {
+            let last_offer = notifier.storage.get_last_offer(None);
+            match last_offer {
                 Ok(Some(offer)) => {
                     match notifier.notify(&offer).await {
                         Ok(_) => {
-                            let _ = notifier.storage.lock().await.mark_notified(&offer.id);
+                            let _ = notifier.storage.mark_notified(&offer.id);
                         },
                         Err(e) => {
-                            if let Err(se) = notifier.notify_text(&format!("❌ Error sending: {:?}", e)).await {
+                            if let Err(se) = notifier.notify_text_to(chat_id, &format!("❌ Error sending: {:?}", e)).await {
                                 warn!("/force_notify send error: {:?}", se);
                             }
                         }
                     }
                 },
                 _ => {
-                    if let Err(e) = notifier.notify_text("❌ No last offer available for notification.").await {
+                    if let Err(e) = notifier.notify_text_to(chat_id, "❌ No last offer available for notification.").await {
                         warn!("/force_notify notify error: {:?}", e);
                     }
                 }
             }
         },
+        "/history" => {
+            let model = args;
+            if model.is_empty() {
+                if let Err(e) = notifier.notify_text_to(chat_id, "ℹ️ Usage: /history <model>").await {
+                    warn!("/history usage notify error: {:?}", e);
+                }
+                return;
+            }
+            match notifier.storage.get_daily_average_prices(model, HISTORY_DAYS) {
+                Ok(points) if !points.is_empty() => {
+                    let prices: Vec<f64> = points.iter().map(|(_, price)| *price).collect();
+                    let config = notifier.config.load();
+                    let mut msg = format!("📈 Price history for {}:\n{}\n\n", model, sparkline(&prices));
+                    for (day, price) in &points {
+                        msg.push_str(&format!("{} — {}\n", day, config.format_price(*price)));
+                    }
+                    if let Err(e) = notifier.notify_text_to(chat_id, &msg).await {
+                        warn!("/history notify error: {:?}", e);
+                    }
+                },
+                Ok(_) => {
+                    if let Err(e) = notifier.notify_text_to(chat_id, &format!("📭 No data for model '{}'.", model)).await {
+                        warn!("/history empty notify error: {:?}", e);
+                    }
+                },
+                Err(e) => {
+                    if let Err(send_err) = notifier.notify_text_to(chat_id, &format!("❌ Error: {:?}", e)).await {
+                        warn!("/history send error: {:?}", send_err);
+                    }
+                }
+            }
+        },
+        "/bands" => {
+            let model = args;
+            if model.is_empty() {
+                if let Err(e) = notifier.notify_text_to(chat_id, "ℹ️ Usage: /bands <model>").await {
+                    warn!("/bands usage notify error: {:?}", e);
+                }
+                return;
+            }
+            let (history, cheapest) = {
+                let storage = &notifier.storage;
+                (
+                    storage.get_daily_average_prices(model, BANDS_HISTORY_DAYS),
+                    storage.get_offers(Some(model), SortKey::PriceAsc, 1, 0),
+                )
+            };
+            let msg = match (history, cheapest) {
+                (Ok(points), Ok(offers)) => {
+                    let prices: Vec<f64> = points.iter().map(|(_, price)| *price).collect();
+                    let bands = MarketAnalyzer::bollinger_bands(&prices, BANDS_PERIOD, BANDS_NUM_STD);
+                    match (bands.last(), offers.first()) {
+                        (Some((lower, middle, upper)), Some(offer)) => {
+                            let verdict = if offer.price < *lower {
+                                "🔥 Below the lower band — strong buy signal!"
+                            } else if offer.price > *upper {
+                                "📈 Above the upper band."
+                            } else {
+                                "➖ Within the bands."
+                            };
+                            let config = notifier.config.load();
+                            format!(
+                                "📊 Bollinger bands for {} ({}d, {}σ):\n\
+                                 ⬇️ Lower: {}\n⏺ Middle: {}\n⬆️ Upper: {}\n\n\
+                                 💰 Cheapest now: {}\n🔗 {}\n{}",
+                                model, BANDS_PERIOD, BANDS_NUM_STD,
+                                config.format_price(*lower), config.format_price(*middle), config.format_price(*upper),
+                                config.format_price(offer.price), offer.link, verdict
+                            )
+                        }
+                        (None, _) => format!(
+                            "📭 Not enough history for '{}' (need {} days, have {}).",
+                            model, BANDS_PERIOD, prices.len()
+                        ),
+                        (_, None) => format!("📭 No active offers for model '{}'.", model),
+                    }
+                }
+                (Err(e), _) | (_, Err(e)) => format!("❌ Error: {:?}", e),
+            };
+            if let Err(e) = notifier.notify_text_to(chat_id, &msg).await {
+                warn!("/bands notify error: {:?}", e);
+            }
+        },
+        "/export" => {
+            let format = match args.to_lowercase().as_str() {
+                "" | "csv" => ExportFormat::Csv,
+                "json" => ExportFormat::Json,
+                _ => {
+                    if let Err(e) = notifier.notify_text_to(chat_id, "ℹ️ Usage: /export [csv|json]").await {
+                        warn!("/export usage notify error: {:?}", e);
+                    }
+                    return;
+                }
+            };
+            let export = notifier.storage.export_offers(format);
+            let result = match export {
+                Ok(content) => {
+                    let filename = format!(
+                        "offers-{}.{}",
+                        chrono::Utc::now().format("%Y%m%d-%H%M%S"),
+                        format.extension()
+                    );
+                    sender::send_document(notifier, chat_id, &filename, content.into_bytes(), "📦 Offers export").await
+                }
+                Err(e) => notifier.notify_text_to(chat_id, &format!("❌ Error: {:?}", e)).await,
+            };
+            if let Err(e) = result {
+                warn!("/export error: {:?}", e);
+            }
+        },
+        "/search" => {
+            if args.is_empty() {
+                if let Err(e) = notifier.notify_text_to(chat_id, "ℹ️ Usage: /search <terms>").await {
+                    warn!("/search usage notify error: {:?}", e);
+                }
+                return;
+            }
+            let found = notifier.storage.search_offers(args, SEARCH_LIMIT);
+            let msg = match found {
+                Ok(offers) if !offers.is_empty() => {
+                    let config = notifier.config.load();
+                    let mut msg = format!("🔎 Results for '{}':\n", args);
+                    for (i, offer) in offers.iter().enumerate() {
+                        msg.push_str(&format!(
+                            "{}. {} — {}\n🔗 {}\n\n",
+                            i + 1,
+                            offer.title,
+                            config.format_price(offer.price),
+                            offer.link
+                        ));
+                    }
+                    msg
+                },
+                Ok(_) => format!("📭 Nothing found for '{}'.", args),
+                Err(e) => format!("❌ Error: {:?}", e),
+            };
+            if let Err(e) = notifier.notify_text_to(chat_id, &msg).await {
+                warn!("/search notify error: {:?}", e);
+            }
+        },
+        "/subscribe" | "/unsubscribe" => {
+            let Some(model) = notifier
+                .config
+                .load()
+                .models
+                .iter()
+                .find(|m| m.query.eq_ignore_ascii_case(args))
+                .map(|m| m.query.clone())
+            else {
+                let msg = if args.is_empty() {
+                    format!("ℹ️ Usage: {} <model>", command)
+                } else {
+                    format!("⚠️ Unknown model '{}'. See /config for the list.", args)
+                };
+                if let Err(e) = notifier.notify_text_to(chat_id, &msg).await {
+                    warn!("{} usage notify error: {:?}", command, e);
+                }
+                return;
+            };
+
+            let result = if command == "/subscribe" {
+                notifier.storage.subscribe(chat_id, &model)
+            } else {
+                notifier.storage.unsubscribe(chat_id, &model)
+            };
+            let msg = match (command, result) {
+                ("/subscribe", Ok(true)) => format!("🔔 Subscribed to {}.", model),
+                ("/subscribe", Ok(false)) => format!("ℹ️ Already subscribed to {}.", model),
+                (_, Ok(true)) => format!("🔕 Unsubscribed from {}.", model),
+                (_, Ok(false)) => format!("ℹ️ Not subscribed to {}.", model),
+                (_, Err(e)) => format!("❌ Error: {:?}", e),
+            };
+            if let Err(e) = notifier.notify_text_to(chat_id, &msg).await {
+                warn!("{} notify error: {:?}", command, e);
+            }
+        },
+        "/enable" | "/disable" => {
+            let Some(model) = notifier
+                .config
+                .load()
+                .models
+                .iter()
+                .find(|m| m.query.eq_ignore_ascii_case(args))
+                .map(|m| m.query.clone())
+            else {
+                let msg = if args.is_empty() {
+                    format!("ℹ️ Usage: {} <model>", command)
+                } else {
+                    format!("⚠️ Unknown model '{}'. See /config for the list.", args)
+                };
+                if let Err(e) = notifier.notify_text_to(chat_id, &msg).await {
+                    warn!("{} usage notify error: {:?}", command, e);
+                }
+                return;
+            };
+
+            let enable = command == "/enable";
+            let msg = match (enable, notifier.storage.set_model_enabled(&model, enable)) {
+                (true, Ok(true)) => format!("▶️ {} enabled, it will be processed from the next cycle.", model),
+                (true, Ok(false)) => format!("ℹ️ {} is already enabled.", model),
+                (false, Ok(true)) => format!("⏸ {} disabled. Its stored history is kept.", model),
+                (false, Ok(false)) => format!("ℹ️ {} is already disabled.", model),
+                (_, Err(e)) => format!("❌ Error: {:?}", e),
+            };
+            if let Err(e) = notifier.notify_text_to(chat_id, &msg).await {
+                warn!("{} notify error: {:?}", command, e);
+            }
+        },
+        "/ignore" | "/unignore" => {
+            if args.is_empty() {
+                if let Err(e) = notifier.notify_text_to(chat_id, &format!("ℹ️ Usage: {} <offer_id>", command)).await {
+                    warn!("{} usage notify error: {:?}", command, e);
+                }
+                return;
+            }
+
+            let result = if command == "/ignore" {
+                notifier.storage.ignore_offer(args)
+            } else {
+                notifier.storage.unignore_offer(args)
+            };
+            let msg = match (command, result) {
+                ("/ignore", Ok(true)) => format!("🙈 Offer {} ignored.", args),
+                ("/ignore", Ok(false)) => format!("ℹ️ Offer {} is already ignored.", args),
+                (_, Ok(true)) => format!("👀 Offer {} is no longer ignored.", args),
+                (_, Ok(false)) => format!("ℹ️ Offer {} was not ignored.", args),
+                (_, Err(e)) => format!("❌ Error: {:?}", e),
+            };
+            if let Err(e) = notifier.notify_text_to(chat_id, &msg).await {
+                warn!("{} notify error: {:?}", command, e);
+            }
+        },
+        "/pause_all" | "/resume_all" => {
+            let pause = command == "/pause_all";
+            let msg = match (pause, notifier.set_paused(pause)) {
+                (true, Ok(true)) => "⏸ All scraping and notifications paused. Commands still work; use /resume_all to continue.".to_string(),
+                (true, Ok(false)) => "ℹ️ Already paused.".to_string(),
+                (false, Ok(true)) => "▶️ Resumed, the next check runs on schedule (or use /refresh).".to_string(),
+                (false, Ok(false)) => "ℹ️ Not paused.".to_string(),
+                (_, Err(e)) => format!("❌ Error: {:?}", e),
+            };
+            if let Err(e) = notifier.notify_text_to(chat_id, &msg).await {
+                warn!("{} notify error: {:?}", command, e);
+            }
+        },
+        "/debug" => {
+            if args.is_empty() {
+                if let Err(e) = notifier.notify_text_to(chat_id, "ℹ️ Usage: /debug <url>").await {
+                    warn!("/debug usage notify error: {:?}", e);
+                }
+                return;
+            }
+            let msg = debug_url(args, notifier).await;
+            if let Err(e) = notifier.notify_text_to(chat_id, &msg).await {
+                warn!("/debug notify error: {:?}", e);
+            }
+        },
         _ => {
-            if let Err(e) = notifier.notify_text("🤖 Unknown command. Type /help for a list of commands.").await {
+            if let Err(e) = notifier.notify_text_to(chat_id, "🤖 Unknown command. Type /help for a list of commands.").await {
                 warn!("Unknown command notify error: {:?}", e);
             }
         }
     }
-}
\ No newline at end of file
+}
+
+/// Fetches a kleinanzeigen.de page with the scraper's client and parses it with a permissive
+/// model config, describing the parse report and the first offers, or the error.
+async fn debug_url(url: &str, notifier: &TelegramNotifier) -> String {
+    let is_kleinanzeigen = reqwest::Url::parse(url).ok().is_some_and(|u| {
+        matches!(u.scheme(), "http" | "https")
+            && u.host_str().is_some_and(|h| h == "kleinanzeigen.de" || h.ends_with(".kleinanzeigen.de"))
+    });
+    if !is_kleinanzeigen {
+        return "⚠️ Only kleinanzeigen.de URLs can be debugged.".to_string();
+    }
+
+    let scraper = match ScraperImpl::new(&notifier.config.load()) {
+        Ok(scraper) => scraper,
+        Err(e) => return format!("❌ Scraper error: {:?}", e),
+    };
+    let html = match scraper.fetch_url(url).await {
+        Ok(html) => html,
+        Err(e) => return format!("❌ Scraper error: {:?}", e),
+    };
+    let config = notifier.config.load();
+    let parsed = KleinanzeigenParser::new().and_then(|parser| {
+        parser
+            .with_number_format(config.number_format)
+            .parse_filtered(&html, &ModelConfig::permissive("debug"))
+    });
+    let (offers, report) = match parsed {
+        Ok(parsed) => parsed,
+        Err(e) => return format!("❌ Parser error: {:?}", e),
+    };
+
+    let mut msg = format!("🐞 {} KB fetched, {} offers parsed\n📊 {}\n\n", html.len() / 1024, offers.len(), report);
+    for (i, offer) in offers.iter().take(DEBUG_OFFERS_LIMIT).enumerate() {
+        msg.push_str(&format!(
+            "{}. {} — {} ({:?})\n📍 {} | 🆔 {}\n🔗 {}\n\n",
+            i + 1,
+            offer.title,
+            config.format_price(offer.price),
+            offer.price_kind,
+            offer.location,
+            offer.id,
+            offer.link
+        ));
+    }
+    msg
+}
+
+/// Renders a series of values as a compact unicode sparkline.
+fn sparkline(values: &[f64]) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let min = values.iter().cloned().fold(f64::INFINITY, f64::min);
+    let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let span = max - min;
+    values
+        .iter()
+        .map(|v| {
+            if span <= f64::EPSILON {
+                BARS[BARS.len() / 2]
+            } else {
+                let idx = ((v - min) / span * (BARS.len() - 1) as f64).round() as usize;
+                BARS[idx.min(BARS.len() - 1)]
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{load_config, SharedConfig};
+    use crate::notifier::telegram::PAUSED_KEY;
+    use crate::test_support::{
+        app_config, config_file, offer, scripted_server, telegram_notifier, telegram_notifier_with, TempFile, TempStorage,
+    };
+    use std::sync::Arc;
+
+    #[test]
+    fn parse_command_splits_off_arguments() {
+        assert_eq!(parse_command("/ping"), ("/ping", ""));
+        assert_eq!(parse_command("/top5 iphone 13"), ("/top5", "iphone 13"));
+        assert_eq!(parse_command("  /mute   30  "), ("/mute", "30"));
+        assert_eq!(parse_command("/search\tiphone  pro"), ("/search", "iphone  pro"));
+        assert_eq!(parse_command("/history\niphone 13"), ("/history", "iphone 13"));
+    }
+
+    #[test]
+    fn parse_command_drops_the_bot_name() {
+        assert_eq!(parse_command("/top5@mybot iphone 13"), ("/top5", "iphone 13"));
+        assert_eq!(parse_command("/status@mybot"), ("/status", ""));
+        assert_eq!(parse_command("/search mail@example.com"), ("/search", "mail@example.com"));
+    }
+
+    #[test]
+    fn parse_command_of_empty_text() {
+        assert_eq!(parse_command(""), ("", ""));
+        assert_eq!(parse_command("   "), ("", ""));
+    }
+
+    #[tokio::test]
+    async fn commands_from_other_chats_are_ignored() {
+        let storage = TempStorage::new();
+        let notifier = telegram_notifier(&storage, app_config(vec![ModelConfig::permissive("iphone 13")]));
+
+        handle_command("/subscribe iphone 13", 666, &notifier).await;
+        handle_command("/pause_all", 666, &notifier).await;
+
+        assert!(storage.get_subscribers("iphone 13").unwrap().is_empty());
+        assert!(!notifier.is_paused());
+    }
+
+    #[tokio::test]
+    async fn allowed_chats_may_subscribe() {
+        let storage = TempStorage::new();
+        let mut config = app_config(vec![ModelConfig::permissive("iphone 13")]);
+        config.allowed_chat_ids = vec![42];
+        let notifier = telegram_notifier(&storage, config);
+
+        handle_command("/subscribe iphone 13", 42, &notifier).await;
+        handle_command("/subscribe iphone 13", 43, &notifier).await;
+
+        assert_eq!(storage.get_subscribers("iphone 13").unwrap(), vec![42]);
+    }
+
+    #[tokio::test]
+    async fn reload_keeps_the_old_config_when_the_scraper_rejects_the_new_one() {
+        let storage = TempStorage::new();
+        let file = TempFile::new("json");
+        std::fs::write(file.path(), config_file(serde_json::json!({}))).unwrap();
+        let config = Arc::new(SharedConfig::new(file.path(), load_config(file.path()).unwrap()));
+        let notifier = telegram_notifier_with(&storage, config.clone());
+        let before = config.load();
+
+        // Passes validate(), but reqwest can't parse the proxy URL
+        let broken = config_file(serde_json::json!({ "proxy_url": "http://[broken" }));
+        std::fs::write(file.path(), broken).unwrap();
+        handle_command("/reload", 1, &notifier).await;
+        assert!(Arc::ptr_eq(&before, &config.load()));
+
+        let changed = config_file(serde_json::json!({ "check_interval_seconds": 120 }));
+        std::fs::write(file.path(), changed).unwrap();
+        handle_command("/reload", 1, &notifier).await;
+        assert_eq!(config.load().check_interval_seconds, 120);
+    }
+
+    /// The `text` of a form-encoded sendMessage body.
+    fn sent_text(body: &str) -> String {
+        let url = reqwest::Url::parse(&format!("http://localhost/?{}", body)).unwrap();
+        url.query_pairs().find(|(key, _)| key == "text").map(|(_, text)| text.into_owned()).unwrap_or_default()
+    }
+
+    #[tokio::test]
+    async fn pause_all_and_resume_all_toggle_the_persisted_state() {
+        let (base, requests) = scripted_server(vec![(200, r#"{"ok":true,"result":{}}"#)]).await;
+        let storage = TempStorage::new();
+        let mut notifier = telegram_notifier(&storage, app_config(vec![ModelConfig::permissive("iphone 13")]));
+        notifier.api_base = base;
+
+        handle_command("/pause_all", 1, &notifier).await;
+        assert!(notifier.is_paused());
+        assert_eq!(storage.get_meta(PAUSED_KEY).unwrap().as_deref(), Some("1"));
+        handle_command("/pause_all", 1, &notifier).await;
+        handle_command("/status", 1, &notifier).await;
+
+        handle_command("/resume_all", 1, &notifier).await;
+        assert!(!notifier.is_paused());
+        assert_eq!(storage.get_meta(PAUSED_KEY).unwrap().as_deref(), Some("0"));
+
+        let replies: Vec<String> = requests.lock().unwrap().iter().map(|(_, body)| sent_text(body)).collect();
+        assert_eq!(replies.len(), 4);
+        assert!(replies[0].contains("paused"), "{}", replies[0]);
+        assert!(replies[1].contains("Already paused"), "{}", replies[1]);
+        assert!(replies[2].contains("All scraping and notifications are paused"), "{}", replies[2]);
+        assert!(replies[3].contains("Resumed"), "{}", replies[3]);
+    }
+
+    #[tokio::test]
+    async fn replies_go_to_the_chat_that_sent_the_command() {
+        let (base, requests) = scripted_server(vec![(200, r#"{"ok":true,"result":{}}"#)]).await;
+        let storage = TempStorage::new();
+        let mut config = app_config(vec![ModelConfig::permissive("iphone 13")]);
+        config.allowed_chat_ids = vec![42];
+        let mut notifier = telegram_notifier(&storage, config);
+        notifier.api_base = base;
+
+        for command in ["/ping", "/help", "/status", "/top5", "/nonsense"] {
+            handle_command(command, 42, &notifier).await;
+        }
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 5);
+        for (_, body) in requests.iter() {
+            assert!(body.starts_with("chat_id=42&"), "{}", body);
+        }
+    }
+
+    #[tokio::test]
+    async fn prices_in_replies_use_the_configured_currency_and_format() {
+        let (base, requests) = scripted_server(vec![(200, r#"{"ok":true,"result":{}}"#)]).await;
+        let storage = TempStorage::new();
+        storage.save_offer(&offer("1", "iPhone 13", 1234.5)).unwrap();
+        let mut config = app_config(vec![ModelConfig::permissive("test")]);
+        config.number_format = crate::config::NumberFormat::Swiss;
+        config.currency_symbol = "CHF".to_string();
+        let mut notifier = telegram_notifier(&storage, config);
+        notifier.api_base = base;
+
+        for command in ["/last", "/top5", "/search iphone"] {
+            handle_command(command, 1, &notifier).await;
+        }
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 3);
+        for (_, body) in requests.iter() {
+            let text = sent_text(body);
+            assert!(text.contains("1'234.50 CHF") && !text.contains('€'), "{}", text);
+        }
+    }
+}
@@ -0,0 +1,93 @@
+// notifier/telegram/digest.rs
+
+use crate::notifier::telegram::TelegramNotifier;
+use chrono::{DateTime, Duration, Local, NaiveTime, TimeZone, Utc};
+use std::sync::Arc;
+use tokio::time::sleep;
+use tracing::{info, warn};
+
+/// Sends one summary per day at `digest_hour` (local time) until the task is aborted.
+pub async fn run_digest_scheduler(notifier: Arc<TelegramNotifier>) {
+    loop {
+        let hour = notifier.config.load().digest_hour;
+        let wait = until_next_hour(Local::now(), hour);
+        info!("📰 Next digest in {}m", wait.num_minutes());
+        sleep(wait.to_std().unwrap_or_default()).await;
+
+        if notifier.is_paused() {
+            info!("⏸ Paused via /pause_all, skipping today's digest");
+        } else {
+            let to = Utc::now();
+            let msg = build_digest(&notifier, to - Duration::days(1), to);
+            if let Err(e) = notifier.notify_text(&msg).await {
+                warn!("❌ Digest send error: {:?}", e);
+            }
+        }
+        // Step past the scheduled minute so the same slot isn't picked again.
+        sleep(std::time::Duration::from_secs(60)).await;
+    }
+}
+
+/// Time left until the next `hour`:00 local time (today if still ahead, otherwise tomorrow).
+fn until_next_hour(now: DateTime<Local>, hour: u8) -> Duration {
+    let time = NaiveTime::from_hms_opt(hour.min(23) as u32, 0, 0).unwrap_or(NaiveTime::MIN);
+    let mut next = now.date_naive().and_time(time);
+    if next <= now.naive_local() {
+        next += Duration::days(1);
+    }
+    let next = Local
+        .from_local_datetime(&next)
+        .earliest()
+        .unwrap_or(now + Duration::days(1));
+    next - now
+}
+
+/// Summarizes new offers, deals and the cheapest new offer per configured model for `[from, to)`.
+pub fn build_digest(notifier: &TelegramNotifier, from: DateTime<Utc>, to: DateTime<Utc>) -> String {
+    let config = notifier.config.load();
+    let mut msg = String::from("📰 Daily digest:\n");
+    for model in &config.models {
+        let offers = notifier.storage.get_offers_first_seen_between(&model.query, from, to);
+        let deals = notifier.storage.count_deals_between(&model.query, from, to);
+        match (offers, deals) {
+            (Ok(offers), Ok(deals)) => {
+                let cheapest = offers
+                    .iter()
+                    .filter(|o| o.price > 0.0)
+                    .map(|o| o.price)
+                    .fold(None, |min: Option<f64>, p| Some(min.map_or(p, |m| m.min(p))));
+                msg.push_str(&format!("\n📦 {}: {} new offers, {} deals", model.query, offers.len(), deals));
+                if let Some(price) = cheapest {
+                    msg.push_str(&format!(", cheapest {}", config.format_price(price)));
+                }
+            }
+            (Err(e), _) | (_, Err(e)) => {
+                warn!("❌ Digest query failed for '{}': {:?}", model.query, e);
+                msg.push_str(&format!("\n📦 {}: ❌ {:?}", model.query, e));
+            }
+        }
+    }
+    msg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ModelConfig, NumberFormat};
+    use crate::test_support::{app_config, offer, telegram_notifier, TempStorage};
+
+    #[test]
+    fn the_digest_formats_prices_with_the_configured_currency() {
+        let storage = TempStorage::new();
+        storage.save_offer(&offer("1", "iPhone 13", 1234.5)).unwrap();
+        storage.save_offer(&offer("2", "iPhone 13", 1500.0)).unwrap();
+        storage.mark_deal_notified("1", "h1").unwrap();
+        let mut config = app_config(vec![ModelConfig::permissive("test")]);
+        (config.number_format, config.currency_symbol) = (NumberFormat::Swiss, "CHF".to_string());
+        let notifier = telegram_notifier(&storage, config);
+
+        let now = Utc::now();
+        let digest = build_digest(&notifier, now - Duration::hours(1), now + Duration::hours(1));
+        assert!(digest.contains("📦 test: 2 new offers, 1 deals, cheapest 1'234.50 CHF"), "{}", digest);
+    }
+}
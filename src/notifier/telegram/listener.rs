@@ -1,19 +1,32 @@
 // notifier/telegram/listener.rs
 
 use crate::notifier::telegram::command_handler::handle_command;
+use crate::notifier::telegram::sender::{self, SEEN_CALLBACK_PREFIX};
 use serde::Deserialize;
 use tokio::time::{sleep, Duration};
 use crate::notifier::telegram::TelegramNotifier;
+use crate::model::NotifyError;
 
+/// Longest pause between `getUpdates` attempts while they keep failing.
+const MAX_POLL_BACKOFF_SECS: u64 = 60;
+
+/// Bot API response envelope; `result` is only present when `ok` is true.
 #[derive(Debug, Deserialize)]
 struct TelegramApiResponse {
+    ok: bool,
+    #[serde(default)]
     result: Vec<TelegramUpdate>,
+    description: Option<String>,
+    error_code: Option<i64>,
 }
 
 #[derive(Debug, Deserialize)]
-struct TelegramUpdate {
+pub(crate) struct TelegramUpdate {
     update_id: i64,
     message: Option<TelegramMessage>,
+    /// A message edited after it was sent; commands fixed by editing are handled like new ones.
+    edited_message: Option<TelegramMessage>,
+    callback_query: Option<CallbackQuery>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -22,31 +35,300 @@ struct TelegramMessage {
     text: Option<String>,
 }
 
+/// Press of an inline keyboard button.
+#[derive(Debug, Deserialize)]
+struct CallbackQuery {
+    id: String,
+    data: Option<String>,
+    message: Option<CallbackMessage>,
+}
+
+/// The message the pressed button belongs to.
+#[derive(Debug, Deserialize)]
+struct CallbackMessage {
+    message_id: i64,
+    chat: TelegramChat,
+    text: Option<String>,
+    caption: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
 struct TelegramChat {
     id: i64,
 }
 
+/// Processes a single update, dispatching text messages (new or edited) to the command handler
+/// and inline button presses to the callback handler.
+pub(crate) async fn handle_update(update: &TelegramUpdate, notifier: &TelegramNotifier) {
+    let message = update.message.as_ref().or(update.edited_message.as_ref());
+    if let Some(TelegramMessage { chat, text: Some(text) }) = message {
+        // Process the command using the command handler.
+        handle_command(text, chat.id, notifier).await;
+    }
+    if let Some(query) = update.callback_query.as_ref() {
+        handle_callback(query, notifier).await;
+    }
+}
+
+/// Handles the "✅ Seen" button: marks the offer notified and strikes the message through.
+/// Presses in chats that `is_chat_allowed` rejects are ignored.
+async fn handle_callback(query: &CallbackQuery, notifier: &TelegramNotifier) {
+    if let Some(message) = query.message.as_ref()
+        && !notifier.config.load().is_chat_allowed(message.chat.id)
+    {
+        tracing::warn!("Ignoring button press from unauthorized chat {}", message.chat.id);
+        return;
+    }
+    let Some(offer_id) = query.data.as_deref().and_then(|d| d.strip_prefix(SEEN_CALLBACK_PREFIX)) else {
+        if let Err(e) = sender::answer_callback_query(notifier, &query.id, "").await {
+            tracing::warn!("answerCallbackQuery error: {:?}", e);
+        }
+        return;
+    };
+
+    let reply = match notifier.storage.mark_notified(offer_id) {
+        Ok(()) => "✅ Marked as seen",
+        Err(e) => {
+            tracing::warn!("Mark seen failed for {}: {:?}", offer_id, e);
+            "❌ Failed to mark as seen"
+        }
+    };
+    if let Err(e) = sender::answer_callback_query(notifier, &query.id, reply).await {
+        tracing::warn!("answerCallbackQuery error: {:?}", e);
+    }
+
+    if let Some(message) = query.message.as_ref() {
+        let (body, is_caption) = match (&message.text, &message.caption) {
+            (Some(text), _) => (text.as_str(), false),
+            (None, Some(caption)) => (caption.as_str(), true),
+            (None, None) => return,
+        };
+        if let Err(e) =
+            sender::strike_message(notifier, message.chat.id, message.message_id, body, is_caption).await
+        {
+            tracing::warn!("Failed to edit seen message: {:?}", e);
+        }
+    }
+}
+
 /// Polls for Telegram updates and processes incoming commands.
+/// Failed polls are logged and retried with exponential backoff (1s, 2s, 4s, ... up to a minute);
+/// the first successful poll after failures is logged as a recovery.
 pub async fn listen_for_commands(notifier: &TelegramNotifier) {
-    let url = format!("https://api.telegram.org/bot{}/getUpdates", notifier.bot_token);
+    // getUpdates is rejected while a webhook is registered.
+    let delete_url = notifier.api_url("deleteWebhook");
+    if let Err(e) = notifier.client.post(&delete_url).send().await {
+        tracing::warn!("deleteWebhook failed: {:?}", e);
+    }
+
+    let url = notifier.api_url("getUpdates");
+    let mut consecutive_failures: u32 = 0;
     loop {
-        let current_offset = notifier.offset.load(std::sync::atomic::Ordering::SeqCst);
-        let response = notifier.client.get(&url)
-            .query(&[("offset", (current_offset + 1).to_string())])
-            .send()
-            .await;
-        if let Ok(resp) = response {
-            if let Ok(api_response) = resp.json::<TelegramApiResponse>().await {
-                for update in api_response.result {
-                    if let Some(text) = update.message.as_ref().and_then(|m| m.text.as_deref()) {
-                        // Process the command using the command handler.
-                        handle_command(text, notifier).await;
+        // Telegram returns updates with update_id >= offset, so ask for the one after the last processed.
+        let last_update_id = notifier.offset.load(std::sync::atomic::Ordering::SeqCst);
+        match get_updates(notifier, &url, last_update_id + 1).await {
+            Ok(updates) => {
+                if consecutive_failures > 0 {
+                    tracing::info!("✅ getUpdates recovered after {} failed attempts", consecutive_failures);
+                    consecutive_failures = 0;
+                }
+                for update in updates {
+                    if update.update_id <= last_update_id {
+                        continue;
                     }
-                    notifier.offset.store(update.update_id + 1, std::sync::atomic::Ordering::SeqCst);
+                    handle_update(&update, notifier).await;
+                    notifier.mark_update_processed(update.update_id);
                 }
+                sleep(Duration::from_secs(1)).await;
+            }
+            Err(e) => {
+                consecutive_failures += 1;
+                let backoff = poll_backoff(consecutive_failures);
+                tracing::warn!(
+                    "❌ getUpdates failed ({} in a row): {:?}. Retrying in {}s",
+                    consecutive_failures,
+                    e,
+                    backoff.as_secs()
+                );
+                sleep(backoff).await;
             }
         }
-        sleep(Duration::from_secs(1)).await;
     }
-}
\ No newline at end of file
+}
+
+/// Fetches pending updates starting at `offset`, turning transport errors, non-JSON bodies
+/// and `ok: false` envelopes into errors.
+async fn get_updates(notifier: &TelegramNotifier, url: &str, offset: i64) -> Result<Vec<TelegramUpdate>, NotifyError> {
+    let response = notifier
+        .client
+        .get(url)
+        .query(&[("offset", offset.to_string())])
+        .send()
+        .await
+        .map_err(|e| NotifyError::ApiError(format!("request failed: {}", e)))?;
+    let status = response.status();
+    let body = response
+        .text()
+        .await
+        .map_err(|e| NotifyError::ApiError(format!("reading body failed: {}", e)))?;
+    let envelope: TelegramApiResponse = serde_json::from_str(&body)
+        .map_err(|e| NotifyError::ApiError(format!("[{}] invalid response ({}): {}", status, e, body)))?;
+    if !envelope.ok {
+        return Err(NotifyError::ApiError(format!(
+            "[{}] error {}: {}",
+            status,
+            envelope.error_code.unwrap_or_default(),
+            envelope.description.unwrap_or_default()
+        )));
+    }
+    Ok(envelope.result)
+}
+
+/// Pause before the next poll after `failures` consecutive failures: 1s doubling up to `MAX_POLL_BACKOFF_SECS`.
+fn poll_backoff(failures: u32) -> Duration {
+    let secs = 1u64 << failures.saturating_sub(1).min(6);
+    Duration::from_secs(secs.min(MAX_POLL_BACKOFF_SECS))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ModelConfig;
+    use crate::http_server::{HttpRequest, HttpResponse};
+    use crate::test_support::{app_config, offer, scripted_server, spawn_http_server, telegram_notifier, TempStorage};
+    use std::sync::{Arc, Mutex};
+    use std::sync::atomic::Ordering;
+
+    const PING_UPDATE: &str = r#"{"ok":true,"result":[{"update_id":7,"message":{"chat":{"id":1},"text":"/ping"}}]}"#;
+
+    #[tokio::test]
+    async fn get_updates_rejects_non_json_and_error_envelopes() {
+        let (base, _) = scripted_server(vec![
+            (502, "<html>Bad Gateway</html>"),
+            (401, r#"{"ok":false,"error_code":401,"description":"Unauthorized"}"#),
+            (200, r#"{"ok":true}"#),
+            (200, PING_UPDATE),
+        ])
+        .await;
+        let storage = TempStorage::new();
+        let mut notifier = telegram_notifier(&storage, app_config(vec![ModelConfig::permissive("iphone 13")]));
+        notifier.api_base = base;
+        let url = notifier.api_url("getUpdates");
+
+        let error = format!("{:?}", get_updates(&notifier, &url, 1).await.unwrap_err());
+        assert!(error.contains("invalid response") && error.contains("Bad Gateway"), "{}", error);
+        let error = format!("{:?}", get_updates(&notifier, &url, 1).await.unwrap_err());
+        assert!(error.contains("error 401: Unauthorized"), "{}", error);
+        assert!(get_updates(&notifier, &url, 1).await.unwrap().is_empty());
+        let updates = get_updates(&notifier, &url, 1).await.unwrap();
+        assert_eq!(updates.iter().map(|u| u.update_id).collect::<Vec<_>>(), vec![7]);
+    }
+
+    #[test]
+    fn poll_backoff_doubles_up_to_the_cap() {
+        let secs: Vec<u64> = [1, 2, 3, 4, 6, 7, 100].into_iter().map(|n| poll_backoff(n).as_secs()).collect();
+        assert_eq!(secs, vec![1, 2, 4, 8, 32, 60, 60]);
+    }
+
+    #[tokio::test]
+    async fn the_listener_recovers_from_a_failed_poll_and_advances_the_offset() {
+        // deleteWebhook, a failing poll, a poll with one update, its /ping reply, then empty polls
+        let script = [
+            (200, r#"{"ok":true,"result":true}"#),
+            (502, "Bad Gateway"),
+            (200, PING_UPDATE),
+            (200, r#"{"ok":true,"result":{}}"#),
+            (200, r#"{"ok":true,"result":[]}"#),
+        ];
+        let paths: Arc<Mutex<Vec<String>>> = Arc::default();
+        let recorded = paths.clone();
+        let base = spawn_http_server(move |request: HttpRequest| {
+            let mut paths = recorded.lock().unwrap();
+            paths.push(request.path.clone());
+            let (status, body) = script[(paths.len() - 1).min(script.len() - 1)];
+            async move { HttpResponse::text(status, body) }
+        })
+        .await;
+        let storage = TempStorage::new();
+        let mut notifier = telegram_notifier(&storage, app_config(vec![ModelConfig::permissive("iphone 13")]));
+        notifier.api_base = base;
+        let notifier = Arc::new(notifier);
+        let listener = tokio::spawn({
+            let notifier = notifier.clone();
+            async move { listen_for_commands(&notifier).await }
+        });
+
+        let polled_after_the_update = || paths.lock().unwrap().iter().any(|p| p.ends_with("getUpdates?offset=8"));
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while !polled_after_the_update() {
+                sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .expect("the listener polls again after processing the update");
+        listener.abort();
+
+        let paths = paths.lock().unwrap();
+        let routes: Vec<&str> = paths.iter().map(|p| p.rsplit('/').next().unwrap()).collect();
+        assert_eq!(
+            routes[..5],
+            ["deleteWebhook", "getUpdates?offset=1", "getUpdates?offset=1", "sendMessage", "getUpdates?offset=8"]
+        );
+        assert_eq!(notifier.offset.load(Ordering::SeqCst), 7);
+    }
+
+    /// A callback_query update as Telegram sends it for a press of the "✅ Seen" button.
+    const SEEN_PRESS: &str = r#"{
+        "update_id": 912345678,
+        "callback_query": {
+            "id": "4382bfdwdsb323b2d9",
+            "from": {"id": 1, "is_bot": false, "first_name": "Max", "language_code": "de"},
+            "message": {
+                "message_id": 1205,
+                "from": {"id": 5550001, "is_bot": true, "first_name": "KleinSniper", "username": "klein_sniper_bot"},
+                "chat": {"id": 1, "first_name": "Max", "type": "private"},
+                "date": 1718000000,
+                "text": "🔥 iPhone 13 — 450 €",
+                "reply_markup": {"inline_keyboard": [[{"text": "✅ Seen", "callback_data": "seen:2750123456"}]]}
+            },
+            "chat_instance": "-8391201923382",
+            "data": "seen:2750123456"
+        }
+    }"#;
+
+    #[test]
+    fn callback_queries_and_edited_messages_are_deserialized() {
+        let update: TelegramUpdate = serde_json::from_str(SEEN_PRESS).unwrap();
+        assert_eq!(update.update_id, 912345678);
+        assert!(update.message.is_none() && update.edited_message.is_none());
+        let query = update.callback_query.unwrap();
+        assert_eq!(query.id, "4382bfdwdsb323b2d9");
+        assert_eq!(query.data.as_deref(), Some("seen:2750123456"));
+        let message = query.message.unwrap();
+        assert_eq!((message.message_id, message.chat.id), (1205, 1));
+        assert_eq!(message.text.as_deref(), Some("🔥 iPhone 13 — 450 €"));
+        assert!(message.caption.is_none());
+
+        let edited = r#"{"update_id":8,"edited_message":{"message_id":3,"date":1718000000,"edit_date":1718000005,
+            "chat":{"id":1,"type":"private"},"text":"/status"}}"#;
+        let update: TelegramUpdate = serde_json::from_str(edited).unwrap();
+        assert_eq!(update.edited_message.unwrap().text.as_deref(), Some("/status"));
+    }
+
+    #[tokio::test]
+    async fn pressing_seen_marks_the_offer_and_answers_the_query() {
+        let (base, requests) = scripted_server(vec![(200, r#"{"ok":true,"result":true}"#)]).await;
+        let storage = TempStorage::new();
+        storage.save_offer(&offer("2750123456", "iPhone 13", 450.0)).unwrap();
+        let mut notifier = telegram_notifier(&storage, app_config(vec![ModelConfig::permissive("iphone 13")]));
+        notifier.api_base = base;
+
+        handle_update(&serde_json::from_str(SEEN_PRESS).unwrap(), &notifier).await;
+
+        assert!(storage.is_notified("2750123456").unwrap());
+        let requests = requests.lock().unwrap();
+        let methods: Vec<&str> = requests.iter().map(|(route, _)| route.rsplit('/').next().unwrap()).collect();
+        assert_eq!(methods, vec!["answerCallbackQuery", "editMessageText"]);
+        assert!(requests[0].1.contains("4382bfdwdsb323b2d9"), "{}", requests[0].1);
+    }
+}
@@ -1,58 +1,179 @@
 pub mod sender;
 pub mod listener;
 pub mod command_handler;
+pub mod webhook;
+pub mod digest;
 pub mod statistics;
 
-use crate::model::{NotifyError, Offer};
+use crate::model::{Deal, NotifyError, Offer, StorageError};
+use crate::notifier::Notifier;
 use crate::storage::SqliteStorage;
-use crate::config::AppConfig;
+use crate::config::{SharedConfig, TelegramMode};
 use reqwest::Client;
 use std::sync::Arc;
-use tokio::sync::{Mutex, Notify};
-use std::sync::atomic::AtomicI64;
+use tokio::sync::Notify;
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
 use std::time::Instant;
 
+/// Bot API endpoint used unless a test points the notifier elsewhere.
+pub const TELEGRAM_API_BASE: &str = "https://api.telegram.org";
+/// Meta key holding the `update_id` of the last processed Telegram update.
+pub const UPDATE_OFFSET_KEY: &str = "telegram_update_offset";
+/// Meta key holding "1" while all scraping and notifying is paused.
+pub const PAUSED_KEY: &str = "paused";
+/// Meta key holding the Unix timestamp until which offer notifications are suppressed (0 = not muted).
+pub const MUTE_UNTIL_KEY: &str = "mute_until";
+
+/// Reads the /pause_all state persisted under [`PAUSED_KEY`].
+pub fn stored_paused(storage: &SqliteStorage) -> bool {
+    match storage.get_meta(PAUSED_KEY) {
+        Ok(value) => value.as_deref() == Some("1"),
+        Err(e) => {
+            tracing::warn!("Failed to load paused state: {:?}", e);
+            false
+        }
+    }
+}
+
+/// Reads the /mute deadline persisted under [`MUTE_UNTIL_KEY`]; `None` when not muted.
+pub fn stored_mute_remaining(storage: &SqliteStorage) -> Option<chrono::Duration> {
+    let mute_until = match storage.get_meta(MUTE_UNTIL_KEY) {
+        Ok(value) => value.and_then(|v| v.parse::<i64>().ok()).unwrap_or(0),
+        Err(e) => {
+            tracing::warn!("Failed to load mute state: {:?}", e);
+            0
+        }
+    };
+    let remaining = mute_until - chrono::Utc::now().timestamp();
+    (remaining > 0).then(|| chrono::Duration::seconds(remaining))
+}
+
 pub struct TelegramNotifier {
     pub bot_token: String,
-    pub chat_id: i64,
+    /// Bot API base URL, [`TELEGRAM_API_BASE`] by default.
+    pub api_base: String,
     pub client: Client,
+    /// `update_id` of the last processed update (0 = none yet), persisted under [`UPDATE_OFFSET_KEY`].
     pub offset: Arc<AtomicI64>,
-    pub storage: Arc<Mutex<SqliteStorage>>,
-    pub config: Arc<AppConfig>,
+    pub storage: Arc<SqliteStorage>,
+    pub config: Arc<SharedConfig>,
     pub start_time: Instant,
     pub refresh_notify: Arc<Notify>,
+    /// Set by /pause_all: the main loop skips scraping and notifying; persisted under [`PAUSED_KEY`].
+    pub paused: AtomicBool,
 }
 
 impl TelegramNotifier {
     pub fn new(
         bot_token: String,
-        chat_id: i64,
-        storage: Arc<Mutex<SqliteStorage>>,
-        config: Arc<AppConfig>,
+        storage: Arc<SqliteStorage>,
+        config: Arc<SharedConfig>,
         refresh_notify: Arc<Notify>,
     ) -> Self {
         let client = Client::builder()
             .timeout(std::time::Duration::from_secs(10))
             .build()
             .expect("❗ Failed to create HTTP client");
+        let last_update_id = match storage.get_meta(UPDATE_OFFSET_KEY) {
+            Ok(value) => value.and_then(|v| v.parse().ok()).unwrap_or(0),
+            Err(e) => {
+                tracing::warn!("Failed to load Telegram update offset: {:?}", e);
+                0
+            }
+        };
+        let paused = stored_paused(&storage);
         Self {
             bot_token: bot_token.clone(),
-            chat_id,
+            api_base: TELEGRAM_API_BASE.to_string(),
             client,
-            offset: Arc::new(AtomicI64::new(0)),
+            offset: Arc::new(AtomicI64::new(last_update_id)),
             storage,
             config,
             start_time: Instant::now(),
             refresh_notify,
+            paused: AtomicBool::new(paused),
         }
     }
 
-    pub async fn notify_text(&self, text: &str) -> Result<(), reqwest::Error> {
+    /// Pauses (or resumes) all scraping and notifying; the state survives restarts.
+    /// Returns false when it was already in that state.
+    pub fn set_paused(&self, paused: bool) -> Result<bool, StorageError> {
+        self.storage.set_meta(PAUSED_KEY, if paused { "1" } else { "0" })?;
+        Ok(self.paused.swap(paused, Ordering::SeqCst) != paused)
+    }
+
+    /// URL of a Bot API method, e.g. `https://api.telegram.org/bot<token>/sendMessage`.
+    pub fn api_url(&self, method: &str) -> String {
+        format!("{}/bot{}/{}", self.api_base, self.bot_token, method)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    /// Suppresses offer notifications for the given number of hours; the mute survives restarts.
+    pub fn mute_for_hours(&self, hours: u64) -> Result<(), StorageError> {
+        let secs = i64::try_from(hours.saturating_mul(3600)).unwrap_or(i64::MAX);
+        let until = chrono::Utc::now().timestamp().saturating_add(secs);
+        self.storage.set_meta(MUTE_UNTIL_KEY, &until.to_string())
+    }
+
+    pub fn unmute(&self) -> Result<(), StorageError> {
+        self.storage.set_meta(MUTE_UNTIL_KEY, "0")
+    }
+
+    /// Remaining mute time, or `None` when notifications are active.
+    /// A failing storage read is logged and treated as not muted.
+    pub fn mute_remaining(&self) -> Option<chrono::Duration> {
+        stored_mute_remaining(&self.storage)
+    }
+
+    /// Records `update_id` as processed, in memory and in storage, so a restart resumes after it.
+    pub fn mark_update_processed(&self, update_id: i64) {
+        self.offset.store(update_id, Ordering::SeqCst);
+        if let Err(e) = self.storage.set_meta(UPDATE_OFFSET_KEY, &update_id.to_string()) {
+            tracing::warn!("Failed to persist Telegram update offset: {:?}", e);
+        }
+    }
+
+    pub async fn notify_text(&self, text: &str) -> Result<(), NotifyError> {
         sender::send_text(self, text).await
     }
 
+    pub async fn notify_text_to(&self, chat_id: i64, text: &str) -> Result<(), NotifyError> {
+        sender::send_text_to(self, chat_id, text).await
+    }
+
+    /// Sends an offer notification; fails with `NotifyError::Muted` while muted.
     pub async fn notify(&self, offer: &Offer) -> Result<(), NotifyError> {
-        sender::send_offer(self, offer).await
+        if self.mute_remaining().is_some() {
+            return Err(NotifyError::Muted);
+        }
+        sender::send_offer(self, offer, sender::OfferAlert::Deal(None)).await
+    }
+
+    /// Sends a deal notification; fails with `NotifyError::Muted` while muted.
+    pub async fn notify_deal(&self, deal: &Deal) -> Result<(), NotifyError> {
+        if self.mute_remaining().is_some() {
+            return Err(NotifyError::Muted);
+        }
+        sender::send_offer(self, &deal.offer, sender::OfferAlert::Deal(Some(deal.score))).await
+    }
+
+    /// Sends a "price dropped" notification; fails with `NotifyError::Muted` while muted.
+    pub async fn notify_price_drop(&self, offer: &Offer, previous_price: f64) -> Result<(), NotifyError> {
+        if self.mute_remaining().is_some() {
+            return Err(NotifyError::Muted);
+        }
+        sender::send_offer(self, offer, sender::OfferAlert::PriceDrop(previous_price)).await
+    }
+
+    /// Sends a "watched seller" notification; fails with `NotifyError::Muted` while muted.
+    pub async fn notify_watched_seller(&self, offer: &Offer) -> Result<(), NotifyError> {
+        if self.mute_remaining().is_some() {
+            return Err(NotifyError::Muted);
+        }
+        sender::send_offer(self, offer, sender::OfferAlert::WatchedSeller).await
     }
 
     pub async fn listen_for_commands(&self) {
@@ -60,7 +181,7 @@ impl TelegramNotifier {
     }
 
     pub async fn set_my_commands(&self) -> Result<(), reqwest::Error> {
-        let url = format!("https://api.telegram.org/bot{}/setMyCommands", self.bot_token);
+        let url = self.api_url("setMyCommands");
         let commands = serde_json::json!({
             "commands": [
                 { "command": "ping", "description": "Check connection" },
@@ -68,96 +189,130 @@ impl TelegramNotifier {
                 { "command": "help", "description": "Command list" },
                 { "command": "last", "description": "Show last great offer" },
                 { "command": "top5", "description": "Top 5 offers" },
+                { "command": "deals", "description": "Current deals" },
                 { "command": "avg", "description": "Average price" },
+                { "command": "runs", "description": "Recent scrape runs" },
+                { "command": "market", "description": "Models ranked by price vs. average" },
+                { "command": "stats", "description": "Price statistics per model" },
                 { "command": "config", "description": "Current configuration" },
+                { "command": "reload", "description": "Reload config.json" },
                 { "command": "refresh", "description": "Manual restart" },
-                { "command": "uptime", "description": "Service uptime" }
+                { "command": "uptime", "description": "Service uptime" },
+                { "command": "history", "description": "Price trend for a model" },
+                { "command": "bands", "description": "Bollinger bands for a model" },
+                { "command": "export", "description": "Download all offers as CSV or JSON" },
+                { "command": "search", "description": "Search stored offers" },
+                { "command": "mute", "description": "Pause notifications for N hours" },
+                { "command": "unmute", "description": "Resume notifications" },
+                { "command": "subscribe", "description": "Get notifications for a model" },
+                { "command": "unsubscribe", "description": "Stop notifications for a model" },
+                { "command": "enable", "description": "Resume processing a model" },
+                { "command": "disable", "description": "Pause processing a model" },
+                { "command": "ignore", "description": "Never flag an offer again" },
+                { "command": "unignore", "description": "Remove an offer from the ignore list" },
+                { "command": "debug", "description": "Fetch and parse a page" },
+                { "command": "pause_all", "description": "Pause all scraping and notifications" },
+                { "command": "resume_all", "description": "Resume scraping and notifications" }
             ]
         });
         self.client.post(&url).json(&commands).send().await?;
         Ok(())
     }
 
-    pub fn spawn_listener(notifier: Arc<TelegramNotifier>) {
+    pub fn spawn_listener(notifier: Arc<TelegramNotifier>) -> tokio::task::JoinHandle<()> {
         tokio::spawn(async move {
-            tracing::info!("▶️ Starting Telegram listener...");
-            notifier.listen_for_commands().await;
+            match notifier.config.load().telegram_mode {
+                TelegramMode::Poll => {
+                    tracing::info!("▶️ Starting Telegram listener...");
+                    notifier.listen_for_commands().await;
+                }
+                TelegramMode::Webhook => {
+                    tracing::info!("▶️ Starting Telegram webhook server...");
+                    webhook::run_webhook(notifier.clone()).await;
+                }
+            }
             tracing::info!("🛑 Telegram listener ended.");
-        });
+        })
     }
+}
 
-    pub async fn check_and_notify_cheapest_for_model(
-        model_name: &str,
-        storage: Arc<Mutex<SqliteStorage>>,
-        notifier: Arc<TelegramNotifier>,
-    ) {
-        use tracing::{info, warn};
+#[async_trait::async_trait]
+impl Notifier for TelegramNotifier {
+    async fn notify(&self, offer: &Offer) -> Result<(), NotifyError> {
+        TelegramNotifier::notify(self, offer).await
+    }
 
-        info!("🔍 [cheapest] Starting check for model '{}'", model_name);
-        let offers = match storage.lock().await.get_all_offers() {
-            Ok(o) => o,
-            Err(e) => {
-                warn!("❌ [cheapest] Failed to get offers for '{}': {:?}", model_name, e);
-                return;
-            }
-        };
+    async fn notify_text(&self, text: &str) -> Result<(), NotifyError> {
+        TelegramNotifier::notify_text(self, text).await
+    }
 
-        let model_offers: Vec<Offer> = offers
-            .into_iter()
-            .filter(|o| o.model == model_name && o.price.is_finite())
-            .collect();
+    async fn notify_deal(&self, deal: &Deal) -> Result<(), NotifyError> {
+        TelegramNotifier::notify_deal(self, deal).await
+    }
 
-        info!("📦 [cheapest] Found {} offers for model '{}'", model_offers.len(), model_name);
+    async fn notify_price_drop(&self, offer: &Offer, previous_price: f64) -> Result<(), NotifyError> {
+        TelegramNotifier::notify_price_drop(self, offer, previous_price).await
+    }
 
-        if model_offers.is_empty() {
-            info!("ℹ️ [cheapest] No offers for '{}'", model_name);
-            return;
-        }
+    async fn notify_watched_seller(&self, offer: &Offer) -> Result<(), NotifyError> {
+        TelegramNotifier::notify_watched_seller(self, offer).await
+    }
 
-        let cheapest = model_offers
-            .iter()
-            .min_by(|a, b| a.price.partial_cmp(&b.price).unwrap());
-
-        if let Some(cheapest) = cheapest {
-            info!(
-                "💰 [cheapest] Cheapest offer: {:.2} € | {} | id={}",
-                cheapest.price, cheapest.link, cheapest.id
-            );
-
-            let should_notify = match storage.lock().await.should_notify(&cheapest.id) {
-                Ok(flag) => flag,
-                Err(e) => {
-                    warn!("❌ [cheapest] Error checking notification status: {:?}", e);
-                    false
-                }
-            };
-
-            if !should_notify {
-                info!(
-                    "✅ [cheapest] Offer already notified recently: {} € (id={})",
-                    cheapest.price, cheapest.id
-                );
-                return;
-            }
+    fn is_paused(&self) -> bool {
+        TelegramNotifier::is_paused(self)
+    }
 
-            info!(
-                "📤 [cheapest] Calling notify() for id={}, price={:.2} €",
-                cheapest.id, cheapest.price
-            );
-
-            match notifier.notify(cheapest).await {
-                Ok(_) => {
-                    info!("✅ [cheapest] Notification sent, saving id.");
-                    if let Err(e) = storage.lock().await.mark_notified(&cheapest.id) {
-                        warn!("❌ [cheapest] Mark notified failed: {:?}", e);
-                    }
-                }
-                Err(e) => {
-                    warn!("❌ [cheapest] Error sending notification: {:?}", e);
-                }
-            }
-        } else {
-            warn!("⚠️ [cheapest] Failed to find the minimum offer for '{}'", model_name);
-        }
+    fn is_muted(&self) -> bool {
+        self.mute_remaining().is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{app_config, telegram_notifier, TempStorage};
+
+    #[test]
+    fn the_update_offset_survives_a_restart() {
+        let storage = TempStorage::new();
+        let notifier = telegram_notifier(&storage, app_config(Vec::new()));
+        assert_eq!(notifier.offset.load(Ordering::SeqCst), 0);
+
+        notifier.mark_update_processed(41);
+        notifier.mark_update_processed(42);
+        assert_eq!(notifier.offset.load(Ordering::SeqCst), 42);
+        assert_eq!(storage.get_meta(UPDATE_OFFSET_KEY).unwrap().as_deref(), Some("42"));
+
+        let restarted = telegram_notifier(&storage, app_config(Vec::new()));
+        assert_eq!(restarted.offset.load(Ordering::SeqCst), 42);
+    }
+
+    #[test]
+    fn an_unreadable_stored_offset_starts_from_zero() {
+        let storage = TempStorage::new();
+        storage.set_meta(UPDATE_OFFSET_KEY, "not a number").unwrap();
+        let notifier = telegram_notifier(&storage, app_config(Vec::new()));
+        assert_eq!(notifier.offset.load(Ordering::SeqCst), 0);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn pause_and_mute_survive_a_restart() {
+        let storage = TempStorage::new();
+        let notifier = telegram_notifier(&storage, app_config(Vec::new()));
+        assert!(!notifier.is_paused() && notifier.mute_remaining().is_none());
+
+        assert!(notifier.set_paused(true).unwrap());
+        assert!(!notifier.set_paused(true).unwrap());
+        notifier.mute_for_hours(2).unwrap();
+
+        let restarted = telegram_notifier(&storage, app_config(Vec::new()));
+        assert!(restarted.is_paused());
+        let remaining = restarted.mute_remaining().unwrap();
+        assert!(remaining > chrono::Duration::minutes(119) && remaining <= chrono::Duration::hours(2));
+
+        restarted.set_paused(false).unwrap();
+        restarted.unmute().unwrap();
+        let restarted = telegram_notifier(&storage, app_config(Vec::new()));
+        assert!(!restarted.is_paused() && restarted.mute_remaining().is_none());
+    }
+}
@@ -1,43 +1,490 @@
 // notifier/telegram/sender.rs
 
+use crate::config::AppConfig;
+use crate::metrics::METRICS;
 use crate::model::{Offer, NotifyError};
 use crate::notifier::telegram::TelegramNotifier;
+use reqwest::StatusCode;
 use std::time::Duration;
-use tokio::time::timeout;
+use tokio::time::{sleep, timeout};
 use tracing::{info, warn};
 
-/// Sends a simple text message via Telegram.
-pub async fn send_text(notifier: &TelegramNotifier, text: &str) -> Result<(), reqwest::Error> {
-    let url = format!("https://api.telegram.org/bot{}/sendMessage", notifier.bot_token);
-    let params = [
-        ("chat_id", notifier.chat_id.to_string()),
-        ("text", text.to_string()),
+/// Wait used when a 429 response doesn't carry `parameters.retry_after`.
+const DEFAULT_RETRY_AFTER_SECS: u64 = 5;
+
+/// Telegram's limits for message text and media captions (in characters).
+const MAX_MESSAGE_CHARS: usize = 4096;
+const MAX_CAPTION_CHARS: usize = 1024;
+/// Offer titles are cut to this length so the rest of the notification always fits.
+const MAX_TITLE_CHARS: usize = 200;
+/// Longest HTML entity `truncate_html` keeps whole (e.g. `&quot;` or `&#128293;`).
+const MAX_ENTITY_LEN: usize = 10;
+
+/// Callback data prefix of the "✅ Seen" button; followed by the offer id.
+pub const SEEN_CALLBACK_PREFIX: &str = "seen:";
+
+/// How Telegram should interpret message text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseMode {
+    Plain,
+    Html,
+}
+
+impl ParseMode {
+    fn api_value(self) -> Option<&'static str> {
+        match self {
+            ParseMode::Plain => None,
+            ParseMode::Html => Some("HTML"),
+        }
+    }
+}
+
+/// Escapes user-provided text (titles, links) for the given parse mode.
+pub fn escape(text: &str, mode: ParseMode) -> String {
+    match mode {
+        ParseMode::Plain => text.to_string(),
+        ParseMode::Html => text
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;"),
+    }
+}
+
+/// Cuts `text` to at most `max_chars` characters, ending with an ellipsis when shortened.
+pub fn truncate_message(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(max_chars.saturating_sub(1)).collect();
+    truncated.push('…');
+    truncated
+}
+
+/// Like `truncate_message` for HTML-formatted text: never cuts inside a tag or an entity such as
+/// `&amp;`, and closes the tags still open at the cut, so the result stays valid for Telegram.
+/// Tags, entities and the closing tags all count towards `max_chars`.
+pub fn truncate_html(html: &str, max_chars: usize) -> String {
+    if html.chars().count() <= max_chars {
+        return html.to_string();
+    }
+    let closing_len = |open: &[&str]| open.iter().map(|name| name.chars().count() + 3).sum::<usize>();
+
+    let mut truncated = String::new();
+    let mut used = 0;
+    let mut open: Vec<&str> = Vec::new();
+    let mut rest = html;
+    while let Some(c) = rest.chars().next() {
+        // The next piece that must not be split: a tag, an entity or a single character
+        let len = match c {
+            '<' => rest.find('>').map_or(rest.len(), |end| end + 1),
+            '&' => rest
+                .find(';')
+                .filter(|&end| end <= MAX_ENTITY_LEN && !rest[1..end].contains(char::is_whitespace))
+                .map_or(1, |end| end + 1),
+            _ => c.len_utf8(),
+        };
+        let piece = &rest[..len];
+
+        let mut next_open = open.clone();
+        if let Some(name) = piece.strip_prefix("</") {
+            let name = name.trim_end_matches('>').trim();
+            if let Some(pos) = next_open.iter().rposition(|open| open.eq_ignore_ascii_case(name)) {
+                next_open.truncate(pos);
+            }
+        } else if piece.starts_with('<') && !piece.ends_with("/>") {
+            let name = piece[1..].split(|c: char| c.is_whitespace() || c == '>').next().unwrap_or("");
+            if !name.is_empty() {
+                next_open.push(name);
+            }
+        }
+
+        let piece_chars = piece.chars().count();
+        // Room for this piece, the ellipsis and the tags that would still need closing
+        if used + piece_chars + 1 + closing_len(&next_open) > max_chars {
+            break;
+        }
+        truncated.push_str(piece);
+        used += piece_chars;
+        open = next_open;
+        rest = &rest[len..];
+    }
+
+    truncated.push('…');
+    for name in open.iter().rev() {
+        truncated.push_str(&format!("</{}>", name));
+    }
+    truncated
+}
+
+/// Replaces `{name}` placeholders in `template` with the matching value.
+/// Unknown placeholders and unmatched braces are kept as written.
+pub fn render_template(template: &str, values: &[(&str, String)]) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let value = after
+            .find('}')
+            .and_then(|end| values.iter().find(|(name, _)| *name == &after[..end]).map(|(_, v)| (end, v)));
+        match value {
+            Some((end, value)) => {
+                rendered.push_str(value);
+                rest = &after[end + 1..];
+            }
+            None => {
+                rendered.push('{');
+                rest = after;
+            }
+        }
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+/// Sends a simple text message via Telegram to every `telegram_chat_id` chat of the current config.
+/// A failing chat doesn't stop delivery to the others.
+pub async fn send_text(notifier: &TelegramNotifier, text: &str) -> Result<(), NotifyError> {
+    let mut errors = Vec::new();
+    for &chat_id in &notifier.config.load().telegram_chat_id {
+        if let Err(e) = send_text_to(notifier, chat_id, text).await {
+            errors.push((chat_id, e));
+        }
+    }
+    combine_errors(errors)
+}
+
+/// Folds per-chat failures into one result. A single failure is returned unchanged;
+/// several are summarized in an `ApiError` listing each chat.
+fn combine_errors(mut errors: Vec<(i64, NotifyError)>) -> Result<(), NotifyError> {
+    match errors.len() {
+        0 => Ok(()),
+        1 => Err(errors.remove(0).1),
+        n => {
+            let details: Vec<String> = errors
+                .iter()
+                .map(|(chat_id, e)| format!("{}: {:?}", chat_id, e))
+                .collect();
+            Err(NotifyError::ApiError(format!("{} chats failed: {}", n, details.join("; "))))
+        }
+    }
+}
+
+/// Sends a simple text message via Telegram to the given chat.
+pub async fn send_text_to(notifier: &TelegramNotifier, chat_id: i64, text: &str) -> Result<(), NotifyError> {
+    // Command replies and status texts are sent as-is, so nothing in them needs escaping.
+    let mode = ParseMode::Plain;
+    let mut params = vec![
+        ("chat_id", chat_id.to_string()),
+        ("text", truncate_message(&escape(text, mode), MAX_MESSAGE_CHARS)),
     ];
-    let response = notifier.client.post(&url).form(&params).send().await?;
+    if let Some(parse_mode) = mode.api_value() {
+        params.push(("parse_mode", parse_mode.to_string()));
+    }
+    post_form(notifier, "sendMessage", &params).await
+}
+
+/// Uploads `content` as a document named `filename` to the given chat via `sendDocument`.
+pub async fn send_document(
+    notifier: &TelegramNotifier,
+    chat_id: i64,
+    filename: &str,
+    content: Vec<u8>,
+    caption: &str,
+) -> Result<(), NotifyError> {
+    let url = notifier.api_url("sendDocument");
+    let boundary = format!("----kleinsniper{}", rand::random::<u64>());
+
+    let mut body = Vec::with_capacity(content.len() + 512);
+    for (name, value) in [("chat_id", chat_id.to_string()), ("caption", caption.to_string())] {
+        body.extend_from_slice(
+            format!(
+                "--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n",
+                boundary, name, value
+            )
+            .as_bytes(),
+        );
+    }
+    body.extend_from_slice(
+        format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"document\"; filename=\"{}\"\r\n\
+             Content-Type: application/octet-stream\r\n\r\n",
+            boundary, filename
+        )
+        .as_bytes(),
+    );
+    body.extend_from_slice(&content);
+    body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+
+    let response = notifier
+        .client
+        .post(&url)
+        .header(
+            reqwest::header::CONTENT_TYPE,
+            format!("multipart/form-data; boundary={}", boundary),
+        )
+        .body(body)
+        .timeout(Duration::from_secs(60))
+        .send()
+        .await
+        .map_err(|e| NotifyError::ApiError(format!("Send failed: {}", e)))?;
+
     let status = response.status();
-    let body = response.text().await.unwrap_or_else(|_| "unknown".into());
+    let response_body = response.text().await.unwrap_or_else(|_| "unknown".into());
     if !status.is_success() {
-        warn!("❌ Telegram text error [{}]: {}", status, body);
-    } else {
-        info!("✅ Telegram text sent [{}]: {}", status, body);
+        warn!("❌ Telegram sendDocument responded [{}]: {}", status, response_body);
+        return Err(NotifyError::ApiError(format!("{}: {}", status, response_body)));
     }
+    info!("✅ Telegram document sent: {}", filename);
     Ok(())
 }
 
-/// Sends a notification message for an offer.
-pub async fn send_offer(notifier: &TelegramNotifier, offer: &Offer) -> Result<(), NotifyError> {
-    let url = format!("https://api.telegram.org/bot{}/sendMessage", notifier.bot_token);
-    let message = format!(
-        "💸 Found a great deal!\n\n📦 Model: {}\n💰 Price: {:.2} €\n🔗 Link: {}",
-        offer.model, offer.price, offer.link
-    );
+/// Why an offer notification is sent; decides its headline.
+#[derive(Debug, Clone, Copy)]
+pub enum OfferAlert {
+    /// New deal or cheapest offer, with the analyzer's score when available.
+    Deal(Option<f64>),
+    /// A stored offer got cheaper; holds the previous price.
+    PriceDrop(f64),
+    /// New offer from a seller in `watched_sellers`, sent whatever its price.
+    WatchedSeller,
+}
+
+/// Sends a notification message for an offer to every chat subscribed to its model,
+/// or to the `telegram_chat_id` chats of the current config when nobody subscribed. Fails if any chat couldn't be reached.
+pub async fn send_offer(notifier: &TelegramNotifier, offer: &Offer, alert: OfferAlert) -> Result<(), NotifyError> {
+    let subscribers = match notifier.storage.get_subscribers(&offer.model) {
+        Ok(chats) => chats,
+        Err(e) => {
+            warn!("❌ Failed to load subscribers for '{}': {:?}", offer.model, e);
+            Vec::new()
+        }
+    };
+    let chats = if subscribers.is_empty() {
+        notifier.config.load().telegram_chat_id.clone()
+    } else {
+        subscribers
+    };
+    let seller_listings = seller_listing_count(notifier, offer);
+    let priority = notifier
+        .config
+        .load()
+        .models
+        .iter()
+        .any(|m| m.query == offer.model && m.is_priority(&offer.title));
+
+    let mut errors = Vec::new();
+    for chat_id in chats {
+        match send_offer_to(notifier, chat_id, offer, alert, priority, seller_listings).await {
+            Ok(()) => METRICS.inc_notifications_sent(),
+            Err(e) => {
+                warn!("❌ Offer notification to chat {} failed: {:?}", chat_id, e);
+                errors.push((chat_id, e));
+            }
+        }
+    }
+    combine_errors(errors)
+}
+
+/// Returns how many active listings the offer's seller has for its model, if that exceeds
+/// the configured `seller_listings_warning` threshold.
+fn seller_listing_count(notifier: &TelegramNotifier, offer: &Offer) -> Option<usize> {
+    let user_id = offer.user_id.as_deref()?;
+    let threshold = notifier.config.load().seller_listings_warning;
+    match notifier.storage.group_offers_by_seller(&offer.model) {
+        Ok(counts) => counts.get(user_id).copied().filter(|&count| count > threshold),
+        Err(e) => {
+            warn!("❌ Failed to count seller listings for '{}': {:?}", offer.model, e);
+            None
+        }
+    }
+}
+
+/// Sends a notification message for an offer to a single chat, formatted as HTML.
+/// Uses `sendPhoto` with the message as caption when the offer has an image,
+/// falling back to a plain `sendMessage` if there is no image or Telegram rejects it.
+async fn send_offer_to(
+    notifier: &TelegramNotifier,
+    chat_id: i64,
+    offer: &Offer,
+    alert: OfferAlert,
+    priority: bool,
+    seller_listings: Option<usize>,
+) -> Result<(), NotifyError> {
+    let mode = ParseMode::Html;
+    let config = notifier.config.load();
+    let headline = match alert {
+        OfferAlert::Deal(_) => "💸 Found a great deal!".to_string(),
+        OfferAlert::WatchedSeller => "👀 New listing from a watched seller".to_string(),
+        OfferAlert::PriceDrop(previous) => format!(
+            "📉 Price dropped from {} to {}",
+            config.format_price(previous),
+            config.format_price(offer.price)
+        ),
+    };
+    let headline = if priority { format!("🔥 PRIORITY\n{}", headline) } else { headline };
+    let message = match config.notification_template.as_deref() {
+        Some(template) => render_offer_template(template, offer, &headline, alert, &config, mode),
+        None => default_offer_message(offer, &headline, alert, seller_listings, &config, mode),
+    };
+    let parse_mode = mode.api_value().unwrap_or_default().to_string();
+    let keyboard = serde_json::json!({
+        "inline_keyboard": [[
+            { "text": "🔗 Open", "url": offer.link },
+            { "text": "✅ Seen", "callback_data": format!("{}{}", SEEN_CALLBACK_PREFIX, offer.id) },
+        ]]
+    })
+    .to_string();
+
+    if let Some(image_url) = offer.image_url.as_deref()
+        && message.chars().count() <= MAX_CAPTION_CHARS
+    {
+        info!("📤 Sending Telegram photo ({}):\n{}", image_url, message);
+        let params = [
+            ("chat_id", chat_id.to_string()),
+            ("photo", image_url.to_string()),
+            ("caption", message.clone()),
+            ("parse_mode", parse_mode.clone()),
+            ("reply_markup", keyboard.clone()),
+        ];
+        match post_form(notifier, "sendPhoto", &params).await {
+            Ok(()) => return Ok(()),
+            Err(e) => warn!("⚠️ sendPhoto failed, falling back to text: {:?}", e),
+        }
+    }
+
     info!("📤 Sending Telegram message:\n{}", message);
+    let params = [
+        ("chat_id", chat_id.to_string()),
+        ("text", truncate_html(&message, MAX_MESSAGE_CHARS)),
+        ("parse_mode", parse_mode),
+        ("reply_markup", keyboard),
+    ];
+    post_form(notifier, "sendMessage", &params).await
+}
+
+/// Renders the configured `notification_template` for an offer. Supported placeholders:
+/// `{headline}`, `{title}`, `{model}`, `{price}`, `{link}`, `{location}`, `{score}` and `{description}`;
+/// a missing location, score or description renders as an empty string.
+fn render_offer_template(
+    template: &str,
+    offer: &Offer,
+    headline: &str,
+    alert: OfferAlert,
+    config: &AppConfig,
+    mode: ParseMode,
+) -> String {
+    let score = match alert {
+        OfferAlert::Deal(Some(score)) => format!("{:.0}", score),
+        _ => String::new(),
+    };
+    let values = [
+        ("headline", escape(headline, mode)),
+        ("title", escape(&truncate_message(&offer.title, MAX_TITLE_CHARS), mode)),
+        ("model", escape(&offer.model, mode)),
+        ("price", config.number_format.format(offer.price)),
+        ("link", escape(&offer.link, mode)),
+        ("location", escape(&offer.location, mode)),
+        ("score", score),
+        ("description", escape(&truncate_message(&offer.description, MAX_TITLE_CHARS), mode)),
+    ];
+    render_template(template, &values)
+}
+
+/// The built-in offer message: headline, bold title, model, price, score, seller warning and link.
+fn default_offer_message(
+    offer: &Offer,
+    headline: &str,
+    alert: OfferAlert,
+    seller_listings: Option<usize>,
+    config: &AppConfig,
+    mode: ParseMode,
+) -> String {
+    let mut message = format!(
+        "{}\n\n<b>{}</b>\n📦 Model: {}\n💰 Price: {}{}\n",
+        headline,
+        escape(&truncate_message(&offer.title, MAX_TITLE_CHARS), mode),
+        escape(&offer.model, mode),
+        escape(&config.format_price(offer.price), mode),
+        if offer.negotiable { " (VB)" } else { "" },
+    );
+    if let OfferAlert::Deal(Some(score)) = alert {
+        message.push_str(&format!("⭐ Deal score: {:.0}\n", score));
+    }
+    if let Some(count) = seller_listings {
+        message.push_str(&format!("⚠️ Seller has {} listings\n", count));
+    }
+    message.push_str(&format!("🔗 Link: {}", escape(&offer.link, mode)));
+    message
+}
+
+/// Acknowledges an inline button press; a non-empty `text` is shown as a toast.
+pub async fn answer_callback_query(
+    notifier: &TelegramNotifier,
+    callback_query_id: &str,
+    text: &str,
+) -> Result<(), NotifyError> {
+    let mut params = vec![("callback_query_id", callback_query_id.to_string())];
+    if !text.is_empty() {
+        params.push(("text", text.to_string()));
+    }
+    post_form(notifier, "answerCallbackQuery", &params).await
+}
+
+/// Replaces a sent offer message with a struck-through copy and removes its keyboard.
+/// `body` is the message's plain text as returned by Telegram; photo messages edit the caption.
+pub async fn strike_message(
+    notifier: &TelegramNotifier,
+    chat_id: i64,
+    message_id: i64,
+    body: &str,
+    is_caption: bool,
+) -> Result<(), NotifyError> {
+    let (method, field, limit) = if is_caption {
+        ("editMessageCaption", "caption", MAX_CAPTION_CHARS)
+    } else {
+        ("editMessageText", "text", MAX_MESSAGE_CHARS)
+    };
+    let suffix = "\n\n✅ Seen";
+    let body = truncate_message(body, limit - "<s></s>".len() - suffix.chars().count());
+    let params = [
+        ("chat_id", chat_id.to_string()),
+        ("message_id", message_id.to_string()),
+        (field, format!("<s>{}</s>{}", escape(&body, ParseMode::Html), suffix)),
+        ("parse_mode", "HTML".to_string()),
+    ];
+    post_form(notifier, method, &params).await
+}
+
+/// Calls a Telegram Bot API method with form parameters.
+/// On 429 Too Many Requests it waits `retry_after` seconds and retries once;
+/// if Telegram is still throttling, returns `NotifyError::RateLimited`.
+async fn post_form(
+    notifier: &TelegramNotifier,
+    method: &str,
+    params: &[(&str, String)],
+) -> Result<(), NotifyError> {
+    match post_form_once(notifier, method, params).await {
+        Err(NotifyError::RateLimited(secs)) => {
+            warn!("⏳ Telegram rate limit on {}, retrying in {}s", method, secs);
+            sleep(Duration::from_secs(secs)).await;
+            post_form_once(notifier, method, params).await
+        }
+        result => result,
+    }
+}
+
+/// Performs a single Bot API call with a 10s timeout.
+async fn post_form_once(
+    notifier: &TelegramNotifier,
+    method: &str,
+    params: &[(&str, String)],
+) -> Result<(), NotifyError> {
+    let url = notifier.api_url(method);
     let response = match timeout(
         Duration::from_secs(10),
-        notifier.client
-            .post(&url)
-            .form(&[("chat_id", notifier.chat_id.to_string()), ("text", message.clone())])
-            .send(),
+        notifier.client.post(&url).form(params).send(),
     )
     .await
     {
@@ -53,10 +500,257 @@ pub async fn send_offer(notifier: &TelegramNotifier, offer: &Offer) -> Result<()
     };
     let status = response.status();
     let body = response.text().await.unwrap_or_else(|_| "unknown".into());
+    if status == StatusCode::TOO_MANY_REQUESTS {
+        let retry_after = parse_retry_after(&body).unwrap_or(DEFAULT_RETRY_AFTER_SECS);
+        return Err(NotifyError::RateLimited(retry_after));
+    }
     if !status.is_success() {
         warn!("❌ Telegram API responded [{}]: {}", status, body);
-        return Err(NotifyError::Unreachable);
+        return Err(NotifyError::ApiError(format!("{}: {}", status, body)));
     }
     info!("✅ Telegram response [{}]: {}", status, body);
     Ok(())
 }
+
+/// Extracts `parameters.retry_after` from a Telegram error body.
+fn parse_retry_after(body: &str) -> Option<u64> {
+    let json: serde_json::Value = serde_json::from_str(body).ok()?;
+    json.get("parameters")?.get("retry_after")?.as_u64()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{load_config, SharedConfig};
+    use std::sync::Arc;
+    use crate::test_support::{
+        app_config, config_file, offer, scripted_server, telegram_notifier, telegram_notifier_with, TempFile, TempStorage,
+    };
+
+    /// Panics unless every `&` starts a complete entity and every tag is closed in order.
+    fn assert_valid_html(html: &str) {
+        let mut open = Vec::new();
+        let mut rest = html;
+        while let Some(pos) = rest.find(['<', '&']) {
+            rest = &rest[pos..];
+            if rest.starts_with('&') {
+                let end = rest.find(';').unwrap_or_else(|| panic!("cut entity in {:?}", html));
+                assert!(
+                    ["&amp;", "&lt;", "&gt;", "&quot;"].contains(&&rest[..=end]),
+                    "unexpected entity {:?}",
+                    &rest[..=end]
+                );
+                rest = &rest[end + 1..];
+            } else {
+                let end = rest.find('>').unwrap_or_else(|| panic!("cut tag in {:?}", html));
+                let tag = &rest[1..end];
+                match tag.strip_prefix('/') {
+                    Some(name) => assert_eq!(open.pop(), Some(name.to_string()), "in {:?}", html),
+                    None => open.push(tag.split_whitespace().next().unwrap().to_string()),
+                }
+                rest = &rest[end + 1..];
+            }
+        }
+        assert!(open.is_empty(), "unclosed tags {:?} in {:?}", open, html);
+    }
+
+    #[test]
+    fn short_html_is_unchanged() {
+        assert_eq!(truncate_html("<b>Tom &amp; Jerry</b>", 100), "<b>Tom &amp; Jerry</b>");
+    }
+
+    #[test]
+    fn truncate_html_never_cuts_an_entity() {
+        let html = "&amp;".repeat(10);
+        for max in 1..html.len() {
+            let truncated = truncate_html(&html, max);
+            assert!(truncated.chars().count() <= max, "{} > {}", truncated, max);
+            assert_valid_html(&truncated);
+            assert!(truncated.ends_with('…'));
+        }
+    }
+
+    #[test]
+    fn truncate_html_closes_open_tags() {
+        let html = "<b>bold <i>italic &lt;3</i> text</b> and <a href=\"https://x.de/?a=1&amp;b=2\">a link</a>";
+        for max in 1..html.chars().count() {
+            let truncated = truncate_html(html, max);
+            assert!(truncated.chars().count() <= max, "{} > {}", truncated, max);
+            assert_valid_html(&truncated);
+        }
+        assert_eq!(truncate_html(html, 20), "<b>bold <i>…</i></b>");
+    }
+
+    #[test]
+    fn plain_truncation_counts_characters_not_bytes() {
+        assert_eq!(truncate_message("äöü€€€", 4), "äöü…");
+        assert_eq!(truncate_message("äöü", 3), "äöü");
+    }
+
+    #[test]
+    fn huge_titles_with_special_characters_stay_valid() {
+        let config = app_config(Vec::new());
+        let title = "<Tom & \"Jerry\"> ".repeat(400);
+        assert!(title.chars().count() > 5000);
+        let offer = offer("1", &title, 99.0);
+
+        let alert = OfferAlert::Deal(Some(80.0));
+        let message = default_offer_message(&offer, "💸 Found a great deal!", alert, None, &config, ParseMode::Html);
+        assert_valid_html(&message);
+        assert!(message.chars().count() < MAX_CAPTION_CHARS * 2);
+        assert!(message.contains("&lt;Tom &amp; &quot;Jerry&quot;&gt;"));
+
+        let template = "{headline}\n<b>{title}</b>\n<i>{description}</i>\n{link}".repeat(30);
+        let rendered = render_offer_template(&template, &offer, "🔥", alert, &config, ParseMode::Html);
+        assert!(rendered.chars().count() > MAX_MESSAGE_CHARS);
+        let truncated = truncate_html(&rendered, MAX_MESSAGE_CHARS);
+        assert!(truncated.chars().count() <= MAX_MESSAGE_CHARS);
+        assert_valid_html(&truncated);
+    }
+
+    #[test]
+    fn render_template_substitutes_known_placeholders() {
+        let values = [("model", "iphone 13".to_string()), ("price", "450,00".to_string())];
+        assert_eq!(render_template("{model} for {price} €", &values), "iphone 13 for 450,00 €");
+        assert_eq!(render_template("{price}{price}", &values), "450,00450,00");
+        assert_eq!(render_template("no placeholders", &values), "no placeholders");
+    }
+
+    #[test]
+    fn render_template_keeps_unknown_placeholders_and_stray_braces() {
+        let values = [("model", "iphone 13".to_string())];
+        assert_eq!(render_template("{color} {model}", &values), "{color} iphone 13");
+        assert_eq!(render_template("{ {model} }", &values), "{ iphone 13 }");
+        assert_eq!(render_template("{model", &values), "{model");
+        assert_eq!(render_template("}{}{", &values), "}{}{");
+    }
+
+    #[test]
+    fn offer_template_fills_in_the_offer() {
+        let config = app_config(Vec::new());
+        let mut offer = offer("42", "iPhone 13 <128GB>", 1234.5);
+        offer.model = "iphone 13".to_string();
+        offer.location = "10115 Berlin".to_string();
+        let template = "{headline}|{title}|{model}|{price}|{location}|{score}|{link}";
+
+        let rendered = render_offer_template(template, &offer, "💸", OfferAlert::Deal(Some(87.4)), &config, ParseMode::Html);
+        assert_eq!(
+            rendered,
+            "💸|iPhone 13 &lt;128GB&gt;|iphone 13|1.234,50|10115 Berlin|87|https://www.kleinanzeigen.de/s-anzeige/42"
+        );
+    }
+
+    #[test]
+    fn offer_template_renders_missing_fields_empty() {
+        let config = app_config(Vec::new());
+        let mut offer = offer("42", "iPhone 13", 300.0);
+        offer.location = String::new();
+        let template = "{title} in {location}{description} ({score}) {seller}";
+
+        let rendered = render_offer_template(template, &offer, "", OfferAlert::Deal(None), &config, ParseMode::Html);
+        assert_eq!(rendered, "iPhone 13 in  () {seller}");
+        let rendered = render_offer_template(template, &offer, "", OfferAlert::PriceDrop(350.0), &config, ParseMode::Html);
+        assert_eq!(rendered, "iPhone 13 in  () {seller}");
+    }
+
+    const OK: (u16, &str) = (200, r#"{"ok":true,"result":{}}"#);
+    const TOO_MANY: (u16, &str) =
+        (429, r#"{"ok":false,"error_code":429,"description":"Too Many Requests: retry after 7","parameters":{"retry_after":7}}"#);
+
+    #[test]
+    fn retry_after_is_read_from_the_error_body() {
+        assert_eq!(parse_retry_after(TOO_MANY.1), Some(7));
+        assert_eq!(parse_retry_after(r#"{"ok":false,"error_code":429}"#), None);
+        assert_eq!(parse_retry_after("Too Many Requests"), None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn text_is_resent_after_retry_after() {
+        let (base, requests) = scripted_server(vec![TOO_MANY, OK]).await;
+        let storage = TempStorage::new();
+        let mut notifier = telegram_notifier(&storage, app_config(Vec::new()));
+        notifier.api_base = base;
+
+        let started = tokio::time::Instant::now();
+        send_text_to(&notifier, 1, "hello").await.unwrap();
+
+        assert!(started.elapsed() >= Duration::from_secs(7));
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[1].0, "/bot123:test/sendMessage");
+        assert!(requests[1].1.contains("text=hello"), "{}", requests[1].1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn offers_are_resent_after_retry_after() {
+        let (base, requests) = scripted_server(vec![TOO_MANY, OK]).await;
+        let storage = TempStorage::new();
+        let mut notifier = telegram_notifier(&storage, app_config(Vec::new()));
+        notifier.api_base = base;
+
+        send_offer(&notifier, &offer("42", "iPhone 13", 450.0), OfferAlert::Deal(None)).await.unwrap();
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert!(requests.iter().all(|(route, _)| route == "/bot123:test/sendMessage"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_second_429_is_returned_as_rate_limited() {
+        let (base, requests) = scripted_server(vec![TOO_MANY]).await;
+        let storage = TempStorage::new();
+        let mut notifier = telegram_notifier(&storage, app_config(Vec::new()));
+        notifier.api_base = base;
+
+        let result = send_text_to(&notifier, 1, "hello").await;
+        assert!(matches!(result, Err(NotifyError::RateLimited(7))), "{:?}", result);
+        assert_eq!(requests.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn default_chats_follow_a_config_reload() {
+        let (base, requests) = scripted_server(vec![OK]).await;
+        let file = TempFile::new("json");
+        std::fs::write(file.path(), config_file(serde_json::json!({}))).unwrap();
+        let config = Arc::new(SharedConfig::new(file.path(), load_config(file.path()).unwrap()));
+        let storage = TempStorage::new();
+        let mut notifier = telegram_notifier_with(&storage, config.clone());
+        notifier.api_base = base;
+
+        std::fs::write(file.path(), config_file(serde_json::json!({ "telegram_chat_id": [7, 8] }))).unwrap();
+        config.reload(|_| Ok(())).unwrap();
+        send_text(&notifier, "hello").await.unwrap();
+        send_offer(&notifier, &offer("1", "iPhone 13", 450.0), OfferAlert::Deal(None)).await.unwrap();
+
+        let chats: Vec<String> =
+            requests.lock().unwrap().iter().map(|(_, body)| body.split('&').next().unwrap().to_string()).collect();
+        assert_eq!(chats, ["chat_id=7", "chat_id=8", "chat_id=7", "chat_id=8"]);
+    }
+
+    #[tokio::test]
+    async fn priority_offers_are_tagged() {
+        let (base, requests) = scripted_server(vec![OK]).await;
+        let mut model_cfg = crate::config::ModelConfig::permissive("test");
+        model_cfg.priority_keywords = vec!["versiegelt".to_string()];
+        let storage = TempStorage::new();
+        let mut notifier = telegram_notifier(&storage, app_config(vec![model_cfg]));
+        notifier.api_base = base;
+
+        send_offer(&notifier, &offer("1", "iPhone 13 Versiegelt", 450.0), OfferAlert::Deal(None)).await.unwrap();
+        send_offer(&notifier, &offer("2", "iPhone 13", 450.0), OfferAlert::Deal(None)).await.unwrap();
+
+        let requests = requests.lock().unwrap();
+        assert!(requests[0].1.contains("PRIORITY"), "{}", requests[0].1);
+        assert!(!requests[1].1.contains("PRIORITY"), "{}", requests[1].1);
+    }
+
+    #[test]
+    fn offer_template_uses_the_number_format() {
+        let mut config = app_config(Vec::new());
+        config.number_format = crate::config::NumberFormat::Swiss;
+        let offer = offer("42", "iPhone 13", 1234.5);
+        let rendered =
+            render_offer_template("CHF {price}", &offer, "", OfferAlert::WatchedSeller, &config, ParseMode::Html);
+        assert_eq!(rendered, "CHF 1'234.50");
+    }
+}
@@ -0,0 +1,108 @@
+// notifier/telegram/webhook.rs
+
+use crate::http_server::{self, HttpRequest, HttpResponse};
+use crate::notifier::telegram::listener::{handle_update, TelegramUpdate};
+use crate::notifier::telegram::TelegramNotifier;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// Header Telegram uses to echo the secret passed to `setWebhook`.
+const SECRET_HEADER: &str = "x-telegram-bot-api-secret-token";
+
+/// Registers the webhook with Telegram and serves incoming updates until the task ends.
+/// TLS is expected to be terminated by a reverse proxy in front of `webhook_listen_addr`.
+/// Webhook settings are read once at startup; changing them requires a restart.
+pub async fn run_webhook(notifier: Arc<TelegramNotifier>) {
+    let config = notifier.config.load();
+    let Some(webhook_url) = config.webhook_url.clone() else {
+        warn!("❌ telegram_mode is \"webhook\" but webhook_url is not set");
+        return;
+    };
+
+    if let Err(e) = register_webhook(&notifier, &webhook_url, config.webhook_secret.as_deref()).await {
+        warn!("❌ setWebhook failed: {}", e);
+        return;
+    }
+
+    // Only accept updates on the path Telegram was told about.
+    let expected_path = reqwest::Url::parse(&webhook_url)
+        .map(|u| u.path().to_string())
+        .unwrap_or_else(|_| "/".to_string());
+
+    let listen_addr = config.webhook_listen_addr.clone();
+    let secret = config.webhook_secret.clone();
+    let server_notifier = notifier.clone();
+    let result = http_server::serve(&listen_addr, move |request| {
+        let notifier = server_notifier.clone();
+        let response = handle_request(request, &expected_path, secret.as_deref(), notifier);
+        async move { response }
+    })
+    .await;
+
+    if let Err(e) = result {
+        warn!("❌ Webhook server on {} stopped: {}", listen_addr, e);
+    }
+}
+
+/// Calls `setWebhook` so Telegram starts pushing updates to `webhook_url`.
+async fn register_webhook(
+    notifier: &TelegramNotifier,
+    webhook_url: &str,
+    secret: Option<&str>,
+) -> Result<(), String> {
+    let url = notifier.api_url("setWebhook");
+    let mut params = vec![("url", webhook_url.to_string())];
+    if let Some(secret) = secret {
+        params.push(("secret_token", secret.to_string()));
+    }
+
+    let response = notifier
+        .client
+        .post(&url)
+        .form(&params)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    if !status.is_success() {
+        return Err(format!("[{}] {}", status, body));
+    }
+    info!("✅ Webhook registered: {}", webhook_url);
+    Ok(())
+}
+
+/// Validates an incoming webhook request and dispatches the update in the background,
+/// so Telegram gets its 200 without waiting for command handling.
+fn handle_request(
+    request: HttpRequest,
+    expected_path: &str,
+    secret: Option<&str>,
+    notifier: Arc<TelegramNotifier>,
+) -> HttpResponse {
+    if request.path != expected_path {
+        return HttpResponse::text(404, "Not Found");
+    }
+    if request.method != "POST" {
+        return HttpResponse::text(405, "Method Not Allowed");
+    }
+
+    let secret_mismatch =
+        secret.is_some_and(|secret| request.headers.get(SECRET_HEADER).map(String::as_str) != Some(secret));
+    if secret_mismatch {
+        return HttpResponse::text(401, "Unauthorized");
+    }
+
+    let update: TelegramUpdate = match serde_json::from_slice(&request.body) {
+        Ok(update) => update,
+        Err(e) => {
+            warn!("Invalid webhook payload: {}", e);
+            return HttpResponse::text(400, "Bad Request");
+        }
+    };
+
+    tokio::spawn(async move {
+        handle_update(&update, &notifier).await;
+    });
+    HttpResponse::text(200, "OK")
+}
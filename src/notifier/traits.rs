@@ -0,0 +1,37 @@
+use crate::model::{Deal, NotifyError, Offer};
+
+/// Destination for offer alerts and status messages.
+#[async_trait::async_trait]
+pub trait Notifier: Send + Sync {
+    /// Sends an offer notification; fails with `NotifyError::Muted` while muted.
+    async fn notify(&self, offer: &Offer) -> Result<(), NotifyError>;
+
+    /// Sends a plain status text (startup, shutdown, empty-run warnings).
+    async fn notify_text(&self, text: &str) -> Result<(), NotifyError>;
+
+    /// Sends a deal notification; without a dedicated format it is sent like any offer.
+    async fn notify_deal(&self, deal: &Deal) -> Result<(), NotifyError> {
+        self.notify(&deal.offer).await
+    }
+
+    /// Sends a "price dropped" notification; without a dedicated format it is sent like any offer.
+    async fn notify_price_drop(&self, offer: &Offer, _previous_price: f64) -> Result<(), NotifyError> {
+        self.notify(offer).await
+    }
+
+    /// Sends a "new listing from a watched seller" notification; without a dedicated format
+    /// it is sent like any offer.
+    async fn notify_watched_seller(&self, offer: &Offer) -> Result<(), NotifyError> {
+        self.notify(offer).await
+    }
+
+    /// True while /pause_all is in effect.
+    fn is_paused(&self) -> bool {
+        false
+    }
+
+    /// True while offer notifications are suppressed by /mute.
+    fn is_muted(&self) -> bool {
+        false
+    }
+}
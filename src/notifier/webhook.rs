@@ -0,0 +1,258 @@
+use crate::config::SharedConfig;
+use crate::metrics::METRICS;
+use crate::model::{Deal, NotifyError, Offer};
+use crate::notifier::telegram::{sender::truncate_message, stored_mute_remaining, stored_paused};
+use crate::notifier::Notifier;
+use crate::storage::SqliteStorage;
+use reqwest::Client;
+use serde::Serialize;
+use std::sync::Arc;
+
+/// Discord rejects messages longer than this; Slack and Matrix bridges accept more.
+const MAX_CONTENT_CHARS: usize = 2000;
+
+/// JSON body posted for every notification.
+#[derive(Debug, Serialize)]
+struct WebhookPayload<'a> {
+    /// "offer", "deal", "price_drop", "watched_seller" or "text".
+    kind: &'a str,
+    /// Message text for Discord webhooks.
+    content: &'a str,
+    /// The same text for Slack incoming webhooks and most Matrix bridges.
+    text: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    offer: Option<&'a Offer>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    score: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    previous_price: Option<f64>,
+}
+
+/// Posts notifications as JSON to `notify_webhook_url` (Discord, Slack, Matrix bridges, ...).
+/// Honors /mute and /pause_all through the state they persist in storage.
+pub struct WebhookNotifier {
+    client: Client,
+    storage: Arc<SqliteStorage>,
+    config: Arc<SharedConfig>,
+}
+
+impl WebhookNotifier {
+    pub fn new(storage: Arc<SqliteStorage>, config: Arc<SharedConfig>) -> Self {
+        let client = Client::builder()
+            .timeout(std::time::Duration::from_secs(10))
+            .build()
+            .expect("❗ Failed to create HTTP client");
+        Self { client, storage, config }
+    }
+
+    /// Sends an offer alert unless muted; `headline` starts the message.
+    async fn send_offer(
+        &self,
+        kind: &str,
+        offer: &Offer,
+        headline: &str,
+        score: Option<f64>,
+        previous_price: Option<f64>,
+    ) -> Result<(), NotifyError> {
+        if stored_mute_remaining(&self.storage).is_some() {
+            return Err(NotifyError::Muted);
+        }
+        let config = self.config.load();
+        let mut message = format!(
+            "{}\n\n{}\n📦 Model: {}\n💰 Price: {}{}\n",
+            headline,
+            offer.title,
+            offer.model,
+            config.format_price(offer.price),
+            if offer.negotiable { " (VB)" } else { "" },
+        );
+        if let Some(score) = score {
+            message.push_str(&format!("⭐ Deal score: {:.0}\n", score));
+        }
+        message.push_str(&format!("🔗 Link: {}", offer.link));
+
+        let payload = WebhookPayload {
+            kind,
+            content: &truncate_message(&message, MAX_CONTENT_CHARS),
+            text: &message,
+            offer: Some(offer),
+            score,
+            previous_price,
+        };
+        self.post(&payload).await?;
+        METRICS.inc_notifications_sent();
+        Ok(())
+    }
+
+    async fn post(&self, payload: &WebhookPayload<'_>) -> Result<(), NotifyError> {
+        let Some(url) = self.config.load().notify_webhook_url.clone() else {
+            return Err(NotifyError::ApiError("notify_webhook_url is not set".to_string()));
+        };
+        let response = self
+            .client
+            .post(&url)
+            .json(payload)
+            .send()
+            .await
+            .map_err(|_| NotifyError::Unreachable)?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            let retry_after = response
+                .headers()
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(1);
+            return Err(NotifyError::RateLimited(retry_after));
+        }
+        let body = response.text().await.unwrap_or_default();
+        Err(NotifyError::ApiError(format!("{}: {}", status, body)))
+    }
+}
+
+#[async_trait::async_trait]
+impl Notifier for WebhookNotifier {
+    async fn notify(&self, offer: &Offer) -> Result<(), NotifyError> {
+        self.send_offer("offer", offer, "💸 Found a great deal!", None, None).await
+    }
+
+    async fn notify_text(&self, text: &str) -> Result<(), NotifyError> {
+        let payload = WebhookPayload {
+            kind: "text",
+            content: &truncate_message(text, MAX_CONTENT_CHARS),
+            text,
+            offer: None,
+            score: None,
+            previous_price: None,
+        };
+        self.post(&payload).await
+    }
+
+    async fn notify_deal(&self, deal: &Deal) -> Result<(), NotifyError> {
+        self.send_offer("deal", &deal.offer, "💸 Found a great deal!", Some(deal.score), None)
+            .await
+    }
+
+    async fn notify_price_drop(&self, offer: &Offer, previous_price: f64) -> Result<(), NotifyError> {
+        let config = self.config.load();
+        let headline = format!(
+            "📉 Price dropped from {} to {}",
+            config.format_price(previous_price),
+            config.format_price(offer.price)
+        );
+        self.send_offer("price_drop", offer, &headline, None, Some(previous_price))
+            .await
+    }
+
+    async fn notify_watched_seller(&self, offer: &Offer) -> Result<(), NotifyError> {
+        self.send_offer("watched_seller", offer, "👀 New listing from a watched seller", None, None)
+            .await
+    }
+
+    fn is_paused(&self) -> bool {
+        stored_paused(&self.storage)
+    }
+
+    fn is_muted(&self) -> bool {
+        stored_mute_remaining(&self.storage).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{NotifierKind, SharedConfig};
+    use crate::notifier::telegram::{MUTE_UNTIL_KEY, PAUSED_KEY};
+    use crate::test_support::{app_config, offer, scripted_server, RecordedRequests, TempStorage};
+
+    async fn webhook_notifier(
+        storage: &TempStorage,
+        responses: Vec<(u16, &'static str)>,
+    ) -> (WebhookNotifier, RecordedRequests) {
+        let (base, requests) = scripted_server(responses).await;
+        let mut config = app_config(Vec::new());
+        (config.notifier, config.notify_webhook_url) = (NotifierKind::Webhook, Some(format!("{}/hook", base)));
+        let config = Arc::new(SharedConfig::new("config.json", config));
+        (WebhookNotifier::new(storage.storage.clone(), config), requests)
+    }
+
+    fn posted(requests: &RecordedRequests) -> Vec<serde_json::Value> {
+        requests.lock().unwrap().iter().map(|(_, body)| serde_json::from_str(body).unwrap()).collect()
+    }
+
+    #[tokio::test]
+    async fn deals_and_texts_are_posted_as_json() {
+        let storage = TempStorage::new();
+        let (notifier, requests) = webhook_notifier(&storage, vec![(204, "")]).await;
+        let deal = Deal { offer: offer("42", "iPhone 13", 450.0), score: 87.4 };
+
+        notifier.notify_deal(&deal).await.unwrap();
+        notifier.notify_text("✅ KleinSniper started").await.unwrap();
+
+        let posted = posted(&requests);
+        assert_eq!(requests.lock().unwrap()[0].0, "/hook");
+        assert_eq!(posted[0]["kind"], "deal");
+        assert_eq!(posted[0]["score"], 87.4);
+        assert_eq!(posted[0]["offer"]["id"], "42");
+        let content = posted[0]["content"].as_str().unwrap();
+        assert!(content.contains("iPhone 13") && content.contains("450,00 €"), "{}", content);
+        assert!(content.contains("Deal score: 87"), "{}", content);
+        assert_eq!(posted[0]["content"], posted[0]["text"]);
+        assert_eq!(
+            posted[1],
+            serde_json::json!({
+                "kind": "text",
+                "content": "✅ KleinSniper started",
+                "text": "✅ KleinSniper started",
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn long_messages_are_cut_for_discord_only() {
+        let storage = TempStorage::new();
+        let (notifier, requests) = webhook_notifier(&storage, vec![(200, "ok")]).await;
+        let text = "x".repeat(MAX_CONTENT_CHARS + 500);
+
+        notifier.notify_text(&text).await.unwrap();
+
+        let posted = posted(&requests);
+        assert_eq!(posted[0]["content"].as_str().unwrap().chars().count(), MAX_CONTENT_CHARS);
+        assert_eq!(posted[0]["text"].as_str().unwrap(), text);
+    }
+
+    #[tokio::test]
+    async fn error_statuses_become_notify_errors() {
+        let storage = TempStorage::new();
+        let (notifier, _) = webhook_notifier(&storage, vec![(429, "slow down"), (500, "boom")]).await;
+
+        assert!(matches!(notifier.notify_text("a").await, Err(NotifyError::RateLimited(1))));
+        match notifier.notify_text("b").await {
+            Err(NotifyError::ApiError(message)) => {
+                assert!(message.contains("500") && message.contains("boom"), "{}", message)
+            }
+            other => panic!("expected an API error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn mute_and_pause_are_read_from_storage() {
+        let storage = TempStorage::new();
+        let (notifier, requests) = webhook_notifier(&storage, vec![(200, "ok")]).await;
+        assert!(!notifier.is_paused() && !notifier.is_muted());
+
+        storage.set_meta(PAUSED_KEY, "1").unwrap();
+        let in_an_hour = chrono::Utc::now().timestamp() + 3600;
+        storage.set_meta(MUTE_UNTIL_KEY, &in_an_hour.to_string()).unwrap();
+        assert!(notifier.is_paused() && notifier.is_muted());
+
+        // Offers are held back while muted; plain texts still go out
+        assert!(matches!(notifier.notify(&offer("1", "iPhone 13", 450.0)).await, Err(NotifyError::Muted)));
+        notifier.notify_text("status").await.unwrap();
+        let kinds: Vec<serde_json::Value> = posted(&requests).into_iter().map(|p| p["kind"].clone()).collect();
+        assert_eq!(kinds, vec!["text"]);
+    }
+}
@@ -1,92 +1,247 @@
-use crate::model::{Offer, ParserError};
-use crate::config::ModelConfig;
+use crate::model::{Offer, ParserError, PriceKind};
+use crate::config::{ModelConfig, NumberFormat};
 use scraper::{Html, Selector};
-use chrono::Utc;
-use tracing::info;
+use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use std::collections::HashSet;
+use std::fmt;
+use tracing::{debug, info, warn};
 
-pub struct KleinanzeigenParser;
+/// Listing elements longer than this are cut in debug logs of skipped listings.
+const MAX_SNIPPET_CHARS: usize = 500;
+
+/// How many listings `parse_filtered` saw and why the ones it dropped were skipped.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParseReport {
+    pub total_items: usize,
+    pub skipped_no_title: usize,
+    pub skipped_no_price: usize,
+    pub skipped_no_id: usize,
+    pub skipped_duplicate: usize,
+    pub skipped_price_filter: usize,
+    pub skipped_keyword: usize,
+    pub skipped_excluded: usize,
+    pub skipped_location: usize,
+    pub skipped_age: usize,
+}
+
+impl ParseReport {
+    pub fn skipped(&self) -> usize {
+        self.skipped_no_title
+            + self.skipped_no_price
+            + self.skipped_no_id
+            + self.skipped_duplicate
+            + self.skipped_price_filter
+            + self.skipped_keyword
+            + self.skipped_excluded
+            + self.skipped_location
+            + self.skipped_age
+    }
+}
+
+impl fmt::Display for ParseReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} items, {} skipped (no title: {}, no price: {}, no id: {}, duplicate: {}, price filter: {}, \
+             keywords: {}, excluded: {}, location: {}, age: {})",
+            self.total_items,
+            self.skipped(),
+            self.skipped_no_title,
+            self.skipped_no_price,
+            self.skipped_no_id,
+            self.skipped_duplicate,
+            self.skipped_price_filter,
+            self.skipped_keyword,
+            self.skipped_excluded,
+            self.skipped_location,
+            self.skipped_age,
+        )
+    }
+}
+
+/// Parser for Kleinanzeigen search result pages. CSS selectors are compiled once in `new`
+/// and reused for every page.
+pub struct KleinanzeigenParser {
+    item_selector: Selector,
+    title_selector: Selector,
+    price_selector: Selector,
+    location_selector: Selector,
+    description_selector: Selector,
+    user_name_selector: Selector,
+    seller_link_selector: Selector,
+    image_selector: Selector,
+    date_selector: Selector,
+    article_selector: Selector,
+    shipping_selector: Selector,
+    number_format: NumberFormat,
+}
 
 impl KleinanzeigenParser {
-    pub fn new() -> Self {
-        Self
+    pub fn new() -> Result<Self, ParserError> {
+        let selector = |css: &str| {
+            Selector::parse(css).map_err(|e| ParserError::HtmlParseError(e.to_string()))
+        };
+        Ok(Self {
+            item_selector: selector("li.ad-listitem")?,
+            title_selector: selector("h2.text-module-begin a.ellipsis")?,
+            price_selector: selector("p.aditem-main--middle--price-shipping--price")?,
+            location_selector: selector("div.aditem-main--top--left")?,
+            description_selector: selector("p.aditem-main--middle--description")?,
+            user_name_selector: selector("div.aditem-main--bottom span.ellipsis")?,
+            seller_link_selector: selector("div.aditem-main--bottom a[href*='userId=']")?,
+            image_selector: selector("div.aditem-image img")?,
+            date_selector: selector("div.aditem-main--top--right")?,
+            article_selector: selector("article.aditem[data-adid]")?,
+            shipping_selector: selector("p.aditem-main--middle--price-shipping--shipping")?,
+            number_format: NumberFormat::default(),
+        })
+    }
+
+    /// Reads listing prices in the given number format instead of the German default.
+    pub fn with_number_format(mut self, number_format: NumberFormat) -> Self {
+        self.number_format = number_format;
+        self
     }
 
-    pub fn parse_filtered(&self, html: &str, cfg: &ModelConfig) -> Result<Vec<Offer>, ParserError> {
+    /// Parses all listings that pass the model's filters, together with a report of
+    /// what was skipped. Skipped listings without a title or price are logged at debug level.
+    pub fn parse_filtered(&self, html: &str, cfg: &ModelConfig) -> Result<(Vec<Offer>, ParseReport), ParserError> {
         let document = Html::parse_document(html);
-        let item_selector = Selector::parse("li.ad-listitem")
-            .map_err(|e| ParserError::HtmlParseError(e.to_string()))?;
-        let title_selector = Selector::parse("h2.text-module-begin a.ellipsis")
-            .map_err(|e| ParserError::HtmlParseError(e.to_string()))?;
-        let price_selector = Selector::parse("p.aditem-main--middle--price-shipping--price")
-            .map_err(|e| ParserError::HtmlParseError(e.to_string()))?;
-        let location_selector = Selector::parse("div.aditem-main--top--left")
-            .map_err(|e| ParserError::HtmlParseError(e.to_string()))?;
-        let description_selector = Selector::parse("p.aditem-main--middle--description")
-            .map_err(|e| ParserError::HtmlParseError(e.to_string()))?;
-        let user_name_selector = Selector::parse("div.aditem-main--bottom span.ellipsis")
-            .map_err(|e| ParserError::HtmlParseError(e.to_string()))?;
 
         let mut offers = Vec::new();
+        let mut report = ParseReport::default();
+        // Pages are concatenated, so the same ad can show up twice; keep the first occurrence.
+        let mut seen_ids = HashSet::new();
+        let title_regex = cfg.title_regex();
 
-        for element in document.select(&item_selector) {
-            let title_elem = element.select(&title_selector).next();
-            if title_elem.is_none() {
+        for element in document.select(&self.item_selector) {
+            report.total_items += 1;
+            let Some(title_node) = element.select(&self.title_selector).next() else {
+                report.skipped_no_title += 1;
+                debug!("Skipping listing without a title: {}", snippet(&element.html()));
                 continue;
-            }
-            let title_node = title_elem.unwrap();
+            };
 
-            let price_elem = element.select(&price_selector).next();
-            if price_elem.is_none() {
+            let Some(price_node) = element.select(&self.price_selector).next() else {
+                report.skipped_no_price += 1;
+                debug!("Skipping listing without a price: {}", snippet(&element.html()));
                 continue;
-            }
-            let price_node = price_elem.unwrap();
+            };
 
             let title = title_node.inner_html().trim().to_string();
             let link_raw = title_node.value().attr("href").unwrap_or("");
             let link = format!("https://www.kleinanzeigen.de{}", link_raw);
 
-            let path_segments: Vec<&str> = link_raw.split('/').collect();
-            let last_segment = path_segments.last().unwrap_or(&"");
-            let numeric_id = last_segment.split('-').next().unwrap_or("");
-            let id = numeric_id.to_string();
-
-            let price_text = price_node
-                .text()
-                .collect::<Vec<_>>()
-                .join(" ")
-                .replace("€", "")
-                .replace(".", "")
-                .replace(",", ".")
-                .trim()
-                .to_string();
-            let price = price_text.parse::<f64>().unwrap_or(0.0);
-
-            if price < cfg.min_price || price > cfg.max_price {
+            // Prefer the canonical ad id; the URL shape varies (e.g. trailing `/preview`).
+            let ad_id = element
+                .select(&self.article_selector)
+                .next()
+                .and_then(|article| article.value().attr("data-adid"))
+                .map(|id| id.trim().to_string())
+                .filter(|id| !id.is_empty())
+                .or_else(|| parse_ad_id_from_link(link_raw));
+            let Some(id) = ad_id else {
+                report.skipped_no_id += 1;
+                warn!("Skipping offer without an ad id: {}", link);
+                continue;
+            };
+            if !seen_ids.insert(id.clone()) {
+                report.skipped_duplicate += 1;
                 continue;
             }
 
-            let title_lower = title.to_lowercase();
-            if !cfg.match_keywords.iter().any(|kw| title_lower.contains(&kw.to_lowercase())) {
+            let raw_price = price_node.text().collect::<Vec<_>>().join(" ");
+            let (price_kind, price, negotiable) = parse_price(&raw_price, self.number_format);
+
+            let outside_price_filter = match price_kind {
+                PriceKind::Fixed => price < cfg.min_price || price > cfg.max_price,
+                // Free items are only wanted when the model accepts a zero price.
+                PriceKind::Free => cfg.min_price > 0.0,
+                // Nothing to compare against.
+                PriceKind::OnRequest => true,
+            };
+            if outside_price_filter {
+                report.skipped_price_filter += 1;
                 continue;
             }
 
+            let title_matches = match &title_regex {
+                Some(regex) => regex.is_match(&title),
+                None => {
+                    let title_lower = title.to_lowercase();
+                    cfg.match_keywords.iter().any(|kw| title_lower.contains(&kw.to_lowercase()))
+                }
+            };
+            if !title_matches {
+                report.skipped_keyword += 1;
+                continue;
+            }
+
+            let shipping_available = element
+                .select(&self.shipping_selector)
+                .any(|n| is_shipping_available(&n.text().collect::<String>()));
+
             let location = element
-                .select(&location_selector)
+                .select(&self.location_selector)
                 .next()
                 .map(|n| n.text().collect::<Vec<_>>().join(" ").trim().to_string())
                 .unwrap_or_default();
+            // The search radius is approximate; far-away listings still show up.
+            if !cfg.is_location_allowed(&location) {
+                report.skipped_location += 1;
+                continue;
+            }
 
             let description = element
-                .select(&description_selector)
+                .select(&self.description_selector)
                 .next()
                 .map(|n| n.text().collect::<Vec<_>>().join(" ").trim().to_string())
                 .unwrap_or_default();
 
+            if is_excluded(&title, &description, &cfg.exclude_keywords) {
+                report.skipped_excluded += 1;
+                continue;
+            }
+
             let user_name = element
-                .select(&user_name_selector)
+                .select(&self.user_name_selector)
                 .last()
                 .map(|n| n.text().collect::<String>().trim().to_string());
 
+            let (user_id, user_url) = element
+                .select(&self.seller_link_selector)
+                .next()
+                .and_then(|n| n.value().attr("href"))
+                .and_then(parse_seller_link)
+                .map(|(id, url)| (Some(id), Some(url)))
+                .unwrap_or((None, None));
+
+            let image_url = element
+                .select(&self.image_selector)
+                .next()
+                .and_then(|img| {
+                    let attrs = img.value();
+                    attrs
+                        .attr("src")
+                        .or_else(|| attrs.attr("data-imgsrc"))
+                        .or_else(|| attrs.attr("data-src"))
+                })
+                .filter(|src| src.starts_with("http"))
+                .map(|src| src.to_string());
+
+            let fetched_at = Utc::now();
+            let posted_at = element
+                .select(&self.date_selector)
+                .next()
+                .map(|n| n.text().collect::<Vec<_>>().join(" "))
+                .and_then(|text| parse_posted_at(&text, fetched_at));
+            // Listings without a readable date (e.g. promoted ones) are kept
+            if posted_at.is_some_and(|posted_at| !cfg.is_age_allowed(posted_at, fetched_at)) {
+                report.skipped_age += 1;
+                continue;
+            }
+            let posted_at = posted_at.unwrap_or(fetched_at);
+
             let offer = Offer {
                 id,
                 title,
@@ -95,17 +250,686 @@ impl KleinanzeigenParser {
                 location,
                 model: cfg.query.clone(),
                 link,
-                posted_at: Utc::now(),
-                fetched_at: Utc::now(),
-                user_id: None,
+                posted_at,
+                fetched_at,
+                user_id,
                 user_name,
-                user_url: None,
+                user_url,
+                negotiable,
+                price_kind,
+                image_url,
+                shipping_available,
             };
 
             offers.push(offer);
         }
 
-        info!("Parsed {} offers from HTML", offers.len());
-        Ok(offers)
+        info!("Parsed {} offers from HTML: {}", offers.len(), report);
+        Ok((offers, report))
+    }
+}
+
+/// Cuts a listing's HTML for logging.
+fn snippet(html: &str) -> String {
+    let mut snippet: String = html.chars().take(MAX_SNIPPET_CHARS).collect();
+    if snippet.len() < html.len() {
+        snippet.push('…');
+    }
+    snippet
+}
+
+/// Whether a listing's shipping badge offers shipping ("Versand möglich") rather than pickup only.
+fn is_shipping_available(badge: &str) -> bool {
+    badge.to_lowercase().contains("versand möglich")
+}
+
+/// Returns true when the title or description contains any of the exclude keywords (case-insensitive).
+pub fn is_excluded(title: &str, description: &str, exclude_keywords: &[String]) -> bool {
+    let title = title.to_lowercase();
+    let description = description.to_lowercase();
+    exclude_keywords.iter().any(|kw| {
+        let kw = kw.to_lowercase();
+        title.contains(&kw) || description.contains(&kw)
+    })
+}
+
+/// Extracts the numeric ad id from a listing URL such as `/s-anzeige/rog-ally/2712345678-278-3331`.
+/// Looks at path segments from the end, so suffixes like `/preview` are skipped.
+pub fn parse_ad_id_from_link(href: &str) -> Option<String> {
+    let path = href.split(['?', '#']).next().unwrap_or(href);
+    path.rsplit('/')
+        .filter_map(|segment| segment.split('-').next())
+        .find(|prefix| !prefix.is_empty() && prefix.chars().all(|c| c.is_ascii_digit()))
+        .map(|prefix| prefix.to_string())
+}
+
+/// Parses a listing price such as "1.250 € VB", "Zu verschenken" or "Preis auf Anfrage".
+/// Returns the price kind, the numeric price (0.0 unless fixed) and whether "VB" was present.
+pub fn parse_price(raw: &str, number_format: NumberFormat) -> (PriceKind, f64, bool) {
+    let lower = raw.to_lowercase();
+    if lower.contains("zu verschenken") {
+        return (PriceKind::Free, 0.0, false);
+    }
+
+    let negotiable = raw.split_whitespace().any(|w| w == "VB");
+    match parse_localized_price(raw, number_format) {
+        Some(price) => (PriceKind::Fixed, price, negotiable),
+        // "Preis auf Anfrage", a bare "VB" or an empty price tag.
+        None => (PriceKind::OnRequest, 0.0, negotiable),
+    }
+}
+
+/// Extracts the first number from a price text written in `number_format`, e.g. "CHF 1'250.50" (Swiss)
+/// or "$1,250.50" (English). Other formats are mapped onto the German one and parsed by `parse_german_price`.
+pub fn parse_localized_price(raw: &str, number_format: NumberFormat) -> Option<f64> {
+    let swap_separators = |text: &str| {
+        text.chars()
+            .map(|c| match c {
+                '.' => ',',
+                ',' => '.',
+                other => other,
+            })
+            .collect::<String>()
+    };
+    match number_format {
+        NumberFormat::German => parse_german_price(raw),
+        NumberFormat::English => parse_german_price(&swap_separators(raw)),
+        NumberFormat::Swiss => parse_german_price(&swap_separators(&raw.replace(['\'', '’'], ""))),
+    }
+}
+
+/// Extracts the first German-formatted number from a price text, e.g. "1.234,56 € VB" -> 1234.56,
+/// "€ 1.000" -> 1000.0, "95 €" -> 95.0. A comma is the decimal separator and dots group thousands;
+/// a lone dot not followed by exactly three digits is read as a decimal point.
+/// Returns `None` when the text contains no number.
+pub fn parse_german_price(raw: &str) -> Option<f64> {
+    let start = raw.find(|c: char| c.is_ascii_digit())?;
+    let token: String = raw[start..]
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || *c == '.' || *c == ',')
+        .collect();
+    let token = token.trim_end_matches(['.', ',']);
+
+    let normalized = match token.split_once(',') {
+        Some((integer, fraction)) => {
+            if fraction.contains([',', '.']) {
+                return None;
+            }
+            format!("{}.{}", integer.replace('.', ""), fraction)
+        }
+        None => {
+            let groups: Vec<&str> = token.split('.').collect();
+            let thousands = groups.len() > 1 && groups[1..].iter().all(|g| g.len() == 3);
+            if thousands || groups.len() == 1 {
+                token.replace('.', "")
+            } else if groups.len() == 2 {
+                token.to_string()
+            } else {
+                return None;
+            }
+        }
+    };
+
+    normalized.parse::<f64>().ok().filter(|p| p.is_finite())
+}
+
+/// Parses the posting date shown on a listing ("Heute, 14:32", "Gestern, 09:10" or "05.03.2024").
+/// The site shows German local time, so the result is converted from Europe/Berlin to UTC and
+/// "heute"/"gestern" refer to the Berlin date at `now`. A missing time defaults to local midnight.
+pub fn parse_posted_at(text: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let (day_part, time_part) = match text.split_once(',') {
+        Some((day, time)) => (day.trim(), Some(time.trim())),
+        None => (text.trim(), None),
+    };
+
+    let today = now.with_timezone(&berlin_offset(now)).date_naive();
+    let date = match day_part.to_lowercase().as_str() {
+        "heute" => today,
+        "gestern" => today.pred_opt()?,
+        other => NaiveDate::parse_from_str(other, "%d.%m.%Y").ok()?,
+    };
+
+    let time = match time_part {
+        Some(t) if !t.is_empty() => NaiveTime::parse_from_str(t, "%H:%M").ok()?,
+        _ => NaiveTime::MIN,
+    };
+
+    Some(berlin_to_utc(date.and_time(time)))
+}
+
+/// UTC offset of German time at `instant`: CEST (+2h) from 01:00 UTC on the last Sunday of March
+/// to 01:00 UTC on the last Sunday of October, CET (+1h) otherwise.
+fn berlin_offset(instant: DateTime<Utc>) -> FixedOffset {
+    let year = instant.year();
+    let summer_start = last_sunday(year, 3).and_hms_opt(1, 0, 0).map(|t| t.and_utc());
+    let summer_end = last_sunday(year, 10).and_hms_opt(1, 0, 0).map(|t| t.and_utc());
+    let is_summer = match (summer_start, summer_end) {
+        (Some(start), Some(end)) => instant >= start && instant < end,
+        _ => false,
+    };
+    FixedOffset::east_opt(if is_summer { 2 * 3600 } else { 3600 }).expect("offset is in range")
+}
+
+/// Converts a Berlin wall-clock time to UTC. In the repeated autumn hour the earlier (CEST) instant
+/// is used; a time in the skipped spring hour is read as CET.
+fn berlin_to_utc(local: NaiveDateTime) -> DateTime<Utc> {
+    let as_summer = (local - Duration::hours(2)).and_utc();
+    if berlin_offset(as_summer).local_minus_utc() == 2 * 3600 {
+        as_summer
+    } else {
+        (local - Duration::hours(1)).and_utc()
+    }
+}
+
+fn last_sunday(year: i32, month: u32) -> NaiveDate {
+    let next_month = if month == 12 {
+        NaiveDate::from_ymd_opt(year + 1, 1, 1)
+    } else {
+        NaiveDate::from_ymd_opt(year, month + 1, 1)
+    };
+    let last_day = next_month.and_then(|d| d.pred_opt()).expect("valid month");
+    last_day - Duration::days(last_day.weekday().num_days_from_sunday() as i64)
+}
+
+/// Extracts the numeric seller id and absolute profile URL from a link like
+/// `/s-bestandsliste.html?userId=12345`.
+pub fn parse_seller_link(href: &str) -> Option<(String, String)> {
+    let (_, query) = href.split_once('?')?;
+    let user_id = query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("userId="))?;
+    if user_id.is_empty() || !user_id.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let url = if href.starts_with("http") {
+        href.to_string()
+    } else {
+        format!("https://www.kleinanzeigen.de{}", href)
+    };
+    Some((user_id.to_string(), url))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{search_page, Listing};
+    use chrono::TimeZone;
+
+    fn utc(y: i32, m: u32, d: u32, h: u32, min: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, m, d, h, min, 0).unwrap()
+    }
+
+    #[test]
+    fn relative_dates_are_berlin_local_time() {
+        // Summer: CEST is UTC+2
+        let now = utc(2025, 7, 10, 10, 0);
+        assert_eq!(parse_posted_at("Heute, 14:32", now), Some(utc(2025, 7, 10, 12, 32)));
+        assert_eq!(parse_posted_at("Gestern, 09:10", now), Some(utc(2025, 7, 9, 7, 10)));
+
+        // Winter: CET is UTC+1
+        let now = utc(2025, 1, 10, 10, 0);
+        assert_eq!(parse_posted_at("heute,  14:32", now), Some(utc(2025, 1, 10, 13, 32)));
+    }
+
+    #[test]
+    fn heute_is_the_berlin_date() {
+        // 22:30 UTC is already 00:30 the next day in Berlin
+        let now = utc(2025, 7, 10, 22, 30);
+        assert_eq!(parse_posted_at("Heute, 00:10", now), Some(utc(2025, 7, 10, 22, 10)));
+        assert_eq!(parse_posted_at("Gestern, 23:50", now), Some(utc(2025, 7, 10, 21, 50)));
+    }
+
+    #[test]
+    fn absolute_dates_start_at_berlin_midnight() {
+        let now = utc(2025, 7, 10, 10, 0);
+        assert_eq!(parse_posted_at("05.03.2024", now), Some(utc(2024, 3, 4, 23, 0)));
+        assert_eq!(parse_posted_at("05.08.2024", now), Some(utc(2024, 8, 4, 22, 0)));
+    }
+
+    #[test]
+    fn daylight_saving_switches_on_the_last_sundays() {
+        // Clocks go forward on 2025-03-30 at 02:00 CET, back on 2025-10-26 at 03:00 CEST
+        let spring = utc(2025, 3, 30, 12, 0);
+        assert_eq!(parse_posted_at("Heute, 01:30", spring), Some(utc(2025, 3, 30, 0, 30)));
+        assert_eq!(parse_posted_at("Heute, 03:30", spring), Some(utc(2025, 3, 30, 1, 30)));
+        let autumn = utc(2025, 10, 26, 12, 0);
+        assert_eq!(parse_posted_at("Heute, 01:30", autumn), Some(utc(2025, 10, 25, 23, 30)));
+        assert_eq!(parse_posted_at("Heute, 03:30", autumn), Some(utc(2025, 10, 26, 2, 30)));
+    }
+
+    #[test]
+    fn unreadable_dates_are_none() {
+        let now = utc(2025, 7, 10, 10, 0);
+        assert_eq!(parse_posted_at("", now), None);
+        assert_eq!(parse_posted_at("Morgen, 10:00", now), None);
+        assert_eq!(parse_posted_at("Heute, 25:00", now), None);
+        assert_eq!(parse_posted_at("31.02.2024", now), None);
+    }
+
+    #[test]
+    fn german_price_strings() {
+        let cases = [
+            ("1.234,56 € VB", Some(1234.56)),
+            ("1.250 € VB", Some(1250.0)),
+            ("1.000 €", Some(1000.0)),
+            ("€ 1.000", Some(1000.0)),
+            ("95 €", Some(95.0)),
+            ("699 € VB", Some(699.0)),
+            ("12,50 €", Some(12.5)),
+            ("2.499,00 €", Some(2499.0)),
+            ("1.234.567 €", Some(1_234_567.0)),
+            ("ab 50 €", Some(50.0)),
+            ("10.5", Some(10.5)),
+            ("1.500,- €", Some(1500.0)),
+            ("VB", None),
+            ("Preis auf Anfrage", None),
+            ("Zu verschenken", None),
+            ("", None),
+            ("1,2,3 €", None),
+            ("1.23.4 €", None),
+        ];
+        for (raw, expected) in cases {
+            assert_eq!(parse_german_price(raw), expected, "{:?}", raw);
+        }
+    }
+
+    #[test]
+    fn price_kinds_and_negotiable_flag() {
+        let german = NumberFormat::German;
+        assert_eq!(parse_price("1.250 € VB", german), (PriceKind::Fixed, 1250.0, true));
+        assert_eq!(parse_price("450 €", german), (PriceKind::Fixed, 450.0, false));
+        assert_eq!(parse_price("VB", german), (PriceKind::OnRequest, 0.0, true));
+        assert_eq!(parse_price("Preis auf Anfrage", german), (PriceKind::OnRequest, 0.0, false));
+        assert_eq!(parse_price("Zu verschenken", german), (PriceKind::Free, 0.0, false));
+    }
+
+    /// The date text the site would show for a listing posted `age` ago.
+    fn shown_date(age: Duration) -> String {
+        let now = Utc::now();
+        let posted = now - age;
+        let local = posted.with_timezone(&berlin_offset(posted));
+        let today = now.with_timezone(&berlin_offset(now)).date_naive();
+        if local.date_naive() == today {
+            local.format("Heute, %H:%M").to_string()
+        } else if Some(local.date_naive()) == today.pred_opt() {
+            local.format("Gestern, %H:%M").to_string()
+        } else {
+            local.format("%d.%m.%Y").to_string()
+        }
+    }
+
+    fn aged_listing(id: &str, age: Duration) -> Listing {
+        Listing { date: shown_date(age), ..Listing::new(id, "iPhone 13", "500 €") }
+    }
+
+    fn parsed_ids(html: &str, cfg: &ModelConfig) -> (Vec<String>, ParseReport) {
+        let (offers, report) = KleinanzeigenParser::new().unwrap().parse_filtered(html, cfg).unwrap();
+        (offers.into_iter().map(|o| o.id).collect(), report)
+    }
+
+    #[test]
+    fn max_age_drops_listings_older_than_the_cutoff() {
+        let mut cfg = ModelConfig::permissive("iphone 13");
+        cfg.max_age_hours = Some(24);
+        let html = search_page(&[
+            aged_listing("fresh", Duration::hours(1)),
+            aged_listing("just-inside", Duration::minutes(23 * 60 + 30)),
+            aged_listing("just-outside", Duration::minutes(24 * 60 + 30)),
+            aged_listing("old", Duration::days(21)),
+            Listing::new("undated", "iPhone 13", "500 €"),
+        ]);
+        let (ids, report) = parsed_ids(&html, &cfg);
+        assert_eq!(ids, vec!["fresh", "just-inside", "undated"]);
+        assert_eq!(report.skipped_age, 2);
+    }
+
+    #[test]
+    fn min_age_drops_brand_new_listings() {
+        let mut cfg = ModelConfig::permissive("iphone 13");
+        cfg.min_age_hours = Some(2);
+        cfg.max_age_hours = Some(48);
+        let html = search_page(&[
+            aged_listing("brand-new", Duration::minutes(10)),
+            aged_listing("just-too-new", Duration::minutes(90)),
+            aged_listing("old-enough", Duration::minutes(150)),
+            aged_listing("too-old", Duration::hours(49)),
+        ]);
+        let (ids, report) = parsed_ids(&html, &cfg);
+        assert_eq!(ids, vec!["old-enough"]);
+        assert_eq!(report.skipped_age, 3);
+    }
+
+    fn parsed_offers(html: &str, cfg: &ModelConfig) -> Vec<Offer> {
+        KleinanzeigenParser::new().unwrap().parse_filtered(html, cfg).unwrap().0
+    }
+
+    #[test]
+    fn seller_id_and_profile_url_are_parsed() {
+        let mut with_seller = Listing::new("1", "iPhone 13", "500 €");
+        with_seller.seller_id = Some("12345".to_string());
+        let without_seller = Listing::new("2", "iPhone 13", "500 €");
+        let offers = parsed_offers(&search_page(&[with_seller, without_seller]), &ModelConfig::permissive("iphone 13"));
+
+        assert_eq!(offers[0].user_id.as_deref(), Some("12345"));
+        assert_eq!(
+            offers[0].user_url.as_deref(),
+            Some("https://www.kleinanzeigen.de/s-bestandsliste.html?userId=12345")
+        );
+        assert_eq!(offers[0].user_name.as_deref(), Some("Seller 12345"));
+        assert_eq!((offers[1].user_id.as_deref(), offers[1].user_url.as_deref()), (None, None));
+    }
+
+    #[test]
+    fn seller_links() {
+        assert_eq!(
+            parse_seller_link("/s-bestandsliste.html?userId=42"),
+            Some(("42".to_string(), "https://www.kleinanzeigen.de/s-bestandsliste.html?userId=42".to_string()))
+        );
+        assert_eq!(
+            parse_seller_link("https://www.kleinanzeigen.de/s-bestandsliste.html?sort=1&userId=7"),
+            Some(("7".to_string(), "https://www.kleinanzeigen.de/s-bestandsliste.html?sort=1&userId=7".to_string()))
+        );
+        assert_eq!(parse_seller_link("/s-bestandsliste.html?userId="), None);
+        assert_eq!(parse_seller_link("/s-bestandsliste.html?userId=abc"), None);
+        assert_eq!(parse_seller_link("/s-bestandsliste.html"), None);
+    }
+
+    #[test]
+    fn free_and_on_request_price_variants() {
+        let german = NumberFormat::German;
+        assert_eq!(parse_price("Zu verschenken", german), (PriceKind::Free, 0.0, false));
+        assert_eq!(parse_price(" zu Verschenken ", german), (PriceKind::Free, 0.0, false));
+        assert_eq!(parse_price("Preis auf Anfrage", german), (PriceKind::OnRequest, 0.0, false));
+        assert_eq!(parse_price("VB", german), (PriceKind::OnRequest, 0.0, true));
+        assert_eq!(parse_price("", german), (PriceKind::OnRequest, 0.0, false));
+        assert_eq!(parse_price("90 € VB", german), (PriceKind::Fixed, 90.0, true));
+    }
+
+    #[test]
+    fn free_listings_need_a_zero_min_price_and_on_request_ones_are_skipped() {
+        let html = search_page(&[
+            Listing::new("free", "iPhone 13", "Zu verschenken"),
+            Listing::new("ask", "iPhone 13", "Preis auf Anfrage"),
+            Listing::new("vb", "iPhone 13", "VB"),
+            Listing::new("fixed", "iPhone 13", "450 € VB"),
+        ]);
+        let mut cfg = ModelConfig::permissive("iphone 13");
+
+        let offers = parsed_offers(&html, &cfg);
+        let kinds: Vec<_> = offers.iter().map(|o| (o.id.as_str(), o.price_kind, o.price, o.negotiable)).collect();
+        assert_eq!(kinds, vec![("free", PriceKind::Free, 0.0, false), ("fixed", PriceKind::Fixed, 450.0, true)]);
+
+        cfg.min_price = 100.0;
+        let (ids, report) = parsed_ids(&html, &cfg);
+        assert_eq!(ids, vec!["fixed"]);
+        assert_eq!(report.skipped_price_filter, 3);
+    }
+
+    #[test]
+    fn ad_id_comes_from_data_adid_and_falls_back_to_the_link() {
+        let cfg = ModelConfig::permissive("iphone 13");
+        // The slug's number differs from data-adid; the attribute wins.
+        let with_attribute = Listing::new("2712345678", "iPhone 13", "500 €")
+            .html()
+            .replace("/2712345678-173-3331", "/99-173-3331/preview");
+        // Older markup without the attribute: the id comes from the link.
+        let without_attribute = Listing::new("2700000001", "iPhone 13", "500 €")
+            .html()
+            .replace(r#" data-adid="2700000001""#, "");
+        let no_id_at_all = Listing::new("x", "iPhone 13", "500 €").html().replace(r#" data-adid="x""#, "");
+
+        let html = format!(
+            r#"<html><body><ul id="srchrslt-adtable">{}{}{}</ul></body></html>"#,
+            with_attribute, without_attribute, no_id_at_all
+        );
+        let (ids, report) = parsed_ids(&html, &cfg);
+        assert_eq!(ids, vec!["2712345678", "2700000001"]);
+        assert_eq!(report.skipped_no_id, 1);
+    }
+
+    #[test]
+    fn ad_ids_from_links() {
+        let cases = [
+            ("/s-anzeige/rog-ally/2712345678-278-3331", Some("2712345678")),
+            ("/s-anzeige/rog-ally/2712345678-278-3331/preview", Some("2712345678")),
+            ("https://www.kleinanzeigen.de/s-anzeige/x/123-1-2?utm=1#top", Some("123")),
+            ("/s-anzeige/rog-ally/abc-278", None),
+            ("/s-anzeige/", None),
+            ("", None),
+        ];
+        for (href, expected) in cases {
+            assert_eq!(parse_ad_id_from_link(href).as_deref(), expected, "{:?}", href);
+        }
+    }
+
+    #[test]
+    fn ads_repeated_across_concatenated_pages_are_kept_once() {
+        let first = Listing::new("1", "iPhone 13", "500 €");
+        let mut repeated = Listing::new("2", "iPhone 13", "450 €");
+        let page_one = search_page(&[first, repeated.clone()]);
+        // The ad moved to the next page between requests; the first copy wins.
+        repeated.price = "400 €".to_string();
+        let page_two = search_page(&[repeated, Listing::new("3", "iPhone 13", "480 €")]);
+
+        let (offers, report) = KleinanzeigenParser::new()
+            .unwrap()
+            .parse_filtered(&format!("{}{}", page_one, page_two), &ModelConfig::permissive("iphone 13"))
+            .unwrap();
+        let prices: Vec<_> = offers.iter().map(|o| (o.id.as_str(), o.price)).collect();
+        assert_eq!(prices, vec![("1", 500.0), ("2", 450.0), ("3", 480.0)]);
+        assert_eq!((report.total_items, report.skipped_duplicate), (4, 1));
+    }
+
+    #[test]
+    fn one_parser_reads_every_field_the_same_way_on_every_page() {
+        let listing = Listing {
+            location: "80331 München".to_string(),
+            description: "Akku 91%, mit OVP".to_string(),
+            date: "05.03.2024".to_string(),
+            seller_id: Some("777".to_string()),
+            shipping: true,
+            ..Listing::new("2712345678", "iPhone 13 Pro", "1.049 € VB")
+        };
+        let html = search_page(&[listing]).replace(
+            r#"<div class="aditem-main--top--left">"#,
+            r#"<div class="aditem-image"><img src="https://img.kleinanzeigen.de/1.jpg"></div><div class="aditem-main--top--left">"#,
+        );
+        let cfg = ModelConfig::permissive("iphone 13");
+        let parser = KleinanzeigenParser::new().unwrap();
+
+        let offer = parser.parse_filtered(&html, &cfg).unwrap().0.remove(0);
+        assert_eq!(offer.id, "2712345678");
+        assert_eq!(offer.title, "iPhone 13 Pro");
+        assert_eq!((offer.price_kind, offer.price, offer.negotiable), (PriceKind::Fixed, 1049.0, true));
+        assert_eq!(offer.location, "80331 München");
+        assert_eq!(offer.description, "Akku 91%, mit OVP");
+        assert_eq!(offer.model, "iphone 13");
+        assert_eq!(offer.link, "https://www.kleinanzeigen.de/s-anzeige/iphone-13-pro/2712345678-173-3331");
+        assert_eq!(offer.posted_at, utc(2024, 3, 4, 23, 0));
+        assert_eq!(offer.user_id.as_deref(), Some("777"));
+        assert_eq!(offer.user_name.as_deref(), Some("Seller 777"));
+        assert_eq!(offer.image_url.as_deref(), Some("https://img.kleinanzeigen.de/1.jpg"));
+        assert!(offer.shipping_available);
+
+        // The selectors are compiled once and reused: a second page parses identically.
+        let again = parser.parse_filtered(&html, &cfg).unwrap().0.remove(0);
+        let again = Offer { fetched_at: offer.fetched_at, ..again };
+        assert_eq!(serde_json::to_value(&again).unwrap(), serde_json::to_value(&offer).unwrap());
+    }
+
+    #[test]
+    fn exclude_keywords_apply_after_the_keyword_match() {
+        let listing = |id: &str, title: &str, description: &str| Listing {
+            description: description.to_string(),
+            ..Listing::new(id, title, "300 €")
+        };
+        let html = search_page(&[
+            listing("phone", "iPhone 13 128GB", "Top Zustand"),
+            listing("case", "Hülle für iPhone 13", ""),
+            listing("broken", "iPhone 13", "Display DEFEKT"),
+            listing("other", "Galaxy S21 kaputt", ""),
+        ]);
+        let mut cfg = ModelConfig::permissive("iphone 13");
+        cfg.match_keywords = vec!["iphone 13".to_string()];
+        cfg.exclude_keywords = vec!["hülle".to_string(), "Defekt".to_string(), "kaputt".to_string()];
+
+        let (ids, report) = parsed_ids(&html, &cfg);
+        assert_eq!(ids, vec!["phone"]);
+        // The Galaxy never matched "iphone 13", so it counts as a keyword miss, not an exclusion.
+        assert_eq!((report.skipped_keyword, report.skipped_excluded), (1, 2));
+
+        cfg.exclude_keywords.clear();
+        assert_eq!(parsed_ids(&html, &cfg).0, vec!["phone", "case", "broken"]);
+    }
+
+    #[test]
+    fn match_regex_replaces_the_keyword_match() {
+        let html = search_page(&[
+            Listing::new("phone", "Apple iPhone13 128GB", "300 €"),
+            Listing::new("case", "Hülle iPhone 135", "300 €"),
+        ]);
+        let mut cfg = ModelConfig::permissive("iphone 13");
+        cfg.match_keywords = vec!["iphone 13".to_string()];
+        cfg.match_regex = Some(r"\biphone\s?13\b".to_string());
+
+        let (ids, report) = parsed_ids(&html, &cfg);
+        assert_eq!(ids, vec!["phone"]);
+        assert_eq!(report.skipped_keyword, 1);
+    }
+
+    #[test]
+    fn exclusion_is_case_insensitive_over_title_and_description() {
+        let exclude = vec!["Displayschaden".to_string()];
+        assert!(is_excluded("iPhone 13 DISPLAYSCHADEN", "", &exclude));
+        assert!(is_excluded("iPhone 13", "leichter displayschaden", &exclude));
+        assert!(!is_excluded("iPhone 13", "Display ok", &exclude));
+        assert!(!is_excluded("iPhone 13", "displayschaden", &[]));
+    }
+
+    #[test]
+    fn shipping_badge_is_parsed() {
+        let shippable = Listing { shipping: true, ..Listing::new("1", "iPhone 13", "500 €") };
+        let pickup_only = Listing::new("2", "iPhone 13", "500 €");
+        let no_badge = Listing::new("3", "iPhone 13", "500 €")
+            .html()
+            .replace(r#"<p class="aditem-main--middle--price-shipping--shipping">Nur Abholung</p>"#, "");
+        let html = format!(
+            r#"<html><body><ul id="srchrslt-adtable">{}{}{}</ul></body></html>"#,
+            shippable.html(),
+            pickup_only.html(),
+            no_badge
+        );
+
+        let shipping: Vec<_> = parsed_offers(&html, &ModelConfig::permissive("iphone 13"))
+            .iter()
+            .map(|o| (o.id.clone(), o.shipping_available))
+            .collect();
+        assert_eq!(shipping, vec![("1".to_string(), true), ("2".to_string(), false), ("3".to_string(), false)]);
+    }
+
+    #[test]
+    fn shipping_badge_texts() {
+        assert!(is_shipping_available("Versand möglich"));
+        assert!(is_shipping_available("  VERSAND MÖGLICH "));
+        assert!(!is_shipping_available("Nur Abholung"));
+        assert!(!is_shipping_available(""));
+    }
+
+    #[test]
+    fn only_allowed_locations_are_kept() {
+        let at = |id: &str, location: &str| Listing {
+            location: location.to_string(),
+            ..Listing::new(id, "iPhone 13", "400 €")
+        };
+        let html = search_page(&[
+            at("berlin", "10115 Berlin Mitte"),
+            at("potsdam", "14467 POTSDAM"),
+            at("munich", "80331 München"),
+            at("nowhere", ""),
+        ]);
+        let mut cfg = ModelConfig::permissive("iphone 13");
+
+        // An empty allowlist keeps everything
+        assert_eq!(parsed_ids(&html, &cfg).0, vec!["berlin", "potsdam", "munich", "nowhere"]);
+
+        cfg.allowed_locations = vec!["berlin".to_string(), "Potsdam".to_string()];
+        let (ids, report) = parsed_ids(&html, &cfg);
+        assert_eq!(ids, vec!["berlin", "potsdam"]);
+        assert_eq!(report.skipped_location, 2);
+    }
+
+    #[test]
+    fn parse_report_counts_every_skip_reason() {
+        let listing = |id: &str, title: &str, price: &str| Listing::new(id, title, price);
+        let no_title = r#"<li class="ad-listitem"><article class="aditem" data-adid="t">
+<p class="aditem-main--middle--price-shipping--price">400 €</p></article></li>"#;
+        let no_price = listing("p", "iPhone 13", "400 €")
+            .html()
+            .replace(r#"<p class="aditem-main--middle--price-shipping--price">400 €</p>"#, "");
+        let no_id = listing("x", "iPhone 13", "400 €")
+            .html()
+            .replace(r#" data-adid="x""#, "")
+            .replace("/x-173-3331", "/preview");
+        let items = [
+            listing("ok", "iPhone 13", "400 €").html(),
+            no_title.to_string(),
+            no_price,
+            no_id,
+            listing("ok", "iPhone 13", "400 €").html(),
+            listing("pricey", "iPhone 13", "2.000 €").html(),
+            listing("pixel", "Pixel 7", "400 €").html(),
+            listing("case", "iPhone 13 Hülle", "20 €").html(),
+            Listing { location: "80331 München".to_string(), ..listing("far", "iPhone 13", "400 €") }.html(),
+            Listing { date: "05.03.2020".to_string(), ..listing("old", "iPhone 13", "400 €") }.html(),
+        ];
+        let html = format!(r#"<html><body><ul id="srchrslt-adtable">{}</ul></body></html>"#, items.concat());
+        let mut cfg = ModelConfig::permissive("iphone 13");
+        cfg.match_keywords = vec!["iphone 13".to_string()];
+        cfg.exclude_keywords = vec!["hülle".to_string()];
+        cfg.max_price = 1000.0;
+        cfg.allowed_locations = vec!["berlin".to_string()];
+        cfg.max_age_hours = Some(24);
+
+        let (ids, report) = parsed_ids(&html, &cfg);
+        assert_eq!(ids, vec!["ok"]);
+        assert_eq!(
+            report,
+            ParseReport {
+                total_items: 10,
+                skipped_no_title: 1,
+                skipped_no_price: 1,
+                skipped_no_id: 1,
+                skipped_duplicate: 1,
+                skipped_price_filter: 1,
+                skipped_keyword: 1,
+                skipped_excluded: 1,
+                skipped_location: 1,
+                skipped_age: 1,
+            }
+        );
+        assert_eq!(report.skipped(), 9);
+        assert!(report.to_string().starts_with("10 items, 9 skipped (no title: 1, no price: 1"), "{}", report);
+    }
+
+    #[test]
+    fn other_number_formats_map_onto_the_german_one() {
+        assert_eq!(parse_localized_price("CHF 1'250.50", NumberFormat::Swiss), Some(1250.5));
+        assert_eq!(parse_localized_price("CHF 1’250", NumberFormat::Swiss), Some(1250.0));
+        assert_eq!(parse_localized_price("$1,250.50", NumberFormat::English), Some(1250.5));
+        assert_eq!(parse_localized_price("£ 999", NumberFormat::English), Some(999.0));
+    }
+
+    #[test]
+    fn the_parser_reads_prices_in_its_number_format() {
+        let html = search_page(&[Listing::new("1", "iPhone 13", "CHF 1'250.50 VB"), Listing::new("2", "iPhone 13", "CHF 480")]);
+        let (offers, _) = KleinanzeigenParser::new()
+            .unwrap()
+            .with_number_format(NumberFormat::Swiss)
+            .parse_filtered(&html, &ModelConfig::permissive("iphone 13"))
+            .unwrap();
+        let prices: Vec<(f64, bool)> = offers.iter().map(|o| (o.price, o.negotiable)).collect();
+        assert_eq!(prices, vec![(1250.5, true), (480.0, false)]);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,133 @@
+use crate::model::ScraperError;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Per-model failure state.
+#[derive(Debug, Default)]
+struct ModelCircuit {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+}
+
+/// Backs a model off after too many consecutive HTTP failures or block pages.
+/// After `threshold` such failures in a row the circuit opens and the model is skipped until
+/// `cooldown` has passed; the next attempt then either closes it (success) or re-opens it (failure).
+#[derive(Debug, Default)]
+pub struct CircuitBreaker {
+    circuits: Mutex<HashMap<String, ModelCircuit>>,
+}
+
+impl CircuitBreaker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the remaining cooldown when the model's circuit is open, `None` when it may be scraped.
+    pub fn open_for(&self, model: &str) -> Option<Duration> {
+        let circuits = self.circuits.lock().unwrap_or_else(|e| e.into_inner());
+        let open_until = circuits.get(model)?.open_until?;
+        open_until.checked_duration_since(Instant::now())
+    }
+
+    /// Records a fetch result for `model`. Only `HttpError` and `Blocked` count as failures;
+    /// a success resets the counter, other errors leave it unchanged.
+    pub fn record(&self, model: &str, result: Result<(), &ScraperError>, threshold: u32, cooldown: Duration) {
+        let mut circuits = self.circuits.lock().unwrap_or_else(|e| e.into_inner());
+        let circuit = circuits.entry(model.to_string()).or_default();
+        match result {
+            Ok(()) => {
+                if circuit.open_until.take().is_some() || circuit.consecutive_failures >= threshold {
+                    info!("🟢 Circuit closed for {}: fetch succeeded", model);
+                }
+                circuit.consecutive_failures = 0;
+            }
+            Err(ScraperError::HttpError(_) | ScraperError::Blocked) => {
+                circuit.consecutive_failures += 1;
+                if circuit.consecutive_failures >= threshold {
+                    circuit.open_until = Some(Instant::now() + cooldown);
+                    warn!(
+                        "🔴 Circuit open for {} after {} consecutive failures, backing off for {}s",
+                        model,
+                        circuit.consecutive_failures,
+                        cooldown.as_secs()
+                    );
+                }
+            }
+            Err(_) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: Duration = Duration::from_secs(3600);
+
+    fn fail(breaker: &CircuitBreaker, model: &str, times: usize) {
+        for _ in 0..times {
+            breaker.record(model, Err(&ScraperError::HttpError("timeout".into())), 3, HOUR);
+        }
+    }
+
+    #[test]
+    fn opens_after_threshold_consecutive_failures() {
+        let breaker = CircuitBreaker::new();
+        fail(&breaker, "iphone", 2);
+        assert_eq!(breaker.open_for("iphone"), None);
+
+        breaker.record("iphone", Err(&ScraperError::Blocked), 3, HOUR);
+        let remaining = breaker.open_for("iphone").unwrap();
+        assert!(remaining > HOUR - Duration::from_secs(5) && remaining <= HOUR, "{:?}", remaining);
+        // Other models are unaffected
+        assert_eq!(breaker.open_for("pixel"), None);
+    }
+
+    #[test]
+    fn a_success_resets_the_count() {
+        let breaker = CircuitBreaker::new();
+        fail(&breaker, "iphone", 2);
+        breaker.record("iphone", Ok(()), 3, HOUR);
+        fail(&breaker, "iphone", 2);
+        assert_eq!(breaker.open_for("iphone"), None);
+        fail(&breaker, "iphone", 1);
+        assert!(breaker.open_for("iphone").is_some());
+    }
+
+    #[test]
+    fn only_http_errors_and_block_pages_count() {
+        let breaker = CircuitBreaker::new();
+        fail(&breaker, "iphone", 2);
+        for _ in 0..5 {
+            breaker.record("iphone", Err(&ScraperError::InvalidResponse("HTTP 404".into())), 3, HOUR);
+            breaker.record("iphone", Err(&ScraperError::HtmlParseError("no listings".into())), 3, HOUR);
+        }
+        assert_eq!(breaker.open_for("iphone"), None);
+        // ...and they don't reset the count either
+        fail(&breaker, "iphone", 1);
+        assert!(breaker.open_for("iphone").is_some());
+    }
+
+    #[test]
+    fn a_failure_after_the_cooldown_reopens_and_a_success_closes() {
+        let breaker = CircuitBreaker::new();
+        let cooldown = Duration::from_millis(20);
+        for _ in 0..3 {
+            breaker.record("iphone", Err(&ScraperError::Blocked), 3, cooldown);
+        }
+        assert!(breaker.open_for("iphone").is_some());
+        std::thread::sleep(Duration::from_millis(40));
+        assert_eq!(breaker.open_for("iphone"), None);
+
+        // The trial attempt fails: open again right away
+        breaker.record("iphone", Err(&ScraperError::Blocked), 3, HOUR);
+        assert!(breaker.open_for("iphone").is_some());
+
+        breaker.record("iphone", Ok(()), 3, HOUR);
+        assert_eq!(breaker.open_for("iphone"), None);
+        fail(&breaker, "iphone", 2);
+        assert_eq!(breaker.open_for("iphone"), None);
+    }
+}
@@ -0,0 +1,52 @@
+use crate::config::{Condition, ModelConfig};
+use crate::model::ScrapeRequest;
+use scraper::Html;
+
+/// Search filters for a single model, independent of the marketplace.
+#[derive(Debug, Clone, Default)]
+pub struct SearchFilters {
+    pub category_id: String,
+    pub min_price: f64,
+    pub max_price: f64,
+    pub zip_code: Option<String>,
+    pub radius_km: Option<u32>,
+    pub only_private: bool,
+    pub condition: Option<Condition>,
+}
+
+impl SearchFilters {
+    pub fn from_model(cfg: &ModelConfig) -> Self {
+        Self {
+            category_id: cfg.category_id.clone(),
+            min_price: cfg.min_price,
+            max_price: cfg.max_price,
+            zip_code: cfg.zip_code.clone(),
+            radius_km: cfg.radius_km,
+            only_private: cfg.only_private,
+            condition: cfg.condition,
+        }
+    }
+}
+
+/// Site-specific parts of scraping: how search URLs look and how result pages are recognized.
+/// `ScraperImpl` drives the fetch loop and delegates everything marketplace-specific here.
+pub trait MarketplaceDriver: Send + Sync {
+    /// Short name used in logs.
+    fn name(&self) -> &'static str;
+
+    /// Builds the search URL for the given page (1-based).
+    fn build_url(&self, req: &ScrapeRequest, filters: &SearchFilters, page: usize) -> String;
+
+    /// Number of listings on a result page.
+    fn count_items(&self, doc: &Html) -> usize;
+
+    /// Absolute URL of the "next page" link on a result page, `None` on the last page
+    /// or when the page has no pagination markup.
+    fn next_page_url(&self, doc: &Html) -> Option<String>;
+
+    /// Id of the first listing on a result page, used to detect that pagination wrapped around.
+    fn first_item_id(&self, doc: &Html) -> Option<String>;
+
+    /// Returns true if the HTML is a captcha / access-denied page rather than search results.
+    fn is_blocked(&self, html: &str) -> bool;
+}
@@ -0,0 +1,238 @@
+use crate::model::ScrapeRequest;
+use crate::scraper::driver::{MarketplaceDriver, SearchFilters};
+use scraper::{Html, Selector};
+
+/// Origin for search URLs and relative pagination links.
+const BASE_URL: &str = "https://www.kleinanzeigen.de";
+
+/// Text fragments that only appear on captcha / access-denied interstitials.
+const BLOCK_MARKERS: [&str; 5] = [
+    "Bitte bestätige",
+    "Zugriff verweigert",
+    "captcha-form",
+    "g-recaptcha",
+    "geetest",
+];
+
+/// Driver for kleinanzeigen.de.
+pub struct KleinanzeigenDriver {
+    item_selector: Selector,
+    ad_id_selector: Selector,
+    next_page_selector: Selector,
+}
+
+impl KleinanzeigenDriver {
+    pub fn new() -> Self {
+        Self {
+            item_selector: Selector::parse("li.ad-listitem").unwrap(),
+            ad_id_selector: Selector::parse("article.aditem").unwrap(),
+            // The "nächste Seite" arrow of the pagination bar.
+            next_page_selector: Selector::parse(".pagination a.pagination-next, a[title='Nächste']").unwrap(),
+        }
+    }
+}
+
+impl MarketplaceDriver for KleinanzeigenDriver {
+    fn name(&self) -> &'static str {
+        "kleinanzeigen"
+    }
+
+    /// Builds the URL for the request.
+    /// Optional filter segments are placed after `s-` in this order, followed by the query and category:
+    ///   https://www.kleinanzeigen.de/s-ort:{zip_code}/radius:{radius_km}/anbieter:privat/zustand:{condition}/preis:{min_price}:{max_price}/seite:{page}/{query}/{category_id}
+    /// The location segments are only added when `zip_code` is set (radius requires a location),
+    /// `anbieter:privat` when `only_private` is set, `zustand:` when a condition is configured,
+    /// the price segment when min_price > 0.0 or max_price > 0.0, and the page segment from page 2 on.
+    /// Without any filters the basic form https://www.kleinanzeigen.de/s-{query}/{category_id} is used.
+    fn build_url(&self, req: &ScrapeRequest, filters: &SearchFilters, page: usize) -> String {
+        let kebab_query = req.query.to_lowercase().replace(" ", "-");
+        let mut segments = Vec::new();
+
+        if let Some(zip_code) = filters.zip_code.as_deref().filter(|z| !z.is_empty()) {
+            segments.push(format!("ort:{}", zip_code));
+            if let Some(radius) = filters.radius_km {
+                segments.push(format!("radius:{}", radius));
+            }
+        }
+        if filters.only_private {
+            segments.push("anbieter:privat".to_string());
+        }
+        if let Some(condition) = filters.condition {
+            segments.push(format!("zustand:{}", condition.as_url_value()));
+        }
+        if filters.min_price > 0.0 || filters.max_price > 0.0 {
+            segments.push(format!("preis:{}:{}", filters.min_price, filters.max_price));
+        }
+        if page > 1 {
+            segments.push(format!("seite:{}", page));
+        }
+        segments.push(kebab_query);
+        segments.push(filters.category_id.clone());
+
+        format!("{}/s-{}", BASE_URL, segments.join("/"))
+    }
+
+    fn count_items(&self, doc: &Html) -> usize {
+        doc.select(&self.item_selector).count()
+    }
+
+    fn next_page_url(&self, doc: &Html) -> Option<String> {
+        let href = doc
+            .select(&self.next_page_selector)
+            .filter_map(|a| a.value().attr("href"))
+            .map(str::trim)
+            .find(|href| !href.is_empty())?;
+        if href.starts_with("http") {
+            Some(href.to_string())
+        } else {
+            Some(format!("{}/{}", BASE_URL, href.trim_start_matches('/')))
+        }
+    }
+
+    fn first_item_id(&self, doc: &Html) -> Option<String> {
+        doc.select(&self.ad_id_selector)
+            .next()
+            .and_then(|n| n.value().attr("data-adid"))
+            .map(|s| s.to_string())
+    }
+
+    fn is_blocked(&self, html: &str) -> bool {
+        BLOCK_MARKERS.iter().any(|marker| html.contains(marker))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Condition, ModelConfig};
+
+    fn url(filters: &SearchFilters, page: usize) -> String {
+        let req = ScrapeRequest { query: "iPhone 13 Pro".to_string(), category_id: "k0c173".to_string() };
+        KleinanzeigenDriver::new().build_url(&req, filters, page)
+    }
+
+    fn filters() -> SearchFilters {
+        SearchFilters { category_id: "k0c173".to_string(), ..SearchFilters::default() }
+    }
+
+    #[test]
+    fn url_without_filters() {
+        assert_eq!(url(&filters(), 1), "https://www.kleinanzeigen.de/s-iphone-13-pro/k0c173");
+        assert_eq!(url(&filters(), 3), "https://www.kleinanzeigen.de/s-seite:3/iphone-13-pro/k0c173");
+    }
+
+    #[test]
+    fn url_with_price_range() {
+        let range = SearchFilters { min_price: 100.0, max_price: 899.5, ..filters() };
+        assert_eq!(url(&range, 1), "https://www.kleinanzeigen.de/s-preis:100:899.5/iphone-13-pro/k0c173");
+        assert_eq!(url(&range, 2), "https://www.kleinanzeigen.de/s-preis:100:899.5/seite:2/iphone-13-pro/k0c173");
+
+        let only_max = SearchFilters { max_price: 500.0, ..filters() };
+        assert_eq!(url(&only_max, 1), "https://www.kleinanzeigen.de/s-preis:0:500/iphone-13-pro/k0c173");
+    }
+
+    #[test]
+    fn url_with_location_and_radius() {
+        let berlin = SearchFilters { zip_code: Some("10115".to_string()), radius_km: Some(50), ..filters() };
+        assert_eq!(url(&berlin, 1), "https://www.kleinanzeigen.de/s-ort:10115/radius:50/iphone-13-pro/k0c173");
+
+        let everything = SearchFilters { min_price: 100.0, max_price: 900.0, ..berlin };
+        assert_eq!(
+            url(&everything, 4),
+            "https://www.kleinanzeigen.de/s-ort:10115/radius:50/preis:100:900/seite:4/iphone-13-pro/k0c173"
+        );
+    }
+
+    #[test]
+    fn radius_needs_a_location() {
+        let radius_only = SearchFilters { radius_km: Some(50), ..filters() };
+        assert_eq!(url(&radius_only, 1), "https://www.kleinanzeigen.de/s-iphone-13-pro/k0c173");
+        let empty_zip = SearchFilters { zip_code: Some(String::new()), radius_km: Some(50), ..filters() };
+        assert_eq!(url(&empty_zip, 1), "https://www.kleinanzeigen.de/s-iphone-13-pro/k0c173");
+        let zip_only = SearchFilters { zip_code: Some("80331".to_string()), ..filters() };
+        assert_eq!(url(&zip_only, 1), "https://www.kleinanzeigen.de/s-ort:80331/iphone-13-pro/k0c173");
+    }
+
+    #[test]
+    fn url_with_seller_type_and_condition() {
+        let private = SearchFilters { only_private: true, ..filters() };
+        assert_eq!(url(&private, 1), "https://www.kleinanzeigen.de/s-anbieter:privat/iphone-13-pro/k0c173");
+
+        let conditions = [
+            (Condition::New, "new"),
+            (Condition::LikeNew, "like_new"),
+            (Condition::Ok, "ok"),
+            (Condition::Alright, "alright"),
+            (Condition::Defect, "defect"),
+        ];
+        for (condition, value) in conditions {
+            let filters = SearchFilters { condition: Some(condition), ..filters() };
+            assert_eq!(url(&filters, 1), format!("https://www.kleinanzeigen.de/s-zustand:{}/iphone-13-pro/k0c173", value));
+        }
+    }
+
+    #[test]
+    fn all_filters_come_in_a_fixed_order() {
+        let filters = SearchFilters {
+            zip_code: Some("10115".to_string()),
+            radius_km: Some(20),
+            only_private: true,
+            condition: Some(Condition::LikeNew),
+            min_price: 50.0,
+            max_price: 400.0,
+            ..filters()
+        };
+        assert_eq!(
+            url(&filters, 2),
+            "https://www.kleinanzeigen.de/s-ort:10115/radius:20/anbieter:privat/zustand:like_new/preis:50:400/seite:2/iphone-13-pro/k0c173"
+        );
+    }
+
+    #[test]
+    fn filters_come_from_the_model_config() {
+        let mut model = ModelConfig::permissive("iphone 13");
+        model.category_id = "k0c173".to_string();
+        (model.min_price, model.max_price) = (100.0, 900.0);
+        model.only_private = true;
+        model.condition = Some(Condition::Ok);
+        assert_eq!(
+            url(&SearchFilters::from_model(&model), 1),
+            "https://www.kleinanzeigen.de/s-anbieter:privat/zustand:ok/preis:100:900/iphone-13-pro/k0c173"
+        );
+    }
+
+    fn next_page(html: &str) -> Option<String> {
+        KleinanzeigenDriver::new().next_page_url(&Html::parse_document(html))
+    }
+
+    #[test]
+    fn next_page_link_is_read_from_the_pagination_bar() {
+        let bar = r#"<div class="pagination">
+            <a class="pagination-prev" href="/s-seite:1/iphone-13/k0c173">zurück</a>
+            <span class="pagination-current">2</span>
+            <a class="pagination-page" href="/s-seite:3/iphone-13/k0c173">3</a>
+            <a class="pagination-next" title="Nächste" href="/s-seite:3/iphone-13/k0c173"></a>
+        </div>"#;
+        assert_eq!(next_page(bar).as_deref(), Some("https://www.kleinanzeigen.de/s-seite:3/iphone-13/k0c173"));
+
+        let absolute = r#"<div class="pagination">
+            <a class="pagination-next" href="https://www.kleinanzeigen.de/s-iphone-13/seite:4/k0c173"></a>
+        </div>"#;
+        assert_eq!(next_page(absolute).as_deref(), Some("https://www.kleinanzeigen.de/s-iphone-13/seite:4/k0c173"));
+
+        // Older markup without the class, only the title
+        let titled = r#"<nav><a title="Nächste" href=" s-seite:2/iphone-13/k0c173 "></a></nav>"#;
+        assert_eq!(next_page(titled).as_deref(), Some("https://www.kleinanzeigen.de/s-seite:2/iphone-13/k0c173"));
+    }
+
+    #[test]
+    fn last_pages_and_pages_without_pagination_have_no_next_link() {
+        let last = r#"<div class="pagination">
+            <a class="pagination-prev" href="/s-seite:2/iphone-13/k0c173">zurück</a>
+            <span class="pagination-current">3</span>
+        </div>"#;
+        assert_eq!(next_page(last), None);
+        assert_eq!(next_page(r#"<div class="pagination"><a class="pagination-next" href=""></a></div>"#), None);
+        assert_eq!(next_page("<html><body><ul></ul></body></html>"), None);
+    }
+}
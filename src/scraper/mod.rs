@@ -1,5 +1,9 @@
+pub mod circuit_breaker;
+pub mod driver;
 pub mod fetcher;
+pub mod kleinanzeigen;
 pub mod traits;
 
+pub use circuit_breaker::CircuitBreaker;
 pub use fetcher::ScraperImpl;
-pub use traits::Scraper;
+pub use traits::Scraper;
\ No newline at end of file
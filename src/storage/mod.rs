@@ -1,4 +1,5 @@
 // Storage abstraction
+pub mod pool;
 pub mod sqlite;
 
 pub use sqlite::SqliteStorage;
\ No newline at end of file
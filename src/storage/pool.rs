@@ -0,0 +1,186 @@
+use crate::model::StorageError;
+use rusqlite::Connection;
+use std::ops::Deref;
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// Сколько соединений пул держит открытыми одновременно
+const DEFAULT_MAX_SIZE: usize = 8;
+/// Сколько ждать свободного соединения, когда все заняты
+const DEFAULT_CHECKOUT_TIMEOUT: Duration = Duration::from_secs(30);
+/// Сколько ждать снятия блокировки записи другим соединением
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Простой пул соединений SQLite: выдаёт свободное соединение или открывает новое, пока их меньше
+/// `max_size`, иначе ждёт возврата занятого. Все соединения работают в режиме WAL,
+/// поэтому чтения не блокируются записью.
+///
+/// Свой пул вместо r2d2/r2d2_sqlite: этих крейтов нет среди зависимостей проекта, а нужно от пула
+/// немногое — ограниченное число соединений с одинаковыми PRAGMA. Методы хранилища берут
+/// не больше одного соединения за раз, поэтому ожидание в `get` не может зациклиться само на себя.
+pub struct ConnectionPool {
+    db_path: String,
+    state: Mutex<PoolState>,
+    /// Сигнал о возвращённом (или так и не открытом) соединении
+    available: Condvar,
+    max_size: usize,
+    checkout_timeout: Duration,
+}
+
+struct PoolState {
+    idle: Vec<Connection>,
+    /// Открытые соединения: свободные и выданные
+    open: usize,
+}
+
+impl ConnectionPool {
+    /// Создаёт пул и сразу открывает первое соединение, чтобы ошибки пути проявились при старте
+    pub fn new(db_path: &str) -> Result<Self, StorageError> {
+        Self::with_limits(db_path, DEFAULT_MAX_SIZE, DEFAULT_CHECKOUT_TIMEOUT)
+    }
+
+    /// Как `new`, но не больше `max_size` соединений и ожидание свободного не дольше `checkout_timeout`
+    pub fn with_limits(db_path: &str, max_size: usize, checkout_timeout: Duration) -> Result<Self, StorageError> {
+        let pool = Self {
+            db_path: db_path.to_string(),
+            state: Mutex::new(PoolState { idle: Vec::new(), open: 1 }),
+            available: Condvar::new(),
+            max_size: max_size.max(1),
+            checkout_timeout,
+        };
+        let conn = pool.open()?;
+        pool.release(conn);
+        Ok(pool)
+    }
+
+    /// Берёт соединение из пула: свободное, новое (если лимит не исчерпан) или возвращённое
+    /// другим потоком в течение `checkout_timeout`
+    pub fn get(&self) -> Result<PooledConnection<'_>, StorageError> {
+        let deadline = Instant::now() + self.checkout_timeout;
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        loop {
+            if let Some(conn) = state.idle.pop() {
+                return Ok(PooledConnection { pool: self, conn: Some(conn) });
+            }
+            if state.open < self.max_size {
+                state.open += 1;
+                drop(state);
+                return match self.open() {
+                    Ok(conn) => Ok(PooledConnection { pool: self, conn: Some(conn) }),
+                    Err(e) => {
+                        self.state.lock().unwrap_or_else(|e| e.into_inner()).open -= 1;
+                        self.available.notify_one();
+                        Err(e)
+                    }
+                };
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(StorageError::DatabaseError(format!(
+                    "все {} соединений заняты дольше {:?}",
+                    self.max_size, self.checkout_timeout
+                )));
+            }
+            state = self
+                .available
+                .wait_timeout(state, remaining)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+        }
+    }
+
+    fn open(&self) -> Result<Connection, StorageError> {
+        let conn = Connection::open(&self.db_path)?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        conn.query_row("PRAGMA journal_mode = WAL", [], |_| Ok(()))?;
+        conn.execute_batch("PRAGMA synchronous = NORMAL;")?;
+        Ok(conn)
+    }
+
+    fn release(&self, conn: Connection) {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).idle.push(conn);
+        self.available.notify_one();
+    }
+}
+
+/// Соединение, взятое из пула; возвращается в пул при drop
+pub struct PooledConnection<'a> {
+    pool: &'a ConnectionPool,
+    conn: Option<Connection>,
+}
+
+impl Deref for PooledConnection<'_> {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        self.conn.as_ref().expect("pooled connection used after release")
+    }
+}
+
+impl Drop for PooledConnection<'_> {
+    fn drop(&mut self) {
+        if let Some(conn) = self.conn.take() {
+            self.pool.release(conn);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempFile;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn open_count(pool: &ConnectionPool) -> usize {
+        pool.state.lock().unwrap().open
+    }
+
+    #[test]
+    fn reuses_released_connections() {
+        let file = TempFile::new("db");
+        let pool = ConnectionPool::with_limits(file.path(), 4, Duration::from_secs(1)).unwrap();
+        for _ in 0..10 {
+            let conn = pool.get().unwrap();
+            conn.execute_batch("SELECT 1").unwrap();
+        }
+        assert_eq!(open_count(&pool), 1);
+    }
+
+    #[test]
+    fn never_opens_more_than_max_size() {
+        let file = TempFile::new("db");
+        let pool = Arc::new(ConnectionPool::with_limits(file.path(), 2, Duration::from_secs(5)).unwrap());
+        let in_use = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+
+        let workers: Vec<_> = (0..8)
+            .map(|_| {
+                let (pool, in_use, peak) = (pool.clone(), in_use.clone(), peak.clone());
+                std::thread::spawn(move || {
+                    let _conn = pool.get().unwrap();
+                    let now = in_use.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    std::thread::sleep(Duration::from_millis(20));
+                    in_use.fetch_sub(1, Ordering::SeqCst);
+                })
+            })
+            .collect();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+        assert_eq!(open_count(&pool), 2);
+    }
+
+    #[test]
+    fn checkout_times_out_when_every_connection_is_busy() {
+        let file = TempFile::new("db");
+        let pool = ConnectionPool::with_limits(file.path(), 1, Duration::from_millis(50)).unwrap();
+        let held = pool.get().unwrap();
+        assert!(matches!(pool.get(), Err(StorageError::DatabaseError(_))));
+        drop(held);
+        assert!(pool.get().is_ok());
+    }
+}
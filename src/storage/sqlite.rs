@@ -1,16 +1,22 @@
-use crate::model::{ModelStats, Offer, StorageError};
-use chrono::{DateTime, Duration, Utc, NaiveDateTime, TimeZone};
+use crate::model::{ExportFormat, MarketSnapshot, ModelStats, Offer, PriceKind, PriceObservation, ScrapeRun, SortKey, StorageError};
+use crate::image_hash::hamming_distance;
+use chrono::{DateTime, Duration, Utc};
 use rusqlite::{params, Connection, Row};
-use std::collections::HashMap;
+use super::pool::ConnectionPool;
+use std::collections::{HashMap, HashSet};
+use tracing::warn;
 
 pub struct SqliteStorage {
-    conn: Connection,
+    pool: ConnectionPool,
+    /// Доступен ли полнотекстовый индекс FTS5 (иначе поиск идёт через LIKE)
+    fts_enabled: bool,
 }
 
 impl SqliteStorage {
-    /// Создаёт новое хранилище, открывая соединение к БД и выполняя миграции
+    /// Создаёт новое хранилище, открывая пул соединений к БД и выполняя миграции
     pub fn new(db_path: &str) -> Result<Self, StorageError> {
-        let conn = Connection::open(db_path)?;
+        let pool = ConnectionPool::new(db_path)?;
+        let conn = pool.get()?;
 
         conn.execute_batch(
             "
@@ -31,12 +37,52 @@ impl SqliteStorage {
                 notified_at TEXT NOT NULL
             );
 
+            CREATE TABLE IF NOT EXISTS offer_price_history (
+                offer_id TEXT NOT NULL,
+                price REAL NOT NULL,
+                observed_at TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS subscriptions (
+                chat_id INTEGER NOT NULL,
+                model TEXT NOT NULL,
+                PRIMARY KEY (chat_id, model)
+            );
+
             CREATE TABLE IF NOT EXISTS model_stats (
                 model TEXT PRIMARY KEY,
                 avg_price REAL NOT NULL,
                 std_dev REAL NOT NULL,
                 last_updated TEXT NOT NULL
             );
+
+            CREATE TABLE IF NOT EXISTS scrape_runs (
+                model TEXT NOT NULL,
+                started_at TEXT NOT NULL,
+                duration_ms INTEGER NOT NULL,
+                offer_count INTEGER NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS disabled_models (
+                model TEXT PRIMARY KEY,
+                disabled_at TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS ignored_offers (
+                offer_id TEXT PRIMARY KEY,
+                ignored_at TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS best_deals (
+                model TEXT PRIMARY KEY,
+                offer_id TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS meta (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL
+            );
             "
         )?;
 
@@ -47,8 +93,82 @@ impl SqliteStorage {
         Self::migrate_add_column_if_missing(&conn, "offers", "user_id", "TEXT")?;
         Self::migrate_add_column_if_missing(&conn, "offers", "user_name", "TEXT")?;
         Self::migrate_add_column_if_missing(&conn, "offers", "user_url", "TEXT")?;
+        Self::migrate_add_column_if_missing(&conn, "offers", "negotiable", "INTEGER NOT NULL DEFAULT 0")?;
+        Self::migrate_add_column_if_missing(&conn, "offers", "price_kind", "TEXT NOT NULL DEFAULT 'fixed'")?;
+        Self::migrate_add_column_if_missing(&conn, "offers", "disappeared_at", "TEXT")?;
+        Self::migrate_add_column_if_missing(&conn, "offers", "image_url", "TEXT")?;
+        Self::migrate_add_column_if_missing(&conn, "offers", "shipping_available", "INTEGER NOT NULL DEFAULT 0")?;
+        // dHash миниатюры оффера (вычисляется только при image_dedup)
+        Self::migrate_add_column_if_missing(&conn, "offers", "image_hash", "INTEGER")?;
+        // Момент, когда оффер был замечен впервые (fetched_at — последний раз)
+        Self::migrate_add_column_if_missing(&conn, "offers", "first_seen_at", "TEXT")?;
+        conn.execute(
+            "UPDATE offers SET first_seen_at = COALESCE(
+                (SELECT MIN(observed_at) FROM offer_price_history WHERE offer_id = offers.id),
+                fetched_at
+             )
+             WHERE first_seen_at IS NULL",
+            [],
+        )?;
+        // Робастные статистики модели
+        Self::migrate_add_column_if_missing(&conn, "model_stats", "median_price", "REAL NOT NULL DEFAULT 0")?;
+        Self::migrate_add_column_if_missing(&conn, "model_stats", "p25", "REAL NOT NULL DEFAULT 0")?;
+        Self::migrate_add_column_if_missing(&conn, "model_stats", "p75", "REAL NOT NULL DEFAULT 0")?;
+
+        // Отпечаток содержимого оффера, чтобы узнавать перевыложенные объявления с новым id
+        Self::migrate_add_column_if_missing(&conn, "notified", "content_hash", "TEXT")?;
+        // Уведомление о настоящей сделке (а не подавленный репост, кнопка «Просмотрено» или самый дешёвый оффер)
+        Self::migrate_add_column_if_missing(&conn, "notified", "is_deal", "INTEGER NOT NULL DEFAULT 0")?;
+
+        // notified_at раньше писался через datetime('now') ("%Y-%m-%d %H:%M:%S", UTC) — переводим в RFC3339
+        conn.execute(
+            "UPDATE notified SET notified_at = replace(notified_at, ' ', 'T') || '+00:00'
+             WHERE length(notified_at) = 19 AND substr(notified_at, 11, 1) = ' '",
+            [],
+        )?;
+
+        // Индексы для горячих запросов: выборки по модели, по времени и очистка уведомлений
+        conn.execute_batch(
+            "
+            CREATE INDEX IF NOT EXISTS idx_offers_model ON offers(model);
+            CREATE INDEX IF NOT EXISTS idx_offers_fetched_at ON offers(fetched_at);
+            CREATE INDEX IF NOT EXISTS idx_notified_notified_at ON notified(notified_at);
+            CREATE INDEX IF NOT EXISTS idx_notified_content_hash ON notified(content_hash);
+            CREATE INDEX IF NOT EXISTS idx_scrape_runs_model_started ON scrape_runs(model, started_at);
+            "
+        )?;
+
+        let fts_enabled = Self::init_fts(&conn);
+        drop(conn);
+
+        Ok(Self { pool, fts_enabled })
+    }
+
+    /// Создаёт полнотекстовый индекс offers_fts и дозаполняет его существующими офферами.
+    /// Возвращает false, если SQLite собран без FTS5.
+    fn init_fts(conn: &Connection) -> bool {
+        let result = conn.execute_batch(
+            "
+            CREATE VIRTUAL TABLE IF NOT EXISTS offers_fts USING fts5(id UNINDEXED, title, description);
+            INSERT INTO offers_fts (id, title, description)
+                SELECT id, title, description FROM offers
+                WHERE id NOT IN (SELECT id FROM offers_fts);
+            ",
+        );
+        match result {
+            Ok(()) => true,
+            Err(e) => {
+                warn!("FTS5 unavailable, /search falls back to LIKE: {}", e);
+                false
+            }
+        }
+    }
 
-        Ok(Self { conn })
+    /// Сбрасывает данные на диск перед завершением работы (checkpoint WAL, если он включён)
+    pub fn flush(&self) -> Result<(), StorageError> {
+        let conn = self.pool.get()?;
+        conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+        Ok(())
     }
 
     /// Проверяет наличие столбца и в случае отсутствия добавляет его в таблицу
@@ -72,14 +192,45 @@ impl SqliteStorage {
     }
 
     /// Сохраняет (вставляет или обновляет) оффер в таблице offers.
-    pub fn save_offer(&self, offer: &Offer) -> Result<(), StorageError> {
-        self.conn.execute(
-            "INSERT OR REPLACE INTO offers (
+    /// При обновлении first_seen_at сохраняет самое раннее значение, fetched_at — время последнего
+    /// сбора, а пометка об исчезновении снимается.
+    /// Возвращает предыдущую сохранённую цену оффера, если он уже был в базе.
+    pub fn save_offer(&self, offer: &Offer) -> Result<Option<f64>, StorageError> {
+        let conn = self.pool.get()?;
+        let previous_price = {
+            let mut stmt = conn.prepare("SELECT price FROM offers WHERE id = ?1")?;
+            let mut rows = stmt.query(params![&offer.id])?;
+            match rows.next()? {
+                Some(row) => Some(row.get::<_, f64>(0)?),
+                None => None,
+            }
+        };
+        conn.execute(
+            "INSERT INTO offers (
                 id, title, price, model, link, 
                 posted_at, fetched_at, location, description,
-                user_id, user_name, user_url
+                user_id, user_name, user_url, negotiable, price_kind, image_url, first_seen_at,
+                shipping_available
             )
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?7, ?16)
+            ON CONFLICT(id) DO UPDATE SET
+                title = excluded.title,
+                price = excluded.price,
+                model = excluded.model,
+                link = excluded.link,
+                posted_at = excluded.posted_at,
+                fetched_at = excluded.fetched_at,
+                location = excluded.location,
+                description = excluded.description,
+                user_id = excluded.user_id,
+                user_name = excluded.user_name,
+                user_url = excluded.user_url,
+                negotiable = excluded.negotiable,
+                price_kind = excluded.price_kind,
+                image_url = excluded.image_url,
+                shipping_available = excluded.shipping_available,
+                disappeared_at = NULL,
+                first_seen_at = COALESCE(MIN(offers.first_seen_at, excluded.first_seen_at), excluded.first_seen_at)",
             params![
                 &offer.id,
                 &offer.title,
@@ -93,15 +244,125 @@ impl SqliteStorage {
                 &offer.user_id,
                 &offer.user_name,
                 &offer.user_url,
+                &offer.negotiable,
+                offer.price_kind.as_str(),
+                &offer.image_url,
+                &offer.shipping_available,
             ],
         )?;
-        Ok(())
+        conn.execute(
+            "INSERT INTO offer_price_history (offer_id, price, observed_at) VALUES (?1, ?2, ?3)",
+            params![&offer.id, &offer.price, &offer.fetched_at.to_rfc3339()],
+        )?;
+        if self.fts_enabled {
+            conn.execute("DELETE FROM offers_fts WHERE id = ?1", params![&offer.id])?;
+            conn.execute(
+                "INSERT INTO offers_fts (id, title, description) VALUES (?1, ?2, ?3)",
+                params![&offer.id, &offer.title, &offer.description],
+            )?;
+        }
+        Ok(previous_price)
     }
 
-    /// Группирует офферы по идентификатору продавца для указанной модели
+    /// Ищет офферы (включая исчезнувшие) по словам в заголовке и описании.
+    /// Использует FTS5 с ранжированием bm25, если он доступен, иначе — LIKE по всей фразе.
+    pub fn search_offers(&self, query: &str, limit: usize) -> Result<Vec<Offer>, StorageError> {
+        let conn = self.pool.get()?;
+        let terms: Vec<String> = query
+            .split_whitespace()
+            .map(|t| format!("\"{}\"", t.replace('"', "\"\"")))
+            .collect();
+        if terms.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let (sql, pattern) = if self.fts_enabled {
+            (
+                "SELECT o.id, o.title, o.price, o.model, o.link, o.posted_at, o.fetched_at, o.location, o.description,
+                        o.user_id, o.user_name, o.user_url, o.negotiable, o.price_kind, o.image_url, o.shipping_available
+                 FROM offers_fts f JOIN offers o ON o.id = f.id
+                 WHERE offers_fts MATCH ?1 ORDER BY bm25(offers_fts) LIMIT ?2",
+                terms.join(" "),
+            )
+        } else {
+            (
+                "SELECT id, title, price, model, link, posted_at, fetched_at, location, description,
+                        user_id, user_name, user_url, negotiable, price_kind, image_url, shipping_available
+                 FROM offers WHERE title LIKE ?1 OR description LIKE ?1
+                 ORDER BY fetched_at DESC LIMIT ?2",
+                format!("%{}%", query.trim()),
+            )
+        };
+
+        let mut stmt = conn.prepare(sql)?;
+        let rows = stmt.query_map(params![pattern, limit as i64], |row| Self::map_offer(row, true))?;
+        let mut offers = Vec::new();
+        for offer in rows {
+            offers.push(offer?);
+        }
+        Ok(offers)
+    }
+
+    /// Возвращает историю цен оффера в хронологическом порядке
+    pub fn get_price_history(&self, offer_id: &str) -> Result<Vec<PriceObservation>, StorageError> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT offer_id, price, observed_at FROM offer_price_history
+             WHERE offer_id = ?1 ORDER BY observed_at ASC",
+        )?;
+        let rows = stmt.query_map(params![offer_id], Self::map_observation)?;
+        let mut history = Vec::new();
+        for row in rows {
+            history.push(row?);
+        }
+        Ok(history)
+    }
+
+    /// Возвращает среднюю наблюдаемую цену модели по дням (последние `days` дней), от старых к новым
+    pub fn get_daily_average_prices(&self, model: &str, days: u32) -> Result<Vec<(String, f64)>, StorageError> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT substr(h.observed_at, 1, 10) AS day, AVG(h.price)
+             FROM offer_price_history h
+             JOIN offers o ON o.id = h.offer_id
+             WHERE o.model = ?1 COLLATE NOCASE AND h.price > 0
+             GROUP BY day ORDER BY day DESC LIMIT ?2",
+        )?;
+        let rows = stmt.query_map(params![model, days], |row| {
+            let day: String = row.get(0)?;
+            let avg: f64 = row.get(1)?;
+            Ok((day, avg))
+        })?;
+        let mut result = Vec::new();
+        for row in rows {
+            result.push(row?);
+        }
+        result.reverse();
+        Ok(result)
+    }
+
+    /// Возвращает историю цен всех офферов модели в хронологическом порядке
+    pub fn get_price_history_for_model(&self, model: &str) -> Result<Vec<PriceObservation>, StorageError> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT h.offer_id, h.price, h.observed_at FROM offer_price_history h
+             JOIN offers o ON o.id = h.offer_id
+             WHERE o.model = ?1 ORDER BY h.observed_at ASC",
+        )?;
+        let rows = stmt.query_map(params![model], Self::map_observation)?;
+        let mut history = Vec::new();
+        for row in rows {
+            history.push(row?);
+        }
+        Ok(history)
+    }
+
+    /// Группирует активные офферы по идентификатору продавца для указанной модели
     pub fn group_offers_by_seller(&self, model: &str) -> Result<HashMap<String, usize>, StorageError> {
-        let mut stmt = self.conn.prepare(
-            "SELECT user_id, COUNT(*) FROM offers WHERE model = ?1 AND user_id IS NOT NULL GROUP BY user_id",
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT user_id, COUNT(*) FROM offers
+             WHERE model = ?1 AND user_id IS NOT NULL AND disappeared_at IS NULL GROUP BY user_id",
         )?;
 
         let rows = stmt.query_map(params![model], |row| {
@@ -119,62 +380,123 @@ impl SqliteStorage {
         Ok(result)
     }
 
-    /// Ищет вероятные репосты для указанной модели, основываясь на близости цен (< 10.0)
-    pub fn find_probable_reposts_for_model(&self, model: &str) -> Result<Vec<Offer>, StorageError> {
-        let mut stmt = self.conn.prepare(
-            "SELECT id, title, price, model, link, posted_at, fetched_at, location, description, user_id, user_name, user_url 
-             FROM offers WHERE model = ?1 AND user_id IS NOT NULL ORDER BY fetched_at DESC",
+    /// Ищет вероятные репосты для указанной модели: тот же заголовок и продавец, цена отличается
+    /// не больше чем на `price_window`. Оригиналом считается самый ранний оффер, уведомлённый
+    /// не раньше `since`. Возвращает карту «id репоста → id оригинала».
+    pub fn find_probable_reposts_for_model(
+        &self,
+        model: &str,
+        price_window: f64,
+        since: DateTime<Utc>,
+    ) -> Result<HashMap<String, String>, StorageError> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT o.id, o.title, o.price, o.model, o.link, o.posted_at, o.fetched_at, o.location, o.description,
+                    o.user_id, o.user_name, o.user_url, o.negotiable, o.price_kind, o.image_url, o.shipping_available,
+                    COALESCE(n.notified_at >= ?2, 0)
+             FROM offers o LEFT JOIN notified n ON n.offer_id = o.id
+             WHERE o.model = ?1 AND o.user_id IS NOT NULL ORDER BY o.posted_at ASC",
         )?;
 
-        let rows = stmt.query_map(params![model], |row| Self::map_offer(row, true))?;
+        let rows = stmt.query_map(params![model, since.to_rfc3339()], |row| {
+            Ok((Self::map_offer(row, true)?, row.get::<_, bool>(16)?))
+        })?;
 
-        let mut seen = HashMap::<(String, String), f64>::new();
-        let mut reposts = Vec::new();
+        let mut originals = HashMap::<(String, String), (String, f64)>::new();
+        let mut reposts = HashMap::new();
 
-        for offer in rows {
-            let offer = offer?;
+        for row in rows {
+            let (offer, recently_notified) = row?;
             let key = (offer.title.clone(), offer.user_id.clone().unwrap_or_default());
-            if let Some(prev_price) = seen.get(&key) {
-                if (offer.price - prev_price).abs() < 10.0 {
-                    reposts.push(offer);
+            if let Some((original_id, original_price)) = originals.get(&key) {
+                if (offer.price - original_price).abs() <= price_window {
+                    reposts.insert(offer.id, original_id.clone());
                 }
-            } else {
-                seen.insert(key, offer.price);
+            } else if recently_notified {
+                originals.insert(key, (offer.id, offer.price));
             }
         }
 
         Ok(reposts)
     }
 
-    /// Удаляет офферы для указанной модели, идентификаторы которых отсутствуют в текущем списке
-    pub fn delete_missing_offers_for_model(&self, model: &str, current_ids: &[String]) -> Result<(), StorageError> {
+    /// Помечает исчезнувшими (disappeared_at = сейчас) офферы модели, отсутствующие в текущем списке.
+    /// Сами записи сохраняются, чтобы анализ жизненного цикла видел реальное время жизни оффера.
+    pub fn mark_missing_offers_for_model(&self, model: &str, current_ids: &[String]) -> Result<(), StorageError> {
+        let conn = self.pool.get()?;
+        let now = Utc::now().to_rfc3339();
         if current_ids.is_empty() {
-            self.conn.execute("DELETE FROM offers WHERE model = ?1", params![model])?;
+            conn.execute(
+                "UPDATE offers SET disappeared_at = ?2 WHERE model = ?1 AND disappeared_at IS NULL",
+                params![model, now],
+            )?;
             return Ok(());
         }
 
         let placeholders = current_ids.iter().map(|_| "?").collect::<Vec<_>>().join(",");
         let sql = format!(
-            "DELETE FROM offers WHERE model = ?1 AND id NOT IN ({})",
+            "UPDATE offers SET disappeared_at = ?2 WHERE model = ?1 AND disappeared_at IS NULL AND id NOT IN ({})",
             placeholders
         );
-        let mut stmt = self.conn.prepare(&sql)?;
-        let mut params_vec = vec![model.to_string()];
+        let mut stmt = conn.prepare(&sql)?;
+        let mut params_vec = vec![model.to_string(), now];
         params_vec.extend(current_ids.iter().cloned());
         stmt.execute(rusqlite::params_from_iter(params_vec))?;
         Ok(())
     }
 
+    /// Окончательно удаляет офферы (вместе с историей цен), исчезнувшие или не встречавшиеся
+    /// на площадке дольше `days` дней. Возвращает количество удалённых офферов.
+    pub fn purge_offers_older_than(&self, days: u32) -> Result<usize, StorageError> {
+        let conn = self.pool.get()?;
+        let cutoff = (Utc::now() - Duration::days(days as i64)).to_rfc3339();
+        let stale = "SELECT id FROM offers WHERE COALESCE(disappeared_at, fetched_at) < ?1";
+        conn.execute(
+            &format!("DELETE FROM offer_price_history WHERE offer_id IN ({})", stale),
+            params![cutoff],
+        )?;
+        if self.fts_enabled {
+            conn.execute(&format!("DELETE FROM offers_fts WHERE id IN ({})", stale), params![cutoff])?;
+        }
+        let deleted = conn.execute(
+            "DELETE FROM offers WHERE COALESCE(disappeared_at, fetched_at) < ?1",
+            params![cutoff],
+        )?;
+        conn.execute("DELETE FROM scrape_runs WHERE started_at < ?1", params![cutoff])?;
+        // История цен, оставшаяся от офферов, удалённых ранее
+        conn.execute(
+            "DELETE FROM offer_price_history WHERE offer_id NOT IN (SELECT id FROM offers)",
+            [],
+        )?;
+        Ok(deleted)
+    }
+
+    /// Удаляет записи об уведомлениях старше `days` дней, а также записи об офферах,
+    /// которых больше нет в базе. Возвращает количество удалённых записей.
+    pub fn purge_notified_older_than(&self, days: u32) -> Result<usize, StorageError> {
+        let conn = self.pool.get()?;
+        let cutoff = (Utc::now() - Duration::days(days as i64)).to_rfc3339();
+        let deleted = conn.execute(
+            "DELETE FROM notified
+             WHERE notified_at < ?1
+                OR offer_id NOT IN (SELECT id FROM offers)",
+            params![cutoff],
+        )?;
+        Ok(deleted)
+    }
+
     /// Проверяет, было ли уже уведомление об оффере
     pub fn is_notified(&self, offer_id: &str) -> Result<bool, StorageError> {
-        let mut stmt = self.conn.prepare("SELECT 1 FROM notified WHERE offer_id = ?1")?;
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare("SELECT 1 FROM notified WHERE offer_id = ?1")?;
         let mut rows = stmt.query(params![offer_id])?;
         Ok(rows.next()?.is_some())
     }
 
     /// Возвращает true, если уведомление отсутствует или прошло более 24 часов с момента последнего уведомления
     pub fn should_notify(&self, offer_id: &str) -> Result<bool, StorageError> {
-        let mut stmt = self.conn.prepare("SELECT notified_at FROM notified WHERE offer_id = ?1")?;
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare("SELECT notified_at FROM notified WHERE offer_id = ?1")?;
         let mut rows = stmt.query(params![offer_id])?;
 
         if let Some(row) = rows.next()? {
@@ -183,10 +505,7 @@ impl SqliteStorage {
                 return Ok(true);
             }
 
-            // Ожидается формат, возвращаемый datetime('now') – "%Y-%m-%d %H:%M:%S"
-            let notified_at_naive = NaiveDateTime::parse_from_str(&notified_at_str, "%Y-%m-%d %H:%M:%S")
-                .map_err(|e| StorageError::DatabaseError(format!("Invalid datetime: {}", e)))?;
-            let notified_at: DateTime<Utc> = Utc.from_utc_datetime(&notified_at_naive);
+            let notified_at: DateTime<Utc> = notified_at_str.parse()?;
 
             Ok(Utc::now().signed_duration_since(notified_at) > Duration::hours(24))
         } else {
@@ -196,17 +515,287 @@ impl SqliteStorage {
 
     /// Отмечает, что уведомление для указанного оффера отправлено (с текущей датой-временем)
     pub fn mark_notified(&self, offer_id: &str) -> Result<(), StorageError> {
-        self.conn.execute(
-            "INSERT OR REPLACE INTO notified (offer_id, notified_at) VALUES (?1, datetime('now'))",
-            params![offer_id],
+        self.mark_notified_with_hash(offer_id, None)
+    }
+
+    /// Как `mark_notified`, но также сохраняет отпечаток содержимого оффера.
+    /// Без отпечатка ранее сохранённый остаётся на месте.
+    pub fn mark_notified_with_hash(&self, offer_id: &str, content_hash: Option<&str>) -> Result<(), StorageError> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            "INSERT INTO notified (offer_id, notified_at, content_hash) VALUES (?1, ?2, ?3)
+             ON CONFLICT(offer_id) DO UPDATE SET
+                notified_at = excluded.notified_at,
+                content_hash = COALESCE(excluded.content_hash, notified.content_hash)",
+            params![offer_id, Utc::now().to_rfc3339(), content_hash],
         )?;
         Ok(())
     }
 
+    /// Как `mark_notified_with_hash`, но отмечает уведомление как сделку — только такие считает дайджест
+    pub fn mark_deal_notified(&self, offer_id: &str, content_hash: &str) -> Result<(), StorageError> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            "INSERT INTO notified (offer_id, notified_at, content_hash, is_deal) VALUES (?1, ?2, ?3, 1)
+             ON CONFLICT(offer_id) DO UPDATE SET
+                notified_at = excluded.notified_at,
+                content_hash = excluded.content_hash,
+                is_deal = 1",
+            params![offer_id, Utc::now().to_rfc3339(), content_hash],
+        )?;
+        Ok(())
+    }
+
+    /// Возвращает id другого оффера с тем же отпечатком, уведомление о котором было после `since`
+    pub fn find_notified_by_hash(
+        &self,
+        content_hash: &str,
+        exclude_offer_id: &str,
+        since: DateTime<Utc>,
+    ) -> Result<Option<String>, StorageError> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT offer_id FROM notified
+             WHERE content_hash = ?1 AND offer_id != ?2 AND notified_at >= ?3
+             ORDER BY notified_at DESC LIMIT 1",
+        )?;
+        let mut rows = stmt.query(params![content_hash, exclude_offer_id, since.to_rfc3339()])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(row.get(0)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Возвращает сохранённый dHash миниатюры оффера
+    pub fn get_image_hash(&self, offer_id: &str) -> Result<Option<u64>, StorageError> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare("SELECT image_hash FROM offers WHERE id = ?1")?;
+        let mut rows = stmt.query(params![offer_id])?;
+        match rows.next()? {
+            Some(row) => Ok(row.get::<_, Option<i64>>(0)?.map(|h| h as u64)),
+            None => Ok(None),
+        }
+    }
+
+    /// Сохраняет dHash миниатюры оффера (в SQLite хранится как знаковое 64-битное число)
+    pub fn set_image_hash(&self, offer_id: &str, hash: u64) -> Result<(), StorageError> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            "UPDATE offers SET image_hash = ?2 WHERE id = ?1",
+            params![offer_id, hash as i64],
+        )?;
+        Ok(())
+    }
+
+    /// Возвращает id другого оффера модели с почти той же миниатюрой (не более `max_distance`
+    /// отличающихся бит), уведомление о котором было после `since`
+    pub fn find_notified_by_image_hash(
+        &self,
+        model: &str,
+        hash: u64,
+        max_distance: u32,
+        exclude_offer_id: &str,
+        since: DateTime<Utc>,
+    ) -> Result<Option<String>, StorageError> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT o.id, o.image_hash FROM offers o
+             JOIN notified n ON n.offer_id = o.id
+             WHERE o.model = ?1 AND o.id != ?2 AND o.image_hash IS NOT NULL AND n.notified_at >= ?3
+             ORDER BY n.notified_at DESC",
+        )?;
+        let candidates = stmt
+            .query_map(params![model, exclude_offer_id, since.to_rfc3339()], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as u64))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        // SQLite не умеет считать биты, поэтому расстояние Хэмминга считаем здесь
+        Ok(candidates
+            .into_iter()
+            .find(|(_, other)| hamming_distance(hash, *other) <= max_distance)
+            .map(|(id, _)| id))
+    }
+
+    /// Возвращает id самого дешёвого оффера, о котором последний раз уведомили по модели
+    pub fn get_best_deal(&self, model: &str) -> Result<Option<String>, StorageError> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare("SELECT offer_id FROM best_deals WHERE model = ?1")?;
+        let mut rows = stmt.query(params![model])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(row.get(0)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Запоминает самый дешёвый оффер модели, о котором отправлено уведомление
+    pub fn set_best_deal(&self, model: &str, offer_id: &str) -> Result<(), StorageError> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            "INSERT OR REPLACE INTO best_deals (model, offer_id, updated_at) VALUES (?1, ?2, ?3)",
+            params![model, offer_id, Utc::now().to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    /// Возвращает значение служебного ключа из таблицы meta
+    pub fn get_meta(&self, key: &str) -> Result<Option<String>, StorageError> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare("SELECT value FROM meta WHERE key = ?1")?;
+        let mut rows = stmt.query(params![key])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(row.get(0)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Сохраняет (или перезаписывает) значение служебного ключа в таблице meta
+    pub fn set_meta(&self, key: &str, value: &str) -> Result<(), StorageError> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            "INSERT INTO meta (key, value) VALUES (?1, ?2)
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            params![key, value],
+        )?;
+        Ok(())
+    }
+
+    /// Записывает прогон сбора по модели: начало, длительность и число найденных офферов
+    pub fn record_scrape_run(
+        &self,
+        model: &str,
+        started_at: DateTime<Utc>,
+        duration_ms: u64,
+        offer_count: usize,
+    ) -> Result<(), StorageError> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            "INSERT INTO scrape_runs (model, started_at, duration_ms, offer_count) VALUES (?1, ?2, ?3, ?4)",
+            params![model, started_at.to_rfc3339(), duration_ms as i64, offer_count as i64],
+        )?;
+        Ok(())
+    }
+
+    /// Возвращает последние `limit` прогонов (опционально — только для указанной модели), новые первыми
+    pub fn get_recent_scrape_runs(&self, model: Option<&str>, limit: usize) -> Result<Vec<ScrapeRun>, StorageError> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT model, started_at, duration_ms, offer_count FROM scrape_runs
+             WHERE ?1 IS NULL OR model = ?1 COLLATE NOCASE
+             ORDER BY started_at DESC LIMIT ?2",
+        )?;
+        let rows = stmt.query_map(params![model, limit as i64], |row| {
+            let started_at_str: String = row.get(1)?;
+            let started_at = started_at_str.parse().map_err(|e| {
+                rusqlite::Error::FromSqlConversionFailure(1, rusqlite::types::Type::Text, Box::new(e))
+            })?;
+            Ok(ScrapeRun {
+                model: row.get(0)?,
+                started_at,
+                duration_ms: row.get::<_, i64>(2)? as u64,
+                offer_count: row.get::<_, i64>(3)? as usize,
+            })
+        })?;
+        let mut runs = Vec::new();
+        for row in rows {
+            runs.push(row?);
+        }
+        Ok(runs)
+    }
+
+    /// Включает или отключает обработку модели. Возвращает false, если состояние не изменилось
+    pub fn set_model_enabled(&self, model: &str, enabled: bool) -> Result<bool, StorageError> {
+        let conn = self.pool.get()?;
+        let changed = if enabled {
+            conn.execute("DELETE FROM disabled_models WHERE model = ?1", params![model])?
+        } else {
+            conn.execute(
+                "INSERT OR IGNORE INTO disabled_models (model, disabled_at) VALUES (?1, ?2)",
+                params![model, Utc::now().to_rfc3339()],
+            )?
+        };
+        Ok(changed > 0)
+    }
+
+    /// Возвращает модели, отключённые командой /disable
+    pub fn get_disabled_models(&self) -> Result<HashSet<String>, StorageError> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare("SELECT model FROM disabled_models")?;
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+        let mut models = HashSet::new();
+        for row in rows {
+            models.insert(row?);
+        }
+        Ok(models)
+    }
+
+    /// Добавляет оффер в игнор-лист. Возвращает false, если он уже там был
+    pub fn ignore_offer(&self, offer_id: &str) -> Result<bool, StorageError> {
+        let conn = self.pool.get()?;
+        let inserted = conn.execute(
+            "INSERT OR IGNORE INTO ignored_offers (offer_id, ignored_at) VALUES (?1, ?2)",
+            params![offer_id, Utc::now().to_rfc3339()],
+        )?;
+        Ok(inserted > 0)
+    }
+
+    /// Убирает оффер из игнор-листа. Возвращает false, если его там не было
+    pub fn unignore_offer(&self, offer_id: &str) -> Result<bool, StorageError> {
+        let conn = self.pool.get()?;
+        let deleted = conn.execute("DELETE FROM ignored_offers WHERE offer_id = ?1", params![offer_id])?;
+        Ok(deleted > 0)
+    }
+
+    /// Возвращает id всех офферов из игнор-листа
+    pub fn get_ignored_ids(&self) -> Result<HashSet<String>, StorageError> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare("SELECT offer_id FROM ignored_offers")?;
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+        let mut ids = HashSet::new();
+        for row in rows {
+            ids.insert(row?);
+        }
+        Ok(ids)
+    }
+
+    /// Подписывает чат на уведомления по модели. Возвращает false, если подписка уже была
+    pub fn subscribe(&self, chat_id: i64, model: &str) -> Result<bool, StorageError> {
+        let conn = self.pool.get()?;
+        let inserted = conn.execute(
+            "INSERT OR IGNORE INTO subscriptions (chat_id, model) VALUES (?1, ?2)",
+            params![chat_id, model],
+        )?;
+        Ok(inserted > 0)
+    }
+
+    /// Отписывает чат от модели. Возвращает false, если подписки не было
+    pub fn unsubscribe(&self, chat_id: i64, model: &str) -> Result<bool, StorageError> {
+        let conn = self.pool.get()?;
+        let deleted = conn.execute(
+            "DELETE FROM subscriptions WHERE chat_id = ?1 AND model = ?2 COLLATE NOCASE",
+            params![chat_id, model],
+        )?;
+        Ok(deleted > 0)
+    }
+
+    /// Возвращает чаты, подписанные на указанную модель
+    pub fn get_subscribers(&self, model: &str) -> Result<Vec<i64>, StorageError> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT chat_id FROM subscriptions WHERE model = ?1 COLLATE NOCASE ORDER BY chat_id",
+        )?;
+        let rows = stmt.query_map(params![model], |row| row.get::<_, i64>(0))?;
+        let mut chats = Vec::new();
+        for row in rows {
+            chats.push(row?);
+        }
+        Ok(chats)
+    }
+
     /// Получает статистику для указанной модели, если она существует
     pub fn get_stats(&self, model: &str) -> Result<Option<ModelStats>, StorageError> {
-        let mut stmt = self.conn.prepare(
-            "SELECT avg_price, std_dev, last_updated FROM model_stats WHERE model = ?1",
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT avg_price, std_dev, last_updated, median_price, p25, p75 FROM model_stats WHERE model = ?1",
         )?;
 
         let mut rows = stmt.query(params![model])?;
@@ -220,6 +809,9 @@ impl SqliteStorage {
                 model: model.to_string(),
                 avg_price,
                 std_dev,
+                median_price: row.get(3)?,
+                p25: row.get(4)?,
+                p75: row.get(5)?,
                 last_updated,
             }))
         } else {
@@ -229,28 +821,34 @@ impl SqliteStorage {
 
     /// Обновляет статистику для модели
     pub fn update_stats(&self, stats: &ModelStats) -> Result<(), StorageError> {
-        self.conn.execute(
-            "INSERT OR REPLACE INTO model_stats (model, avg_price, std_dev, last_updated)
-             VALUES (?1, ?2, ?3, ?4)",
+        let conn = self.pool.get()?;
+        conn.execute(
+            "INSERT OR REPLACE INTO model_stats (model, avg_price, std_dev, last_updated, median_price, p25, p75)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
             params![
                 &stats.model,
                 &stats.avg_price,
                 &stats.std_dev,
                 &stats.last_updated.to_rfc3339(),
+                &stats.median_price,
+                &stats.p25,
+                &stats.p75,
             ],
         )?;
         Ok(())
     }
 
-    /// Возвращает последний по времени оффер
-    pub fn get_last_offer(&self) -> Result<Option<Offer>, StorageError> {
-        let mut stmt = self.conn.prepare(
+    /// Возвращает последний по времени оффер (опционально — только для указанной модели)
+    pub fn get_last_offer(&self, model: Option<&str>) -> Result<Option<Offer>, StorageError> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
             "SELECT id, title, price, model, link, posted_at, fetched_at, location, description,
-                    user_id, user_name, user_url
-             FROM offers ORDER BY fetched_at DESC LIMIT 1",
+                    user_id, user_name, user_url, negotiable, price_kind, image_url, shipping_available
+             FROM offers WHERE disappeared_at IS NULL AND (?1 IS NULL OR model = ?1 COLLATE NOCASE)
+             ORDER BY fetched_at DESC LIMIT 1",
         )?;
 
-        let mut rows = stmt.query([])?;
+        let mut rows = stmt.query(params![model])?;
         if let Some(row) = rows.next()? {
             let offer = Self::map_offer(row, true)?;
             Ok(Some(offer))
@@ -259,15 +857,69 @@ impl SqliteStorage {
         }
     }
 
-    /// Получает 5 офферов с минимальной положительной ценой
-    pub fn get_top5_offers(&self) -> Result<Vec<Offer>, StorageError> {
-        let mut stmt = self.conn.prepare(
+    /// Возвращает офферы модели, впервые замеченные в интервале [from, to)
+    pub fn get_offers_first_seen_between(
+        &self,
+        model: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<Offer>, StorageError> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
             "SELECT id, title, price, model, link, posted_at, fetched_at, location, description,
-                    user_id, user_name, user_url
-             FROM offers WHERE price > 0 ORDER BY price ASC LIMIT 5",
+                    user_id, user_name, user_url, negotiable, price_kind, image_url, shipping_available
+             FROM offers WHERE model = ?1 AND first_seen_at >= ?2 AND first_seen_at < ?3
+             ORDER BY price ASC",
         )?;
 
-        let rows = stmt.query_map([], |row| Self::map_offer(row, true))?;
+        let rows = stmt.query_map(params![model, from.to_rfc3339(), to.to_rfc3339()], |row| {
+            Self::map_offer(row, true)
+        })?;
+        let mut offers = Vec::new();
+        for offer in rows {
+            offers.push(offer?);
+        }
+        Ok(offers)
+    }
+
+    /// Считает сделки модели (см. `mark_deal_notified`), уведомления о которых были в интервале [from, to)
+    pub fn count_deals_between(
+        &self,
+        model: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<usize, StorageError> {
+        let conn = self.pool.get()?;
+        let count: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM notified n JOIN offers o ON o.id = n.offer_id
+             WHERE o.model = ?1 AND n.is_deal = 1 AND n.notified_at >= ?2 AND n.notified_at < ?3",
+            params![model, from.to_rfc3339(), to.to_rfc3339()],
+            |row| row.get(0),
+        )?;
+        Ok(count as usize)
+    }
+
+    /// Возвращает страницу активных офферов с положительной ценой (опционально — только для модели),
+    /// отсортированных по `sort`
+    pub fn get_offers(
+        &self,
+        model: Option<&str>,
+        sort: SortKey,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<Offer>, StorageError> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT id, title, price, model, link, posted_at, fetched_at, location, description,
+                    user_id, user_name, user_url, negotiable, price_kind, image_url, shipping_available
+             FROM offers WHERE price > 0 AND disappeared_at IS NULL AND (?1 IS NULL OR model = ?1 COLLATE NOCASE)
+             ORDER BY {} LIMIT ?2 OFFSET ?3",
+            sort.order_by()
+        ))?;
+
+        let rows = stmt.query_map(params![model, limit as i64, offset as i64], |row| {
+            Self::map_offer(row, true)
+        })?;
         let mut offers = Vec::new();
         for offer in rows {
             offers.push(offer?);
@@ -276,11 +928,12 @@ impl SqliteStorage {
         Ok(offers)
     }
 
-    /// Получает все офферы
+    /// Получает все офферы, включая исчезнувшие с площадки
     pub fn get_all_offers(&self) -> Result<Vec<Offer>, StorageError> {
-        let mut stmt = self.conn.prepare(
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
             "SELECT id, title, price, model, link, posted_at, fetched_at, location, description,
-                    user_id, user_name, user_url
+                    user_id, user_name, user_url, negotiable, price_kind, image_url, shipping_available
              FROM offers",
         )?;
 
@@ -293,13 +946,73 @@ impl SqliteStorage {
         Ok(offers)
     }
 
-    /// Возвращает список (модель, средняя цена) для статистики
-    pub fn get_average_prices(&self) -> Result<Vec<(String, f64)>, StorageError> {
-        let mut stmt = self.conn.prepare(
-            "SELECT model, avg_price FROM model_stats ORDER BY model ASC",
+    /// Выгружает все офферы (включая исчезнувшие) в CSV или JSON
+    pub fn export_offers(&self, format: ExportFormat) -> Result<String, StorageError> {
+        let offers = self.get_all_offers()?;
+        match format {
+            ExportFormat::Csv => {
+                let mut out = String::from(
+                    "id,title,price,price_kind,negotiable,model,location,link,posted_at,fetched_at,user_id,user_name,user_url,image_url,shipping_available,description\n",
+                );
+                for offer in &offers {
+                    let fields = [
+                        offer.id.clone(),
+                        offer.title.clone(),
+                        format!("{:.2}", offer.price),
+                        offer.price_kind.as_str().to_string(),
+                        offer.negotiable.to_string(),
+                        offer.model.clone(),
+                        offer.location.clone(),
+                        offer.link.clone(),
+                        offer.posted_at.to_rfc3339(),
+                        offer.fetched_at.to_rfc3339(),
+                        offer.user_id.clone().unwrap_or_default(),
+                        offer.user_name.clone().unwrap_or_default(),
+                        offer.user_url.clone().unwrap_or_default(),
+                        offer.image_url.clone().unwrap_or_default(),
+                        offer.shipping_available.to_string(),
+                        offer.description.clone(),
+                    ];
+                    let line: Vec<String> = fields.iter().map(|f| csv_escape(f)).collect();
+                    out.push_str(&line.join(","));
+                    out.push('\n');
+                }
+                Ok(out)
+            }
+            ExportFormat::Json => {
+                serde_json::to_string_pretty(&offers)
+                    .map_err(|e| StorageError::DatabaseError(format!("JSON export failed: {}", e)))
+            }
+        }
+    }
+
+    /// Получает офферы модели, которые всё ещё присутствуют на площадке (по индексу idx_offers_model)
+    pub fn get_offers_for_model(&self, model: &str) -> Result<Vec<Offer>, StorageError> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, title, price, model, link, posted_at, fetched_at, location, description,
+                    user_id, user_name, user_url, negotiable, price_kind, image_url, shipping_available
+             FROM offers WHERE model = ?1 AND disappeared_at IS NULL",
+        )?;
+
+        let rows = stmt.query_map(params![model], |row| Self::map_offer(row, true))?;
+        let mut offers = Vec::new();
+        for offer in rows {
+            offers.push(offer?);
+        }
+
+        Ok(offers)
+    }
+
+    /// Возвращает список (модель, средняя цена) для статистики (опционально — только для указанной модели)
+    pub fn get_average_prices(&self, model: Option<&str>) -> Result<Vec<(String, f64)>, StorageError> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT model, avg_price FROM model_stats
+             WHERE ?1 IS NULL OR model = ?1 COLLATE NOCASE ORDER BY model ASC",
         )?;
 
-        let rows = stmt.query_map([], |row| {
+        let rows = stmt.query_map(params![model], |row| {
             let model: String = row.get(0)?;
             let avg_price: f64 = row.get(1)?;
             Ok((model, avg_price))
@@ -313,8 +1026,48 @@ impl SqliteStorage {
         Ok(results)
     }
 
+    /// Сопоставляет активные офферы каждой модели с её сохранённой статистикой
+    pub fn get_market_snapshots(&self) -> Result<Vec<MarketSnapshot>, StorageError> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT s.model, s.avg_price, AVG(o.price), MIN(o.price), COUNT(o.id)
+             FROM model_stats s
+             JOIN offers o ON o.model = s.model AND o.disappeared_at IS NULL AND o.price > 0
+             GROUP BY s.model, s.avg_price",
+        )?;
+
+        let rows = stmt.query_map([], |row| {
+            Ok(MarketSnapshot {
+                model: row.get(0)?,
+                stats_avg_price: row.get(1)?,
+                current_avg_price: row.get(2)?,
+                cheapest_price: row.get(3)?,
+                offer_count: row.get::<_, i64>(4)? as usize,
+            })
+        })?;
+
+        let mut snapshots = Vec::new();
+        for row in rows {
+            snapshots.push(row?);
+        }
+        Ok(snapshots)
+    }
+
+    /// Приватная функция для маппинга строки истории цен в PriceObservation.
+    fn map_observation(row: &Row) -> Result<PriceObservation, rusqlite::Error> {
+        let observed_at_str: String = row.get(2)?;
+        let observed_at = observed_at_str.parse().map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(2, rusqlite::types::Type::Text, Box::new(e))
+        })?;
+        Ok(PriceObservation {
+            offer_id: row.get(0)?,
+            price: row.get(1)?,
+            observed_at,
+        })
+    }
+
     /// Приватная функция для маппинга строки результата в структуру Offer.
-    /// Если параметр `full` равен true, ожидается, что в строке присутствуют поля user_id, user_name и user_url.
+    /// Если параметр `full` равен true, ожидается, что в строке присутствуют поля user_id, user_name, user_url, negotiable, price_kind, image_url и shipping_available.
     fn map_offer(row: &Row, full: bool) -> Result<Offer, rusqlite::Error> {
         let posted_at_str: String = row.get(5)?;
         let fetched_at_str: String = row.get(6)?;
@@ -325,10 +1078,15 @@ impl SqliteStorage {
             rusqlite::Error::FromSqlConversionFailure(6, rusqlite::types::Type::Text, Box::new(e))
         })?;
 
-        let (user_id, user_name, user_url) = if full {
-            (row.get(9)?, row.get(10)?, row.get(11)?)
+        let (user_id, user_name, user_url, negotiable) = if full {
+            (row.get(9)?, row.get(10)?, row.get(11)?, row.get(12)?)
+        } else {
+            (None, None, None, false)
+        };
+        let (price_kind, image_url, shipping_available) = if full {
+            (PriceKind::from_str(&row.get::<_, String>(13)?), row.get(14)?, row.get(15)?)
         } else {
-            (None, None, None)
+            (PriceKind::Fixed, None, false)
         };
 
         Ok(Offer {
@@ -344,6 +1102,356 @@ impl SqliteStorage {
             user_id,
             user_name,
             user_url,
+            negotiable,
+            price_kind,
+            image_url,
+            shipping_available,
         })
     }
 }
+
+/// Экранирует поле CSV: поля с запятыми, кавычками или переводами строк берутся в кавычки.
+fn csv_escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{offer, TempStorage};
+
+    fn ids(offers: &[Offer]) -> Vec<String> {
+        let mut ids: Vec<String> = offers.iter().map(|o| o.id.clone()).collect();
+        ids.sort();
+        ids
+    }
+
+    fn save_listings(storage: &SqliteStorage) {
+        let listings = [
+            ("o1", "iPhone 13 Pro 128GB", "Akku 90%, mit Hülle"),
+            ("o2", "Samsung Galaxy S21", "Tausch gegen iPhone möglich"),
+            ("o3", "Pixel 7", "kaum benutzt"),
+        ];
+        for (id, title, description) in listings {
+            let mut offer = offer(id, title, 500.0);
+            offer.description = description.to_string();
+            storage.save_offer(&offer).unwrap();
+        }
+    }
+
+    #[test]
+    fn search_finds_words_in_titles_and_descriptions() {
+        let storage = TempStorage::new();
+        assert!(storage.fts_enabled);
+        save_listings(&storage);
+
+        assert_eq!(ids(&storage.search_offers("iphone", 10).unwrap()), vec!["o1", "o2"]);
+        assert_eq!(ids(&storage.search_offers("iPhone 128GB", 10).unwrap()), vec!["o1"]);
+        assert_eq!(ids(&storage.search_offers("hülle", 10).unwrap()), vec!["o1"]);
+        assert_eq!(storage.search_offers("iphone", 1).unwrap().len(), 1);
+        assert!(storage.search_offers("nokia", 10).unwrap().is_empty());
+        assert!(storage.search_offers("  ", 10).unwrap().is_empty());
+        // Кавычки и операторы FTS в запросе — просто слова
+        assert!(storage.search_offers("\"iphone OR", 10).is_ok());
+    }
+
+    #[test]
+    fn search_index_follows_updated_offers() {
+        let storage = TempStorage::new();
+        save_listings(&storage);
+        storage.save_offer(&offer("o3", "Pixel 7 Pro", 450.0)).unwrap();
+
+        assert_eq!(ids(&storage.search_offers("pixel", 10).unwrap()), vec!["o3"]);
+        assert!(storage.search_offers("benutzt", 10).unwrap().is_empty());
+        assert_eq!(storage.search_offers("pro", 10).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn search_falls_back_to_like_without_fts() {
+        let storage = TempStorage::new();
+        save_listings(&storage);
+        let fallback = SqliteStorage {
+            pool: ConnectionPool::new(&storage.path).unwrap(),
+            fts_enabled: false,
+        };
+
+        assert_eq!(ids(&fallback.search_offers("iphone", 10).unwrap()), vec!["o1", "o2"]);
+        assert_eq!(ids(&fallback.search_offers("Galaxy S21", 10).unwrap()), vec!["o2"]);
+        assert!(fallback.search_offers("nokia", 10).unwrap().is_empty());
+    }
+
+    fn count(storage: &SqliteStorage, table: &str) -> i64 {
+        let conn = storage.pool.get().unwrap();
+        conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn purge_removes_only_stale_offers() {
+        let storage = TempStorage::new();
+        let mut stale = offer("stale", "iPhone 13 alt", 400.0);
+        stale.fetched_at = Utc::now() - Duration::days(40);
+        storage.save_offer(&stale).unwrap();
+        storage.save_offer(&offer("fresh", "iPhone 13 neu", 500.0)).unwrap();
+        // Давно исчезнувший оффер удаляется, даже если его fetched_at свежий
+        storage.save_offer(&offer("gone", "iPhone 13 weg", 450.0)).unwrap();
+        storage
+            .pool
+            .get()
+            .unwrap()
+            .execute(
+                "UPDATE offers SET disappeared_at = ?1 WHERE id = 'gone'",
+                params![(Utc::now() - Duration::days(31)).to_rfc3339()],
+            )
+            .unwrap();
+
+        assert_eq!(storage.purge_offers_older_than(30).unwrap(), 2);
+
+        assert_eq!(ids(&storage.get_all_offers().unwrap()), vec!["fresh"]);
+        assert_eq!(count(&storage, "offer_price_history"), 1);
+        assert_eq!(ids(&storage.search_offers("iphone", 10).unwrap()), vec!["fresh"]);
+        assert_eq!(storage.purge_offers_older_than(30).unwrap(), 0);
+    }
+
+    #[test]
+    fn purge_removes_old_and_orphaned_notifications() {
+        let storage = TempStorage::new();
+        storage.save_offer(&offer("old", "iPhone 13", 400.0)).unwrap();
+        storage.save_offer(&offer("recent", "iPhone 13", 500.0)).unwrap();
+        for id in ["old", "recent", "orphan"] {
+            storage.mark_notified(id).unwrap();
+        }
+        storage
+            .pool
+            .get()
+            .unwrap()
+            .execute(
+                "UPDATE notified SET notified_at = ?1 WHERE offer_id = 'old'",
+                params![(Utc::now() - Duration::days(10)).to_rfc3339()],
+            )
+            .unwrap();
+
+        assert_eq!(storage.purge_notified_older_than(7).unwrap(), 2);
+
+        assert!(storage.is_notified("recent").unwrap());
+        assert!(!storage.is_notified("old").unwrap());
+        assert!(!storage.is_notified("orphan").unwrap());
+        assert_eq!(count(&storage, "offers"), 2);
+    }
+
+    fn listed(id: &str, title: &str, seller: Option<&str>, price: f64, days_ago: i64) -> Offer {
+        let mut offer = offer(id, title, price);
+        offer.user_id = seller.map(str::to_string);
+        offer.posted_at = Utc::now() - Duration::days(days_ago);
+        offer
+    }
+
+    #[test]
+    fn reposts_map_onto_the_earliest_notified_offer() {
+        let storage = TempStorage::new();
+        let offers = [
+            // Раньше оригинала, но без уведомления — не оригинал и не репост
+            listed("unnotified", "iPhone 13", Some("s1"), 500.0, 6),
+            listed("first", "iPhone 13", Some("s1"), 500.0, 5),
+            listed("repost", "iPhone 13", Some("s1"), 495.0, 2),
+            listed("again", "iPhone 13", Some("s1"), 505.0, 1),
+            // Ровно на границе окна — ещё репост
+            listed("edge", "iPhone 13", Some("s1"), 510.0, 1),
+            // Цена вне окна, другой продавец, другой заголовок или продавец неизвестен — не репост
+            listed("pricier", "iPhone 13", Some("s1"), 520.0, 1),
+            listed("other-seller", "iPhone 13", Some("s2"), 500.0, 1),
+            listed("other-title", "iPhone 13 Pro", Some("s1"), 500.0, 1),
+            listed("anonymous", "iPhone 13", None, 500.0, 1),
+        ];
+        for offer in &offers {
+            storage.save_offer(offer).unwrap();
+        }
+        storage.mark_notified("first").unwrap();
+        let since = Utc::now() - Duration::hours(24);
+
+        let reposts = storage.find_probable_reposts_for_model("test", 10.0, since).unwrap();
+        let mut pairs: Vec<(&str, &str)> = reposts.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+        pairs.sort();
+        assert_eq!(pairs, vec![("again", "first"), ("edge", "first"), ("repost", "first")]);
+
+        // Окно настраивается: при 25 € в него попадает и более дорогой оффер, при 1 € — никто
+        assert_eq!(storage.find_probable_reposts_for_model("test", 25.0, since).unwrap().len(), 4);
+        assert!(storage.find_probable_reposts_for_model("test", 1.0, since).unwrap().is_empty());
+        assert!(storage.find_probable_reposts_for_model("other", 10.0, since).unwrap().is_empty());
+    }
+
+    #[test]
+    fn an_original_notified_long_ago_does_not_make_reposts() {
+        let storage = TempStorage::new();
+        storage.save_offer(&listed("first", "iPhone 13", Some("s1"), 500.0, 30)).unwrap();
+        storage.save_offer(&listed("repost", "iPhone 13", Some("s1"), 500.0, 1)).unwrap();
+        storage.mark_notified("first").unwrap();
+        storage
+            .pool
+            .get()
+            .unwrap()
+            .execute(
+                "UPDATE notified SET notified_at = ?1 WHERE offer_id = 'first'",
+                params![(Utc::now() - Duration::days(10)).to_rfc3339()],
+            )
+            .unwrap();
+
+        let since = Utc::now() - Duration::hours(24);
+        assert!(storage.find_probable_reposts_for_model("test", 10.0, since).unwrap().is_empty());
+    }
+
+    fn notified_at(storage: &SqliteStorage, offer_id: &str) -> String {
+        let conn = storage.pool.get().unwrap();
+        conn.query_row("SELECT notified_at FROM notified WHERE offer_id = ?1", params![offer_id], |row| row.get(0))
+            .unwrap()
+    }
+
+    fn set_notified_at(storage: &SqliteStorage, offer_id: &str, value: &str) {
+        let conn = storage.pool.get().unwrap();
+        conn.execute("UPDATE notified SET notified_at = ?2 WHERE offer_id = ?1", params![offer_id, value])
+            .unwrap();
+    }
+
+    #[test]
+    fn notified_timestamps_round_trip_as_rfc3339() {
+        let storage = TempStorage::new();
+        assert!(storage.should_notify("o1").unwrap());
+
+        let before = Utc::now();
+        storage.mark_notified("o1").unwrap();
+        let stored = DateTime::parse_from_rfc3339(&notified_at(&storage, "o1")).unwrap();
+        assert!(stored >= before && stored <= Utc::now());
+        assert!(!storage.should_notify("o1").unwrap());
+
+        set_notified_at(&storage, "o1", &(Utc::now() - Duration::hours(25)).to_rfc3339());
+        assert!(storage.should_notify("o1").unwrap());
+        set_notified_at(&storage, "o1", &(Utc::now() - Duration::hours(23)).to_rfc3339());
+        assert!(!storage.should_notify("o1").unwrap());
+    }
+
+    #[test]
+    fn legacy_sqlite_timestamps_are_migrated_on_open() {
+        let storage = TempStorage::new();
+        storage.mark_notified("old").unwrap();
+        storage.mark_notified("recent").unwrap();
+        // Старый формат datetime('now')
+        set_notified_at(&storage, "old", "2024-03-05 10:20:30");
+        let recent = (Utc::now() - Duration::hours(1)).format("%Y-%m-%d %H:%M:%S").to_string();
+        set_notified_at(&storage, "recent", &recent);
+
+        let reopened = SqliteStorage::new(&storage.path).unwrap();
+        assert_eq!(notified_at(&reopened, "old"), "2024-03-05T10:20:30+00:00");
+        assert!(reopened.should_notify("old").unwrap());
+        assert!(!reopened.should_notify("recent").unwrap());
+    }
+
+    #[test]
+    fn only_deal_notifications_are_counted_as_deals() {
+        let storage = TempStorage::new();
+        for id in ["deal", "repost", "seen", "cheapest"] {
+            storage.save_offer(&offer(id, "iPhone 13", 400.0)).unwrap();
+        }
+        storage.mark_deal_notified("deal", "h1").unwrap();
+        // Подавленный репост, кнопка «Просмотрено» и самый дешёвый оффер — не сделки
+        storage.mark_notified("repost").unwrap();
+        storage.mark_notified("seen").unwrap();
+        storage.mark_notified_with_hash("cheapest", Some("h2")).unwrap();
+        // Повторная отметка сделки как просмотренной её не обнуляет
+        storage.mark_notified("deal").unwrap();
+
+        let (from, to) = (Utc::now() - Duration::hours(1), Utc::now() + Duration::hours(1));
+        assert_eq!(storage.count_deals_between("test", from, to).unwrap(), 1);
+        assert_eq!(storage.count_deals_between("other", from, to).unwrap(), 0);
+        assert_eq!(storage.count_deals_between("test", to, to + Duration::hours(1)).unwrap(), 0);
+    }
+
+    fn first_and_last_seen(storage: &SqliteStorage, offer_id: &str) -> (String, String) {
+        let conn = storage.pool.get().unwrap();
+        conn.query_row(
+            "SELECT first_seen_at, fetched_at FROM offers WHERE id = ?1",
+            params![offer_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn saving_again_keeps_the_first_seen_time() {
+        let storage = TempStorage::new();
+        let first_seen = Utc::now() - Duration::days(3);
+        let mut listing = offer("o1", "iPhone 13", 500.0);
+        listing.fetched_at = first_seen;
+        assert_eq!(storage.save_offer(&listing).unwrap(), None);
+
+        let seen_again = Utc::now();
+        listing.fetched_at = seen_again;
+        listing.price = 450.0;
+        assert_eq!(storage.save_offer(&listing).unwrap(), Some(500.0));
+        assert_eq!(first_and_last_seen(&storage, "o1"), (first_seen.to_rfc3339(), seen_again.to_rfc3339()));
+
+        // Оффер считается новым только в интервале первого появления
+        let day = Duration::days(1);
+        let seen_around = |at: DateTime<Utc>| ids(&storage.get_offers_first_seen_between("test", at - day, at + day).unwrap());
+        assert_eq!(seen_around(first_seen), vec!["o1"]);
+        assert!(seen_around(seen_again).is_empty());
+
+        // Более ранний fetched_at (например, импорт старой страницы) сдвигает first_seen_at назад
+        listing.fetched_at = first_seen - day;
+        storage.save_offer(&listing).unwrap();
+        assert_eq!(first_and_last_seen(&storage, "o1").0, (first_seen - day).to_rfc3339());
+    }
+
+    fn page(storage: &SqliteStorage, model: Option<&str>, sort: SortKey, limit: usize, offset: usize) -> Vec<String> {
+        storage.get_offers(model, sort, limit, offset).unwrap().into_iter().map(|o| o.id).collect()
+    }
+
+    #[test]
+    fn offers_are_sorted_and_paged() {
+        let storage = TempStorage::new();
+        let now = Utc::now();
+        for (id, price, hours_ago) in [("a", 300.0, 3), ("b", 500.0, 1), ("c", 300.0, 2), ("d", 400.0, 4)] {
+            let mut listing = offer(id, "iPhone 13", price);
+            listing.fetched_at = now - Duration::hours(hours_ago);
+            storage.save_offer(&listing).unwrap();
+        }
+        // Бесплатные, исчезнувшие и офферы другой модели не попадают в выборку
+        storage.save_offer(&offer("free", "iPhone 13", 0.0)).unwrap();
+        let mut other = offer("other", "Pixel 7", 100.0);
+        other.model = "pixel".to_string();
+        storage.save_offer(&other).unwrap();
+        storage.mark_missing_offers_for_model("pixel", &[]).unwrap();
+
+        // При равной цене порядок задаёт id
+        assert_eq!(page(&storage, None, SortKey::PriceAsc, 10, 0), vec!["a", "c", "d", "b"]);
+        assert_eq!(page(&storage, None, SortKey::PriceDesc, 10, 0), vec!["b", "d", "a", "c"]);
+        assert_eq!(page(&storage, Some("TEST"), SortKey::FetchedAtDesc, 10, 0), vec!["b", "c", "a", "d"]);
+
+        assert_eq!(page(&storage, None, SortKey::PriceAsc, 2, 0), vec!["a", "c"]);
+        assert_eq!(page(&storage, None, SortKey::PriceAsc, 2, 2), vec!["d", "b"]);
+        assert_eq!(page(&storage, None, SortKey::PriceAsc, 2, 3), vec!["b"]);
+        assert!(page(&storage, None, SortKey::PriceAsc, 2, 4).is_empty());
+        assert!(page(&storage, None, SortKey::PriceAsc, 0, 0).is_empty());
+        assert!(page(&storage, Some("pixel"), SortKey::PriceAsc, 10, 0).is_empty());
+    }
+
+    #[test]
+    fn meta_values_are_set_overwritten_and_kept() {
+        let storage = TempStorage::new();
+        assert_eq!(storage.get_meta("missing").unwrap(), None);
+
+        storage.set_meta("paused", "1").unwrap();
+        storage.set_meta("mute_until", "0").unwrap();
+        assert_eq!(storage.get_meta("paused").unwrap().as_deref(), Some("1"));
+
+        storage.set_meta("paused", "0").unwrap();
+        assert_eq!(storage.get_meta("paused").unwrap().as_deref(), Some("0"));
+        assert_eq!(storage.get_meta("mute_until").unwrap().as_deref(), Some("0"));
+        assert_eq!(count(&storage, "meta"), 2);
+
+        let reopened = SqliteStorage::new(&storage.path).unwrap();
+        assert_eq!(reopened.get_meta("paused").unwrap().as_deref(), Some("0"));
+    }
+}
@@ -0,0 +1,365 @@
+// Shared fixtures for unit tests.
+use crate::config::{AppConfig, ModelConfig, SharedConfig};
+use crate::http_server::{self, HttpRequest, HttpResponse};
+use crate::notifier::{Notifier, TelegramNotifier};
+use crate::model::{Deal, ModelStats, NotifyError, Offer, PriceKind, ScrapeRequest};
+use crate::scraper::driver::{MarketplaceDriver, SearchFilters};
+use crate::scraper::kleinanzeigen::KleinanzeigenDriver;
+use crate::storage::SqliteStorage;
+use chrono::Utc;
+use scraper::Html;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+/// A fixed-price offer with only the fields most tests care about filled in.
+pub fn offer(id: &str, title: &str, price: f64) -> Offer {
+    let now = Utc::now();
+    Offer {
+        id: id.to_string(),
+        title: title.to_string(),
+        description: String::new(),
+        price,
+        location: "10115 Berlin".to_string(),
+        model: "test".to_string(),
+        link: format!("https://www.kleinanzeigen.de/s-anzeige/{}", id),
+        posted_at: now,
+        fetched_at: now,
+        user_id: None,
+        user_name: None,
+        user_url: None,
+        negotiable: false,
+        price_kind: PriceKind::Fixed,
+        image_url: None,
+        shipping_available: false,
+    }
+}
+
+/// Offers for `model` at the given prices, with ids "o0", "o1", ...
+pub fn offers_at(model: &str, prices: &[f64]) -> Vec<Offer> {
+    prices
+        .iter()
+        .enumerate()
+        .map(|(i, &price)| {
+            let mut offer = offer(&format!("o{}", i), model, price);
+            offer.model = model.to_string();
+            offer
+        })
+        .collect()
+}
+
+/// Stats for `model` with the given average and spread; median and quartiles equal the average.
+pub fn model_stats(model: &str, avg_price: f64, std_dev: f64) -> ModelStats {
+    ModelStats {
+        model: model.to_string(),
+        avg_price,
+        std_dev,
+        median_price: avg_price,
+        p25: avg_price,
+        p75: avg_price,
+        last_updated: Utc::now(),
+    }
+}
+
+/// A config with the given models and defaults everywhere else.
+pub fn app_config(models: Vec<ModelConfig>) -> AppConfig {
+    let mut config: AppConfig = serde_json::from_value(serde_json::json!({
+        "telegram_bot_token": "123:test",
+        "telegram_chat_id": 1,
+        "models": [],
+        "check_interval_seconds": 60,
+    }))
+    .expect("test config is valid");
+    config.models = models;
+    config
+}
+
+/// A fresh path in the temp directory ending in `extension`.
+pub fn temp_path(extension: &str) -> String {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    std::env::temp_dir()
+        .join(format!(
+            "klein-sniper-test-{}-{}.{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed),
+            extension
+        ))
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// A temp file path whose file (and SQLite's `-wal`/`-shm` files next to it) is removed on drop.
+pub struct TempFile(pub String);
+
+impl TempFile {
+    pub fn new(extension: &str) -> Self {
+        Self(temp_path(extension))
+    }
+
+    pub fn path(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", self.0, suffix));
+        }
+    }
+}
+
+/// A storage on a fresh temp-file database (the pool can't share `:memory:`), removed on drop.
+pub struct TempStorage {
+    pub path: String,
+    pub storage: Arc<SqliteStorage>,
+}
+
+impl TempStorage {
+    pub fn new() -> Self {
+        let path = temp_path("db");
+        let storage = Arc::new(SqliteStorage::new(&path).expect("temp storage opens"));
+        Self { path, storage }
+    }
+}
+
+impl std::ops::Deref for TempStorage {
+    type Target = SqliteStorage;
+
+    fn deref(&self) -> &SqliteStorage {
+        &self.storage
+    }
+}
+
+impl Drop for TempStorage {
+    fn drop(&mut self) {
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", self.path, suffix));
+        }
+    }
+}
+
+/// A Telegram notifier for the configured chats of `config`, backed by `storage`.
+pub fn telegram_notifier(storage: &TempStorage, config: AppConfig) -> TelegramNotifier {
+    telegram_notifier_with(storage, Arc::new(SharedConfig::new("config.json", config)))
+}
+
+/// A Telegram notifier for the current `config`, backed by `storage`.
+pub fn telegram_notifier_with(storage: &TempStorage, config: Arc<SharedConfig>) -> TelegramNotifier {
+    let current = config.load();
+    TelegramNotifier::new(
+        current.telegram_bot_token.clone(),
+        storage.storage.clone(),
+        config,
+        Arc::new(tokio::sync::Notify::new()),
+    )
+}
+
+/// Contents of a config file that passes `validate`, with one "iphone 13" model and extra top-level `fields`.
+pub fn config_file(fields: serde_json::Value) -> String {
+    let mut config = serde_json::json!({
+        "telegram_bot_token": "123:test",
+        "telegram_chat_id": 1,
+        "check_interval_seconds": 60,
+        "models": [{
+            "query": "iphone 13",
+            "category_id": "k0c173",
+            "deviation_threshold": 0.2,
+            "min_price_delta": 50.0,
+            "min_price": 100.0,
+            "max_price": 900.0,
+            "match_keywords": ["iphone 13"],
+        }],
+    });
+    if let (Some(config), Some(fields)) = (config.as_object_mut(), fields.as_object()) {
+        config.extend(fields.clone());
+    }
+    config.to_string()
+}
+
+/// One listing of a Kleinanzeigen search page; `html` renders it with the markup the parser expects.
+#[derive(Clone)]
+pub struct Listing {
+    pub id: String,
+    pub title: String,
+    /// Price text as shown on the site, e.g. "1.234 € VB".
+    pub price: String,
+    pub location: String,
+    pub description: String,
+    /// Date text, e.g. "Heute, 14:32"; no date element when empty.
+    pub date: String,
+    pub seller_id: Option<String>,
+    pub shipping: bool,
+}
+
+impl Listing {
+    pub fn new(id: &str, title: &str, price: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            title: title.to_string(),
+            price: price.to_string(),
+            location: "10115 Berlin".to_string(),
+            description: String::new(),
+            date: String::new(),
+            seller_id: None,
+            shipping: false,
+        }
+    }
+
+    pub fn html(&self) -> String {
+        let date = if self.date.is_empty() {
+            String::new()
+        } else {
+            format!(r#"<div class="aditem-main--top--right">{}</div>"#, self.date)
+        };
+        let seller = self.seller_id.as_deref().map_or(String::new(), |id| {
+            format!(r#"<a href="/s-bestandsliste.html?userId={}"><span class="ellipsis">Seller {}</span></a>"#, id, id)
+        });
+        let shipping = if self.shipping { "Versand möglich" } else { "Nur Abholung" };
+        format!(
+            r#"<li class="ad-listitem"><article class="aditem" data-adid="{id}">
+<div class="aditem-main--top--left">{location}</div>{date}
+<h2 class="text-module-begin"><a class="ellipsis" href="/s-anzeige/{slug}/{id}-173-3331">{title}</a></h2>
+<p class="aditem-main--middle--description">{description}</p>
+<p class="aditem-main--middle--price-shipping--price">{price}</p>
+<p class="aditem-main--middle--price-shipping--shipping">{shipping}</p>
+<div class="aditem-main--bottom">{seller}</div>
+</article></li>"#,
+            id = self.id,
+            location = self.location,
+            date = date,
+            slug = self.title.to_lowercase().replace(' ', "-"),
+            title = self.title,
+            description = self.description,
+            price = self.price,
+            shipping = shipping,
+            seller = seller,
+        )
+    }
+}
+
+/// A search result page with the given listings.
+pub fn search_page(listings: &[Listing]) -> String {
+    let items: String = listings.iter().map(Listing::html).collect();
+    format!(r#"<html><body><ul id="srchrslt-adtable">{}</ul></body></html>"#, items)
+}
+
+/// The Kleinanzeigen driver with search pages served from `base` ("{base}/seite:{page}").
+pub struct LocalDriver {
+    base: String,
+    site: KleinanzeigenDriver,
+}
+
+impl LocalDriver {
+    pub fn new(base: String) -> Self {
+        Self { base, site: KleinanzeigenDriver::new() }
+    }
+}
+
+impl MarketplaceDriver for LocalDriver {
+    fn name(&self) -> &'static str {
+        "local"
+    }
+
+    fn build_url(&self, _req: &ScrapeRequest, _filters: &SearchFilters, page: usize) -> String {
+        format!("{}/seite:{}", self.base, page)
+    }
+
+    fn count_items(&self, doc: &Html) -> usize {
+        self.site.count_items(doc)
+    }
+
+    fn next_page_url(&self, doc: &Html) -> Option<String> {
+        self.site.next_page_url(doc)
+    }
+
+    fn first_item_id(&self, doc: &Html) -> Option<String> {
+        self.site.first_item_id(doc)
+    }
+
+    fn is_blocked(&self, html: &str) -> bool {
+        self.site.is_blocked(html)
+    }
+}
+
+/// A notifier that records what it was asked to send as "kind:offer id" (or "text:<text>").
+#[derive(Default)]
+pub struct RecordingNotifier {
+    pub sent: Mutex<Vec<String>>,
+    pub paused: AtomicBool,
+    pub muted: AtomicBool,
+}
+
+impl RecordingNotifier {
+    pub fn sent(&self) -> Vec<String> {
+        self.sent.lock().unwrap().clone()
+    }
+
+    fn record(&self, entry: String) -> Result<(), NotifyError> {
+        if self.muted.load(Ordering::SeqCst) && !entry.starts_with("text:") {
+            return Err(NotifyError::Muted);
+        }
+        self.sent.lock().unwrap().push(entry);
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl Notifier for RecordingNotifier {
+    async fn notify(&self, offer: &Offer) -> Result<(), NotifyError> {
+        self.record(format!("offer:{}", offer.id))
+    }
+
+    async fn notify_text(&self, text: &str) -> Result<(), NotifyError> {
+        self.record(format!("text:{}", text))
+    }
+
+    async fn notify_deal(&self, deal: &Deal) -> Result<(), NotifyError> {
+        self.record(format!("deal:{}", deal.offer.id))
+    }
+
+    async fn notify_price_drop(&self, offer: &Offer, _previous_price: f64) -> Result<(), NotifyError> {
+        self.record(format!("price_drop:{}", offer.id))
+    }
+
+    async fn notify_watched_seller(&self, offer: &Offer) -> Result<(), NotifyError> {
+        self.record(format!("watched_seller:{}", offer.id))
+    }
+
+    fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    fn is_muted(&self) -> bool {
+        self.muted.load(Ordering::SeqCst)
+    }
+}
+
+/// Serves `handler` on a free local port in the background and returns its base URL, e.g. "http://127.0.0.1:40123".
+/// The server stops when the test's runtime shuts down.
+pub async fn spawn_http_server<F, Fut>(handler: F) -> String
+where
+    F: Fn(HttpRequest) -> Fut + Send + Sync + 'static,
+    Fut: std::future::Future<Output = HttpResponse> + Send + 'static,
+{
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.expect("bind a free port");
+    let addr = listener.local_addr().expect("bound address");
+    tokio::spawn(http_server::serve_listener(listener, handler));
+    format!("http://{}", addr)
+}
+
+/// Requests received by a [`scripted_server`], as (route, body).
+pub type RecordedRequests = Arc<Mutex<Vec<(String, String)>>>;
+
+/// Serves `responses` as (status, body) in order, repeating the last one, and records every request.
+pub async fn scripted_server(responses: Vec<(u16, &'static str)>) -> (String, RecordedRequests) {
+    let requests: RecordedRequests = Arc::default();
+    let recorded = requests.clone();
+    let base = spawn_http_server(move |request: HttpRequest| {
+        let mut requests = recorded.lock().unwrap();
+        requests.push((request.route().to_string(), String::from_utf8_lossy(&request.body).into_owned()));
+        let (status, body) = responses[(requests.len() - 1).min(responses.len() - 1)];
+        async move { HttpResponse::text(status, body) }
+    })
+    .await;
+    (base, requests)
+}